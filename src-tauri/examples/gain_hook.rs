@@ -0,0 +1,28 @@
+// Runs the player with a DSP hook that attenuates everything by 6 dB.
+
+use wenplayer_lib::DspHook;
+
+/// Fixed gain applied to every sample
+struct Gain {
+    factor: f32,
+}
+
+impl Gain {
+    fn from_db(db: f32) -> Self {
+        Self {
+            factor: 10f32.powf(db / 20.0),
+        }
+    }
+}
+
+impl DspHook for Gain {
+    fn process(&mut self, samples: &mut [f32], _sample_rate: u32, _channels: u16) {
+        for sample in samples.iter_mut() {
+            *sample *= self.factor;
+        }
+    }
+}
+
+fn main() {
+    wenplayer_lib::run_with_dsp_hooks(vec![Box::new(Gain::from_db(-6.0))]);
+}
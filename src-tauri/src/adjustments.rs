@@ -0,0 +1,101 @@
+use crate::tonemap::PixelFormat;
+use serde::{Deserialize, Serialize};
+
+/// User-facing picture controls, applied to frames after conversion
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoAdjustments {
+    /// Offset added to every channel, from -1.0 to 1.0
+    pub brightness: f32,
+    /// Scale around mid grey, from 0.0 (flat grey) to 2.0
+    pub contrast: f32,
+    /// Scale of the distance from grey, from 0.0 (monochrome) to 3.0
+    pub saturation: f32,
+    /// Gamma from 0.1 to 10.0; above 1.0 brightens the mid tones
+    pub gamma: f32,
+}
+
+impl Default for VideoAdjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl VideoAdjustments {
+    /// Clamp values into their valid ranges (non-finite values become neutral)
+    pub fn clamped(self) -> Self {
+        let neutral = Self::default();
+        let clamp = |value: f32, fallback: f32, min: f32, max: f32| {
+            if value.is_finite() {
+                value.clamp(min, max)
+            } else {
+                fallback
+            }
+        };
+        Self {
+            brightness: clamp(self.brightness, neutral.brightness, -1.0, 1.0),
+            contrast: clamp(self.contrast, neutral.contrast, 0.0, 2.0),
+            saturation: clamp(self.saturation, neutral.saturation, 0.0, 3.0),
+            gamma: clamp(self.gamma, neutral.gamma, 0.1, 10.0),
+        }
+    }
+
+    /// Whether the settings leave the picture untouched
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Applies `VideoAdjustments` to packed RGB(A) pixels.
+///
+/// Brightness, contrast and gamma are folded into one lookup table per
+/// channel value; saturation mixes each pixel with its BT.709 luma first.
+pub struct ColorAdjuster {
+    saturation: f32,
+    lut: [u8; 256],
+}
+
+impl ColorAdjuster {
+    /// `None` for neutral settings, so untouched frames skip the pass
+    pub fn new(adjustments: VideoAdjustments) -> Option<Self> {
+        if adjustments.is_neutral() {
+            return None;
+        }
+        let mut lut = [0u8; 256];
+        for (code, out) in lut.iter_mut().enumerate() {
+            let value = code as f32 / 255.0;
+            let value = ((value - 0.5) * adjustments.contrast + 0.5 + adjustments.brightness)
+                .clamp(0.0, 1.0)
+                .powf(1.0 / adjustments.gamma);
+            *out = (value * 255.0).round() as u8;
+        }
+        Some(Self {
+            saturation: adjustments.saturation,
+            lut,
+        })
+    }
+
+    /// Adjust tightly packed pixels of `format` in place (alpha is left alone)
+    pub fn process(&self, pixels: &mut [u8], format: PixelFormat) {
+        let saturate = self.saturation != 1.0;
+        let [red, green, blue] = format.rgb_offsets();
+        for px in pixels.chunks_exact_mut(format.bytes_per_pixel()) {
+            if saturate {
+                let (r, g, b) = (px[red] as f32, px[green] as f32, px[blue] as f32);
+                let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                for c in px[..3].iter_mut() {
+                    let value = luma + (*c as f32 - luma) * self.saturation;
+                    *c = value.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            for c in px[..3].iter_mut() {
+                *c = self.lut[*c as usize];
+            }
+        }
+    }
+}
@@ -0,0 +1,532 @@
+use crate::decoder::{open_probed, source_channel_layout};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::timebase::micros_to_seconds;
+use anyhow::Result;
+use crossbeam_channel::Sender;
+use ffmpeg_next as ffmpeg;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Samples of this magnitude or more count as clipped; 16-bit full scale
+/// (32767 / 32768) is just above it
+pub const CLIP_LEVEL: f32 = 0.9999;
+
+/// BS.1770 measures loudness over 400 ms blocks overlapping by 75%, i.e.
+/// four 100 ms steps per block
+const STEP_SECONDS: f64 = 0.1;
+const STEPS_PER_BLOCK: usize = 4;
+/// Blocks quieter than this never count toward the integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Then blocks this far below the loudness of the remaining ones are dropped
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// True peak is measured on the signal oversampled by this factor, with an
+/// interpolation filter of `TRUE_PEAK_TAPS` taps per phase
+const TRUE_PEAK_FACTOR: usize = 4;
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Level and loudness summary of a file's audio
+#[derive(Clone, Debug, Serialize)]
+pub struct AudioAnalysis {
+    /// Highest sample magnitude in dBFS; `None` for digital silence
+    pub sample_peak_db: Option<f64>,
+    /// Highest magnitude between the samples too (4x oversampled), in dBTP;
+    /// what a DAC or lossy encoder may reach although no sample clips
+    pub true_peak_db: Option<f64>,
+    /// Integrated loudness (EBU R128 / ITU-R BS.1770) in LUFS; `None` for
+    /// audio shorter than one 400 ms block or below the -70 LUFS gate
+    pub integrated_lufs: Option<f64>,
+    /// Samples at full scale, counted over all channels
+    pub clipped_samples: u64,
+    /// Seconds of audio measured
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Handle to a running analysis; dropping it does not cancel the analysis
+pub struct AnalysisJob {
+    cancel: Arc<AtomicBool>,
+}
+
+impl AnalysisJob {
+    /// Ask the analysis thread to stop; nothing is cached for the file
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Release);
+    }
+}
+
+/// Finished analyses by path, kept while the file's modification time stays
+/// the same, so each file is decoded once
+#[derive(Clone, Default)]
+pub struct AnalysisCache {
+    entries: Arc<Mutex<HashMap<String, CachedAnalysis>>>,
+}
+
+/// Analysis of a file, with the modification time it had when it was opened
+struct CachedAnalysis {
+    modified: Option<SystemTime>,
+    analysis: AudioAnalysis,
+}
+
+impl AnalysisCache {
+    pub fn get(&self, path: &str) -> Option<AudioAnalysis> {
+        let entries = self.entries.lock();
+        let entry = entries.get(path)?;
+        (entry.modified == modified_time(path)).then(|| entry.analysis.clone())
+    }
+
+    fn insert(&self, path: &str, modified: Option<SystemTime>, analysis: AudioAnalysis) {
+        self.entries
+            .lock()
+            .insert(path.to_string(), CachedAnalysis { modified, analysis });
+    }
+}
+
+/// `None` for network sources and files that cannot be read
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Decode all of the audio in `path` and measure it on a background thread.
+///
+/// The input is opened before returning so bad paths fail immediately; the
+/// thread reports `AnalysisProgress`, then `AnalysisFinished` through
+/// `events`, and stores a completed analysis in `cache`.
+pub fn analyze(
+    path: &str,
+    cache: AnalysisCache,
+    events: Sender<PlayerEvent>,
+) -> Result<AnalysisJob> {
+    // A file changed while it is decoded is measured again next time
+    let modified = modified_time(path);
+    let analyzer = Analyzer::open(path)?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let thread_cancel = cancel.clone();
+    let path = path.to_string();
+
+    std::thread::spawn(move || {
+        let result = analyzer.run(&path, &thread_cancel, &events);
+        if let Ok(ref analysis) = result {
+            cache.insert(&path, modified, analysis.clone());
+        }
+        let (analysis, error) = match result {
+            Ok(analysis) => (Some(analysis), None),
+            Err(e) => (None, Some(PlayerError::from(e).to_string())),
+        };
+        let _ = events.send(PlayerEvent::AnalysisFinished {
+            path,
+            analysis,
+            error,
+        });
+    });
+
+    Ok(AnalysisJob { cancel })
+}
+
+/// Input, decoder and resampler owned by the analysis thread
+struct Analyzer {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Audio,
+    resampler: ffmpeg::software::resampling::context::Context,
+    stream_index: usize,
+    /// Layout bits of the source (the layout itself is not `Send`)
+    layout_mask: u64,
+    rate: u32,
+    channels: u16,
+    /// Declared length in seconds, for progress (0 when unknown)
+    duration: f64,
+}
+
+impl Analyzer {
+    fn open(path: &str) -> Result<Self, PlayerError> {
+        let _ = ffmpeg::init();
+        let input = open_probed(path)?;
+
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or_else(|| PlayerError::Unsupported("No audio stream".to_string()))?;
+        let stream_index = stream.index();
+
+        let mut decoder_context = ffmpeg::codec::Context::new();
+        decoder_context.set_parameters(stream.parameters())?;
+        let decoder = decoder_context.decoder().audio()?;
+
+        // Measure at the source rate and channels, as planar floats
+        let rate = decoder.rate();
+        let channels = decoder.channels();
+        let layout = source_channel_layout(decoder.channel_layout(), channels);
+        let resampler = ffmpeg::software::resampling::context::Context::get(
+            decoder.format(),
+            layout,
+            rate,
+            ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
+            layout,
+            rate,
+        )?;
+        let duration = micros_to_seconds(input.duration()).max(0.0);
+
+        Ok(Self {
+            input,
+            decoder,
+            resampler,
+            stream_index,
+            layout_mask: layout.bits(),
+            rate,
+            channels,
+            duration,
+        })
+    }
+
+    fn run(
+        mut self,
+        path: &str,
+        cancel: &AtomicBool,
+        events: &Sender<PlayerEvent>,
+    ) -> Result<AudioAnalysis> {
+        let mut meter = Meter::new(self.rate, self.layout_mask, self.channels as usize);
+        let mut last_progress = 0.0;
+
+        for (stream, packet) in self.input.packets() {
+            if cancel.load(Ordering::Acquire) {
+                return Err(PlayerError::Internal("Analysis cancelled".to_string()).into());
+            }
+            if stream.index() != self.stream_index || self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            measure_frames(&mut self.decoder, &mut self.resampler, &mut meter);
+
+            if self.duration > 0.0 {
+                let progress = (meter.seconds() / self.duration).clamp(0.0, 1.0);
+                if progress - last_progress >= 0.01 {
+                    last_progress = progress;
+                    let _ = events.send(PlayerEvent::AnalysisProgress {
+                        path: path.to_string(),
+                        progress,
+                    });
+                }
+            }
+        }
+        let _ = self.decoder.send_eof();
+        measure_frames(&mut self.decoder, &mut self.resampler, &mut meter);
+
+        let _ = events.send(PlayerEvent::AnalysisProgress {
+            path: path.to_string(),
+            progress: 1.0,
+        });
+        Ok(meter.finish(self.channels))
+    }
+}
+
+/// Convert and measure every frame the decoder has ready
+fn measure_frames(
+    decoder: &mut ffmpeg::decoder::Audio,
+    resampler: &mut ffmpeg::software::resampling::context::Context,
+    meter: &mut Meter,
+) {
+    let mut frame = ffmpeg::frame::Audio::empty();
+    let mut converted = ffmpeg::frame::Audio::empty();
+    while decoder.receive_frame(&mut frame).is_ok() {
+        if frame.channel_layout().is_empty() {
+            let layout = source_channel_layout(frame.channel_layout(), frame.channels());
+            frame.set_channel_layout(layout);
+        }
+        if resampler.run(&frame, &mut converted).is_err() || converted.samples() == 0 {
+            continue;
+        }
+        let planes: Vec<&[f32]> = (0..converted.planes())
+            .map(|ch| converted.plane::<f32>(ch))
+            .collect();
+        meter.process(&planes);
+    }
+}
+
+/// Second-order IIR section in double precision (the K-weighting high-pass
+/// sits at 38 Hz, where single precision drifts), direct form I
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The BS.1770 K-weighting filter at `rate`: a high shelf modelling the head,
+/// then a high-pass. Coefficients are derived for any rate the way libebur128
+/// does, matching the 48 kHz values of the standard.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Biquad::default()
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Biquad::default()
+    };
+    [shelf, high_pass]
+}
+
+/// BS.1770 weight of each channel of a layout with the `mask` bits: surround channels count 1.41
+/// times (+1.5 dB), the LFE not at all
+fn channel_weights(mask: u64, channels: usize) -> Vec<f64> {
+    use ffmpeg::ffi::{AV_CH_BACK_LEFT, AV_CH_BACK_RIGHT, AV_CH_LOW_FREQUENCY};
+    use ffmpeg::ffi::{AV_CH_SIDE_LEFT, AV_CH_SIDE_RIGHT};
+    let surround = AV_CH_BACK_LEFT | AV_CH_BACK_RIGHT | AV_CH_SIDE_LEFT | AV_CH_SIDE_RIGHT;
+    // Channels are stored in the order of their layout bits
+    let mut weights: Vec<f64> = (0..64)
+        .map(|bit| 1u64 << bit)
+        .filter(|bit| mask & bit != 0)
+        .map(|bit| {
+            if bit & AV_CH_LOW_FREQUENCY != 0 {
+                0.0
+            } else if bit & surround != 0 {
+                1.41
+            } else {
+                1.0
+            }
+        })
+        .collect();
+    weights.resize(channels, 1.0);
+    weights
+}
+
+/// Phases of the windowed-sinc interpolation filter, each producing one of
+/// the `TRUE_PEAK_FACTOR` output samples per input sample
+fn true_peak_phases() -> [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR] {
+    let length = TRUE_PEAK_FACTOR * TRUE_PEAK_TAPS;
+    let center = (length - 1) as f64 / 2.0;
+    let mut phases = [[0.0; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR];
+    for n in 0..length {
+        let t = (n as f64 - center) / TRUE_PEAK_FACTOR as f64;
+        let sinc = if t == 0.0 {
+            1.0
+        } else {
+            (std::f64::consts::PI * t).sin() / (std::f64::consts::PI * t)
+        };
+        let window =
+            0.5 - 0.5 * (2.0 * std::f64::consts::PI * (n as f64 + 0.5) / length as f64).cos();
+        phases[n % TRUE_PEAK_FACTOR][n / TRUE_PEAK_FACTOR] = (sinc * window) as f32;
+    }
+    phases
+}
+
+/// Oversampling peak detector for one channel, finding the peaks between
+/// samples that a DAC reconstructs
+#[derive(Clone)]
+pub struct TruePeak {
+    phases: [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR],
+    /// Last input samples, newest first
+    history: [f32; TRUE_PEAK_TAPS],
+}
+
+impl Default for TruePeak {
+    fn default() -> Self {
+        Self {
+            phases: true_peak_phases(),
+            history: [0.0; TRUE_PEAK_TAPS],
+        }
+    }
+}
+
+impl TruePeak {
+    /// Feed the next sample; returns the largest magnitude of the signal
+    /// interpolated around it
+    pub fn push(&mut self, x: f32) -> f32 {
+        self.history.copy_within(..TRUE_PEAK_TAPS - 1, 1);
+        self.history[0] = x;
+        self.phases
+            .iter()
+            .map(|phase| {
+                let y: f32 = phase
+                    .iter()
+                    .zip(self.history.iter())
+                    .map(|(h, x)| h * x)
+                    .sum();
+                y.abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Forget the past samples, e.g. after a seek
+    pub fn reset(&mut self) {
+        self.history = [0.0; TRUE_PEAK_TAPS];
+    }
+}
+
+/// Running peak, clipping and loudness measurement of planar samples
+struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    interpolators: Vec<TruePeak>,
+    /// Weighted mean square of every completed 100 ms step
+    step_powers: Vec<f64>,
+    /// Weighted sum of squares and sample frames of the step in progress
+    step_sum: f64,
+    step_frames: usize,
+    frames_per_step: usize,
+    sample_peak: f32,
+    true_peak: f32,
+    clipped: u64,
+    frames: u64,
+    rate: u32,
+}
+
+impl Meter {
+    fn new(rate: u32, layout_mask: u64, channels: usize) -> Self {
+        Self {
+            filters: vec![k_weighting(rate); channels],
+            weights: channel_weights(layout_mask, channels),
+            interpolators: vec![TruePeak::default(); channels],
+            step_powers: Vec::new(),
+            step_sum: 0.0,
+            step_frames: 0,
+            frames_per_step: ((rate as f64 * STEP_SECONDS).round() as usize).max(1),
+            sample_peak: 0.0,
+            true_peak: 0.0,
+            clipped: 0,
+            frames: 0,
+            rate,
+        }
+    }
+
+    /// Seconds of audio measured so far
+    fn seconds(&self) -> f64 {
+        self.frames as f64 / self.rate.max(1) as f64
+    }
+
+    /// Measure one block of planes, one per channel and of equal length
+    fn process(&mut self, planes: &[&[f32]]) {
+        let frames = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+        let channels = planes.len().min(self.filters.len());
+        for i in 0..frames {
+            let mut power = 0.0;
+            for (ch, plane) in planes.iter().enumerate().take(channels) {
+                let x = plane[i];
+                let magnitude = x.abs();
+                self.sample_peak = self.sample_peak.max(magnitude);
+                if magnitude >= CLIP_LEVEL {
+                    self.clipped += 1;
+                }
+
+                self.true_peak = self.true_peak.max(self.interpolators[ch].push(x));
+
+                let [shelf, high_pass] = &mut self.filters[ch];
+                let y = high_pass.process(shelf.process(x as f64));
+                power += self.weights[ch] * y * y;
+            }
+
+            self.step_sum += power;
+            self.step_frames += 1;
+            if self.step_frames == self.frames_per_step {
+                self.step_powers
+                    .push(self.step_sum / self.frames_per_step as f64);
+                self.step_sum = 0.0;
+                self.step_frames = 0;
+            }
+        }
+        self.frames += frames as u64;
+    }
+
+    fn finish(self, channels: u16) -> AudioAnalysis {
+        let peak_db = |peak: f32| (peak > 0.0).then(|| 20.0 * (peak as f64).log10());
+        AudioAnalysis {
+            sample_peak_db: peak_db(self.sample_peak),
+            true_peak_db: peak_db(self.true_peak.max(self.sample_peak)),
+            integrated_lufs: self.integrated_loudness(),
+            clipped_samples: self.clipped,
+            duration: self.seconds(),
+            sample_rate: self.rate,
+            channels,
+        }
+    }
+
+    /// Gated loudness of all 400 ms blocks (BS.1770-4, section 2.8)
+    fn integrated_loudness(&self) -> Option<f64> {
+        let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+        let mean = |powers: &[f64]| powers.iter().sum::<f64>() / powers.len() as f64;
+
+        let blocks: Vec<f64> = self
+            .step_powers
+            .windows(STEPS_PER_BLOCK)
+            .map(mean)
+            .filter(|power| loudness(*power) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        let gate = loudness(mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|power| loudness(*power) > gate)
+            .collect();
+        (!gated.is_empty()).then(|| loudness(mean(&gated)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis() -> AudioAnalysis {
+        AudioAnalysis {
+            sample_peak_db: None,
+            true_peak_db: None,
+            integrated_lufs: None,
+            clipped_samples: 0,
+            duration: 1.0,
+            sample_rate: 48000,
+            channels: 2,
+        }
+    }
+
+    #[test]
+    fn a_file_changed_while_analyzed_is_not_cached() {
+        let path = std::env::temp_dir().join(format!("wenplayer-analysis-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"before").unwrap();
+        let cache = AnalysisCache::default();
+
+        cache.insert(path, modified_time(path), analysis());
+        assert!(cache.get(path).is_some());
+
+        // Modified after it was opened, before the result came in
+        let opened = Some(SystemTime::UNIX_EPOCH);
+        cache.insert(path, opened, analysis());
+        assert!(cache.get(path).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+
+/// File name endings of font attachments muxers do not tag with a codec
+const FONT_EXTENSIONS: [&str; 5] = [".ttf", ".otf", ".ttc", ".woff", ".woff2"];
+
+/// A font embedded in the file (Matroska attachment), for styled (ASS)
+/// subtitle renderers to register
+#[derive(Clone, Debug, Serialize)]
+pub struct SubtitleFont {
+    /// Attachment file name, which ASS styles refer to by font family instead
+    pub name: String,
+    pub mime_type: Option<String>,
+    /// The font file as stored
+    pub data: Vec<u8>,
+}
+
+/// Font attachments of an opened input; empty if it has none
+pub fn subtitle_fonts(input: &ffmpeg::format::context::Input) -> Vec<SubtitleFont> {
+    input
+        .streams()
+        .filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Attachment)
+        .filter_map(|stream| {
+            let tags = stream.metadata();
+            let name = tags.get("filename").unwrap_or_default().to_string();
+            let mime_type = tags.get("mimetype").map(str::to_string);
+            let parameters = stream.parameters();
+            if !is_font(parameters.id(), mime_type.as_deref(), &name) {
+                return None;
+            }
+
+            // Attachment streams carry their file as the codec extradata
+            let data = unsafe {
+                let p = parameters.as_ptr();
+                if (*p).extradata.is_null() || (*p).extradata_size <= 0 {
+                    return None;
+                }
+                std::slice::from_raw_parts((*p).extradata, (*p).extradata_size as usize).to_vec()
+            };
+            let name = if name.is_empty() {
+                format!("attachment-{}", stream.index())
+            } else {
+                name
+            };
+            Some(SubtitleFont {
+                name,
+                mime_type,
+                data,
+            })
+        })
+        .collect()
+}
+
+/// Whether an attachment is a font, by codec, MIME type or file name
+fn is_font(id: ffmpeg::codec::Id, mime_type: Option<&str>, name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    matches!(id, ffmpeg::codec::Id::TTF | ffmpeg::codec::Id::OTF)
+        || mime_type.is_some_and(|mime| mime.contains("font"))
+        || FONT_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
@@ -1,10 +1,33 @@
+use crate::clock::{Clock, SharedClock};
+use crate::sync::AudioClock;
 use anyhow::{Context, Result};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     Device, OutputCallbackInfo, Stream, StreamConfig,
 };
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender, TryRecvError};
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Sample rate the decoder resamples to unless another one is requested
+pub const DEFAULT_OUTPUT_SAMPLE_RATE: u32 = 44100;
+/// Channel count the decoder resamples to
+pub const OUTPUT_CHANNELS: u16 = 2;
+
+/// Maximum audio queued ahead of the device in live mode
+const LIVE_PREROLL_MS: usize = 100;
+
+/// How long a held (silent) stream stays running before the device is paused
+const WARM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Attempts at building the device stream before the output gives up
+const STREAM_BUILD_ATTEMPTS: u32 = 4;
+/// Wait before the second attempt, doubled for each one after it
+const STREAM_BUILD_BACKOFF: Duration = Duration::from_millis(50);
 
 /// Audio output using CPAL - runs in a dedicated thread
 pub struct AudioOutput {
@@ -12,25 +35,69 @@ pub struct AudioOutput {
     _thread_handle: JoinHandle<()>,
     sample_rate: u32,
     channels: u16,
+    device_name: String,
+}
+
+/// How an `AudioOutput` feeds and paces its device
+pub struct OutputOptions {
+    /// Keep at most `LIVE_PREROLL_MS` of audio queued, for live sources
+    pub live_mode: bool,
+    /// Raise the scheduling priority of the device callback thread
+    pub realtime: bool,
+    /// Clock the stream build retries and the idle timeout are measured on
+    pub timer: SharedClock,
 }
 
 enum AudioCommand {
     Play,
     Pause,
+    /// Keep the stream running but output silence
+    Hold,
     Stop,
 }
 
 impl AudioOutput {
-    /// Create a new audio output with a sample receiver
+    /// Create a new audio output with a sample receiver, on the output device
+    /// named `device` or the system default for `None`.
+    ///
+    /// In live mode the output keeps at most `LIVE_PREROLL_MS` of audio queued and
+    /// discards the oldest samples beyond that, trading occasional glitches for
+    /// bounded latency. With `options.realtime` the device callback thread asks for a
+    /// raised scheduling priority (see `raise_callback_priority`).
+    ///
+    /// The stream runs at the rate closest to `sample_rate` the device
+    /// supports, which `sample_rate()` reports; the decoder must resample to it.
+    ///
+    /// Waits until the stream is running; a device that stays unavailable
+    /// through the build retries is returned as an error. The retry backoff
+    /// and the idle timeout are measured on `options.timer`.
     pub fn new(
+        device: Option<&str>,
         sample_rate: u32,
         channels: u16,
         sample_receiver: Receiver<Vec<f32>>,
+        clock: Arc<AudioClock>,
+        options: OutputOptions,
     ) -> Result<Self> {
+        let OutputOptions {
+            live_mode,
+            realtime,
+            timer,
+        } = options;
         let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .context("No output device available")?;
+        let device = match device {
+            Some(name) => host
+                .output_devices()?
+                .find(|d| d.name().is_ok_and(|n| n == name))
+                .with_context(|| format!("Output device {} not found", name))?,
+            None => host
+                .default_output_device()
+                .context("No output device available")?,
+        };
+        let device_name = device.name().unwrap_or_default();
+        // A stream requested at a rate the device lacks may fail to build, or
+        // play at the wrong speed where the backend does not check
+        let sample_rate = closest_rate(&device, sample_rate, channels).unwrap_or(sample_rate);
 
         let config = StreamConfig {
             channels,
@@ -38,46 +105,83 @@ impl AudioOutput {
             buffer_size: cpal::BufferSize::Default,
         };
 
+        let max_pending = if live_mode {
+            (sample_rate as usize * channels as usize * LIVE_PREROLL_MS) / 1000
+        } else {
+            usize::MAX
+        };
+
         let (command_sender, command_receiver): (Sender<AudioCommand>, Receiver<AudioCommand>) =
             bounded(16);
 
-        let thread_handle = std::thread::spawn(move || {
-            let stream = match device.build_output_stream(
-                &config,
-                move |data: &mut [f32], _: &OutputCallbackInfo| {
-                    // Try to receive samples from the channel
-                    match sample_receiver.try_recv() {
-                        Ok(samples) => {
-                            // Copy samples to the output buffer
-                            let len = samples.len().min(data.len());
-                            data[..len].copy_from_slice(&samples[..len]);
-                            // Zero out the rest of the buffer
-                            for i in len..data.len() {
-                                data[i] = 0.0;
-                            }
-                        }
-                        Err(TryRecvError::Empty) => {
-                            // No samples available, output silence
-                            for sample in data.iter_mut() {
-                                *sample = 0.0;
-                            }
-                        }
-                        Err(TryRecvError::Disconnected) => {
-                            // Channel closed, output silence
-                            for sample in data.iter_mut() {
-                                *sample = 0.0;
-                            }
-                        }
+        let samples_per_second = sample_rate as f64 * channels as f64;
+        let holding = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = bounded::<Result<(), String>>(1);
+
+        let callback_holding = holding.clone();
+        // A failed build consumes the callback, so each attempt gets a new one
+        let make_callback = move || {
+            let clock = clock.clone();
+            let sample_receiver = sample_receiver.clone();
+            let callback_holding = callback_holding.clone();
+            // Samples received from the decoder but not yet written to the device
+            let mut pending: VecDeque<f32> = VecDeque::new();
+            // The callback runs on a thread CPAL owns, so it raises itself once
+            let mut prioritized = !realtime;
+
+            move |data: &mut [f32], info: &OutputCallbackInfo| {
+                if !prioritized {
+                    prioritized = true;
+                    raise_callback_priority();
+                }
+
+                // Held: keep the device fed without consuming audio or moving the clock
+                if callback_holding.load(Ordering::Acquire) {
+                    data.fill(0.0);
+                    return;
+                }
+
+                // What is written now is heard after the device latency
+                let timestamp = info.timestamp();
+                if let Some(latency) = timestamp.playback.duration_since(&timestamp.callback) {
+                    clock.set_latency((latency.as_secs_f64() * samples_per_second) as u64);
+                }
+
+                // Drop what is left from before a seek
+                if clock.take_flush() {
+                    pending.clear();
+                }
+
+                // Pull decoded chunks until the callback buffer can be filled
+                while let Ok(samples) = sample_receiver.try_recv() {
+                    pending.extend(samples);
+                    if pending.len() >= data.len() && !live_mode {
+                        break;
                     }
-                },
-                move |err| {
-                    eprintln!("Audio output error: {}", err);
-                },
-                None,
-            ) {
+                }
+
+                // Live sources: drop the oldest audio to keep latency bounded
+                if pending.len() > max_pending {
+                    let excess = pending.len() - max_pending;
+                    pending.drain(..excess);
+                    clock.add_played(excess as u64);
+                }
+
+                // Copy samples to the output buffer, padding with silence
+                let available = pending.len().min(data.len());
+                for sample in data.iter_mut() {
+                    *sample = pending.pop_front().unwrap_or(0.0);
+                }
+                clock.add_played(available as u64);
+            }
+        };
+
+        let thread_handle = std::thread::spawn(move || {
+            let stream = match build_stream(&device, &config, timer.as_ref(), make_callback) {
                 Ok(s) => s,
                 Err(e) => {
                     eprintln!("Failed to build audio stream: {}", e);
+                    let _ = ready_tx.send(Err(format!("Cannot open the audio stream: {}", e)));
                     return;
                 }
             };
@@ -85,23 +189,46 @@ impl AudioOutput {
             // Start playing
             if let Err(e) = stream.play() {
                 eprintln!("Failed to start audio stream: {}", e);
+                let _ = ready_tx.send(Err(format!("Cannot start the audio stream: {}", e)));
                 return;
             }
+            let _ = ready_tx.send(Ok(()));
 
-            // Listen for commands
+            // Listen for commands; a held stream is paused once it has idled too long
+            let mut idle = IdleTimer::new(timer);
             loop {
-                match command_receiver.recv() {
+                let command = match idle.remaining() {
+                    Some(remaining) => command_receiver.recv_timeout(remaining),
+                    None => command_receiver
+                        .recv()
+                        .map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match command {
                     Ok(AudioCommand::Play) => {
+                        idle.cancel();
+                        holding.store(false, Ordering::Release);
                         let _ = stream.play();
                     }
                     Ok(AudioCommand::Pause) => {
+                        idle.cancel();
                         let _ = stream.pause();
                     }
+                    Ok(AudioCommand::Hold) => {
+                        idle.start();
+                        holding.store(true, Ordering::Release);
+                        let _ = stream.play();
+                    }
                     Ok(AudioCommand::Stop) => {
                         let _ = stream.pause();
                         break;
                     }
-                    Err(_) => {
+                    Err(RecvTimeoutError::Timeout) if idle.expired() => {
+                        // Paused for a long time, let the device sleep
+                        idle.cancel();
+                        let _ = stream.pause();
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
                         // Channel closed, exit
                         break;
                     }
@@ -109,14 +236,31 @@ impl AudioOutput {
             }
         });
 
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => anyhow::bail!(e),
+            Err(_) => anyhow::bail!("Audio thread closed"),
+        }
+
         Ok(Self {
             command_sender,
             _thread_handle: thread_handle,
             sample_rate,
             channels,
+            device_name,
         })
     }
 
+    /// Name of the device this output plays on
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Rate the device stream runs at
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     /// Stop the audio stream
     pub fn stop(&self) {
         let _ = self.command_sender.send(AudioCommand::Stop);
@@ -127,32 +271,317 @@ impl AudioOutput {
         let _ = self.command_sender.send(AudioCommand::Pause);
     }
 
+    /// Pause playback but keep the device running on silence, so resuming is instant.
+    ///
+    /// The device is paused anyway after `WARM_IDLE_TIMEOUT` to save power.
+    pub fn hold(&self) {
+        let _ = self.command_sender.send(AudioCommand::Hold);
+    }
+
     /// Resume playback
     pub fn resume(&self) {
         let _ = self.command_sender.send(AudioCommand::Play);
     }
 }
 
+/// When a held stream is due to be paused, measured on an injected clock
+struct IdleTimer {
+    clock: SharedClock,
+    deadline: Option<Instant>,
+}
+
+impl IdleTimer {
+    fn new(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            deadline: None,
+        }
+    }
+
+    /// Pause the device `WARM_IDLE_TIMEOUT` from now
+    fn start(&mut self) {
+        self.deadline = Some(self.clock.now() + WARM_IDLE_TIMEOUT);
+    }
+
+    fn cancel(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Time left before the device is paused, `None` when not held
+    fn remaining(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+
+    fn expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+}
+
+/// Build the output stream, retrying with a doubling backoff while the device
+/// is momentarily unavailable (e.g. still busy after another app released it)
+fn build_stream<F, C>(
+    device: &Device,
+    config: &StreamConfig,
+    clock: &dyn Clock,
+    mut make_callback: F,
+) -> Result<Stream, cpal::BuildStreamError>
+where
+    F: FnMut() -> C,
+    C: FnMut(&mut [f32], &OutputCallbackInfo) + Send + 'static,
+{
+    retry_with_backoff(clock, || {
+        device.build_output_stream(
+            config,
+            make_callback(),
+            |err| eprintln!("Audio output error: {}", err),
+            None,
+        )
+    })
+}
+
+/// Call `build` up to `STREAM_BUILD_ATTEMPTS` times until it succeeds,
+/// sleeping on `clock` between failures, `STREAM_BUILD_BACKOFF` first
+fn retry_with_backoff<T, E: std::fmt::Display>(
+    clock: &dyn Clock,
+    mut build: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let mut backoff = STREAM_BUILD_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match build() {
+            Ok(built) => return Ok(built),
+            Err(e) if attempt < STREAM_BUILD_ATTEMPTS => {
+                eprintln!(
+                    "Failed to build audio stream (attempt {} of {}), retrying: {}",
+                    attempt, STREAM_BUILD_ATTEMPTS, e
+                );
+                clock.sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Ask the OS to schedule the calling device callback thread ahead of normal
+/// threads, so audio keeps up when the machine is busy.
+///
+/// Linux only grants it with rtkit or `CAP_SYS_NICE` (or a raised `rtprio`
+/// limit); without permission the thread keeps its normal priority. CoreAudio
+/// already runs its callbacks on a real-time thread, which is left alone.
+fn raise_callback_priority() {
+    if cfg!(target_os = "macos") {
+        return;
+    }
+    if let Err(e) =
+        thread_priority::set_current_thread_priority(thread_priority::ThreadPriority::Max)
+    {
+        eprintln!("Audio thread priority not raised: {:?}", e);
+    }
+}
+
+/// Pick the rate supported by the default output device that is closest to `requested`
+pub fn nearest_supported_rate(requested: u32, channels: u16) -> Result<u32> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .context("No output device available")?;
+    closest_rate(&device, requested, channels)
+}
+
+/// The rate `device` supports for `channels` that is closest to `requested`
+fn closest_rate(device: &Device, requested: u32, channels: u16) -> Result<u32> {
+    let mut best: Option<u32> = None;
+    for range in device.supported_output_configs()? {
+        if range.channels() != channels {
+            continue;
+        }
+        let candidate = requested.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+        match best {
+            Some(b) if b.abs_diff(requested) <= candidate.abs_diff(requested) => {}
+            _ => best = Some(candidate),
+        }
+    }
+
+    best.context("Output device has no configuration for this channel count")
+}
+
+/// Output devices currently known to the host
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct OutputDevices {
+    pub devices: Vec<String>,
+    /// The system default output, if any
+    pub default_device: Option<String>,
+}
+
+impl OutputDevices {
+    /// Enumerate the host's output devices (an empty list if that fails)
+    pub fn scan() -> Self {
+        let host = cpal::default_host();
+        let devices = host
+            .output_devices()
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default();
+        let default_device = host.default_output_device().and_then(|d| d.name().ok());
+        Self {
+            devices,
+            default_device,
+        }
+    }
+}
+
 /// Audio buffer for queuing samples
 pub struct AudioBuffer {
     sender: Sender<Vec<f32>>,
+    receiver: Receiver<Vec<f32>>,
 }
 
 impl AudioBuffer {
-    pub fn new(sender: Sender<Vec<f32>>) -> Self {
-        Self { sender }
+    pub fn new(sender: Sender<Vec<f32>>, receiver: Receiver<Vec<f32>>) -> Self {
+        Self { sender, receiver }
     }
 
     /// Add samples to the buffer
-    pub fn push_samples(&self, samples: &[f32]) -> anyhow::Result<()> {
+    pub fn push_samples(&self, samples: Vec<f32>) -> anyhow::Result<()> {
         self.sender
-            .send(samples.to_vec())
+            .send(samples)
             .context("Failed to send samples to audio output")?;
         Ok(())
     }
+
+    /// Chunks sent but not yet picked up by the output
+    pub fn queued_chunks(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Discard chunks not yet picked up by the output
+    pub fn clear(&self) {
+        while self.receiver.try_recv().is_ok() {}
+    }
 }
 
 /// Create a sample channel pair
 pub fn create_sample_channel() -> (Sender<Vec<f32>>, Receiver<Vec<f32>>) {
     unbounded()
 }
+
+/// Split interleaved samples into one buffer per channel.
+///
+/// A trailing partial frame is ignored; `channels == 0` yields no buffers.
+/// This allocates, so use it for analysis off the decoder thread, not in a
+/// `DspHook`.
+pub fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    if channels == 0 {
+        return Vec::new();
+    }
+    let frames = samples.len() / channels;
+    let mut planes: Vec<Vec<f32>> = (0..channels).map(|_| Vec::with_capacity(frames)).collect();
+    for frame in samples.chunks_exact(channels) {
+        for (plane, &sample) in planes.iter_mut().zip(frame) {
+            plane.push(sample);
+        }
+    }
+    planes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn idle_timer_expires_after_the_warm_timeout() {
+        let clock = Arc::new(MockClock::new());
+        let mut idle = IdleTimer::new(clock.clone());
+        assert_eq!(idle.remaining(), None);
+
+        idle.start();
+        assert_eq!(idle.remaining(), Some(WARM_IDLE_TIMEOUT));
+        clock.advance(WARM_IDLE_TIMEOUT - Duration::from_secs(1));
+        assert_eq!(idle.remaining(), Some(Duration::from_secs(1)));
+        assert!(!idle.expired());
+        clock.advance(Duration::from_secs(1));
+        assert!(idle.expired());
+
+        // Resuming playback disarms it
+        idle.cancel();
+        assert_eq!(idle.remaining(), None);
+        assert!(!idle.expired());
+    }
+
+    #[test]
+    fn retries_back_off_doubling_on_the_clock() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut waits = Vec::new();
+        let mut last = start;
+        let built = retry_with_backoff(&clock, || {
+            waits.push(clock.now() - last);
+            last = clock.now();
+            if waits.len() < 3 {
+                Err("device busy")
+            } else {
+                Ok(waits.len())
+            }
+        });
+        assert_eq!(built, Ok(3));
+        let ms = Duration::from_millis;
+        assert_eq!(waits, [ms(0), ms(50), ms(100)]);
+        assert_eq!(clock.elapsed(start), ms(150));
+    }
+
+    #[test]
+    fn retries_give_up_after_the_last_attempt() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut attempts = 0;
+        let built: Result<(), _> = retry_with_backoff(&clock, || {
+            attempts += 1;
+            Err("no device")
+        });
+        assert_eq!(built, Err("no device"));
+        assert_eq!(attempts, STREAM_BUILD_ATTEMPTS);
+        // No wait after the final failure
+        assert_eq!(clock.elapsed(start), Duration::from_millis(50 + 100 + 200));
+    }
+
+    #[test]
+    fn deinterleave_mono_is_a_copy() {
+        assert_eq!(deinterleave(&[0.1, 0.2, 0.3], 1), [vec![0.1, 0.2, 0.3]]);
+    }
+
+    #[test]
+    fn deinterleave_stereo() {
+        let planes = deinterleave(&[1.0, -1.0, 2.0, -2.0, 3.0, -3.0], 2);
+        assert_eq!(planes, [vec![1.0, 2.0, 3.0], vec![-1.0, -2.0, -3.0]]);
+    }
+
+    #[test]
+    fn deinterleave_5_1() {
+        // Two frames; each sample encodes its frame (tens) and channel (units)
+        let samples: Vec<f32> = (0..2)
+            .flat_map(|frame| (0..6).map(move |ch| (frame * 10 + ch) as f32))
+            .collect();
+        let planes = deinterleave(&samples, 6);
+        assert_eq!(planes.len(), 6);
+        for (ch, plane) in planes.iter().enumerate() {
+            assert_eq!(plane, &[ch as f32, 10.0 + ch as f32]);
+        }
+    }
+
+    #[test]
+    fn deinterleave_drops_a_partial_frame() {
+        let planes = deinterleave(&[1.0, -1.0, 2.0, -2.0, 3.0], 2);
+        assert_eq!(planes, [vec![1.0, 2.0], vec![-1.0, -2.0]]);
+        // Too few samples for one frame still yields every channel, empty
+        assert_eq!(deinterleave(&[1.0], 2), [Vec::<f32>::new(), Vec::new()]);
+    }
+
+    #[test]
+    fn deinterleave_without_channels() {
+        assert!(deinterleave(&[1.0, 2.0], 0).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::decoder::{VideoFrame, SEEK_TOLERANCE};
+use serde::{Deserialize, Serialize};
+
+/// Memory a full-decode cache may take unless another cap is set
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+/// Accepted memory caps, in MiB
+pub const CACHE_MAX_MB_RANGE: std::ops::RangeInclusive<u64> = 16..=16384;
+/// Longest file (seconds) that is decoded into memory
+pub const MAX_FULL_DECODE_DURATION: f64 = 600.0;
+
+/// How much of the file the decoder keeps decoded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CacheMode {
+    /// Decode up to `buffer_ahead` seconds ahead of the playback position
+    #[default]
+    Streaming,
+    /// Decode the whole file into memory on load, so seeks are instant; files
+    /// over the duration or memory cap stream instead
+    FullDecode,
+}
+
+/// Requested cache mode and whether the loaded file plays from memory
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CacheStatus {
+    pub mode: CacheMode,
+    /// Memory a full-decode cache may take
+    pub max_bytes: u64,
+    /// The loaded file was decoded completely and plays from memory
+    pub active: bool,
+    /// Memory held by the decoded frames and samples
+    pub bytes: u64,
+}
+
+impl Default for CacheStatus {
+    fn default() -> Self {
+        Self {
+            mode: CacheMode::default(),
+            max_bytes: DEFAULT_CACHE_MAX_BYTES,
+            active: false,
+            bytes: 0,
+        }
+    }
+}
+
+/// A frame or audio chunk taken from a `FullCache`
+pub enum CachedItem {
+    Video(VideoFrame),
+    /// Interleaved output samples at unity gain and their start time
+    Audio(Vec<f32>, f64),
+}
+
+/// Every frame and audio chunk of a file in presentation order, with the
+/// position playback has reached in each
+#[derive(Default)]
+pub struct FullCache {
+    video: Vec<VideoFrame>,
+    audio: Vec<(Vec<f32>, f64)>,
+    next_video: usize,
+    next_audio: usize,
+    bytes: u64,
+}
+
+impl FullCache {
+    pub fn push_video(&mut self, frame: VideoFrame) {
+        self.bytes += frame.data.len() as u64;
+        self.video.push(frame);
+    }
+
+    pub fn push_audio(&mut self, samples: Vec<f32>, timestamp: f64) {
+        self.bytes += (samples.len() * std::mem::size_of::<f32>()) as u64;
+        self.audio.push((samples, timestamp));
+    }
+
+    /// Memory held by the cached media
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Continue from `time`: the first frame at or after it and the audio
+    /// chunk playing at it (which starts up to one chunk early)
+    pub fn seek(&mut self, time: f64) {
+        self.next_video = self
+            .video
+            .partition_point(|frame| frame.timestamp + SEEK_TOLERANCE < time);
+        self.next_audio = self
+            .audio
+            .partition_point(|(_, start)| *start <= time)
+            .saturating_sub(1);
+    }
+
+    /// Take the next frame or chunk, whichever starts first; `None` at the end
+    pub fn next(&mut self) -> Option<CachedItem> {
+        let video = self.video.get(self.next_video);
+        let audio = self.audio.get(self.next_audio);
+        let take_video = match (video, audio) {
+            (Some(frame), Some((_, start))) => frame.timestamp <= *start,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => return None,
+        };
+        if take_video {
+            let frame = self.video[self.next_video].clone();
+            self.next_video += 1;
+            Some(CachedItem::Video(frame))
+        } else {
+            let (samples, start) = self.audio[self.next_audio].clone();
+            self.next_audio += 1;
+            Some(CachedItem::Audio(samples, start))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tonemap::{ColorInfo, PixelFormat};
+
+    fn frame(timestamp: f64) -> VideoFrame {
+        VideoFrame {
+            width: 4,
+            height: 2,
+            data: vec![0; 4 * 2 * 4],
+            format: PixelFormat::default(),
+            timestamp,
+            color: ColorInfo {
+                range: "limited",
+                primaries: "bt709",
+                transfer: "bt709",
+                matrix: "bt709",
+                tagged: false,
+            },
+        }
+    }
+
+    /// Start time of each item left, "v" for video and "a" for audio
+    fn drain(cache: &mut FullCache) -> Vec<(&'static str, f64)> {
+        std::iter::from_fn(|| cache.next())
+            .map(|item| match item {
+                CachedItem::Video(frame) => ("v", frame.timestamp),
+                CachedItem::Audio(_, start) => ("a", start),
+            })
+            .collect()
+    }
+
+    fn cache() -> FullCache {
+        let mut cache = FullCache::default();
+        for timestamp in [0.0, 0.04, 0.08] {
+            cache.push_video(frame(timestamp));
+        }
+        cache.push_audio(vec![0.0; 100], 0.0);
+        cache.push_audio(vec![0.0; 100], 0.05);
+        cache
+    }
+
+    #[test]
+    fn bytes_count_pixels_and_samples() {
+        let mut cache = FullCache::default();
+        assert_eq!(cache.bytes(), 0);
+        cache.push_video(frame(0.0));
+        assert_eq!(cache.bytes(), 32);
+        cache.push_audio(vec![0.0; 100], 0.0);
+        assert_eq!(cache.bytes(), 32 + 400);
+        // Playing from the cache does not release anything
+        cache.next();
+        cache.seek(0.0);
+        assert_eq!(cache.bytes(), 432);
+    }
+
+    #[test]
+    fn items_come_out_in_time_order() {
+        let mut cache = cache();
+        assert_eq!(
+            drain(&mut cache),
+            [
+                ("v", 0.0),
+                ("a", 0.0),
+                ("v", 0.04),
+                ("a", 0.05),
+                ("v", 0.08)
+            ]
+        );
+        assert!(cache.next().is_none());
+    }
+
+    #[test]
+    fn seek_resumes_at_the_frame_and_the_chunk_playing() {
+        let mut cache = cache();
+        drain(&mut cache);
+
+        // Between frames: the next frame, and the audio chunk that covers it
+        cache.seek(0.06);
+        assert_eq!(drain(&mut cache), [("a", 0.05), ("v", 0.08)]);
+        // A frame within SEEK_TOLERANCE of the target is not skipped
+        cache.seek(0.0405);
+        assert_eq!(
+            drain(&mut cache),
+            [("a", 0.0), ("v", 0.04), ("a", 0.05), ("v", 0.08)]
+        );
+        // Back to the start
+        cache.seek(0.0);
+        assert_eq!(drain(&mut cache).len(), 5);
+    }
+}
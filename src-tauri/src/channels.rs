@@ -0,0 +1,130 @@
+use ffmpeg::channel_layout::ChannelLayout;
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+
+/// How vocals are being removed from the loaded audio
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub enum VocalRemovalMode {
+    #[default]
+    Off,
+    /// The source has a center channel, which is muted before the downmix
+    CenterMuted,
+    /// Stereo source: left minus right on both channels, cancelling whatever
+    /// is mixed equally into both (usually the lead vocal, but also centered
+    /// bass and drums; reverb on the vocal stays, and the result is mono)
+    PhaseCancel,
+    /// Requested, but the source is mono, so there is nothing to cancel
+    Unavailable,
+}
+
+impl VocalRemovalMode {
+    /// The method that applies to a source with `layout`
+    pub fn for_layout(layout: ChannelLayout) -> Self {
+        match layout.channels() {
+            1 => Self::Unavailable,
+            2 => Self::PhaseCancel,
+            _ if center_channel(layout).is_some() => Self::CenterMuted,
+            _ => Self::PhaseCancel,
+        }
+    }
+}
+
+/// Index of the front center channel in frames of `layout`, if it has one
+pub fn center_channel(layout: ChannelLayout) -> Option<usize> {
+    let center = ffmpeg::ffi::AV_CH_FRONT_CENTER;
+    if layout.bits() & center == 0 {
+        return None;
+    }
+    // Channels are stored in the order of their layout bits
+    let below = layout.bits() & (center - 1);
+    Some(below.count_ones() as usize)
+}
+
+/// Silence the channels of a decoded frame whose bit is set in `mask`
+/// (bit 0 is the first channel), whatever its sample format
+pub fn mute_channels(frame: &mut ffmpeg::frame::Audio, mask: u64) {
+    let channels = frame.channels() as usize;
+    let bytes = frame.format().bytes();
+    let samples = frame.samples();
+    // Unsigned 8-bit samples are centered on 0x80
+    let silence = match frame.format() {
+        ffmpeg::format::Sample::U8(_) => 0x80,
+        _ => 0,
+    };
+    let muted = (0..channels.min(64)).filter(|ch| mask & (1 << ch) != 0);
+
+    if frame.is_planar() {
+        for ch in muted {
+            let plane = frame.data_mut(ch);
+            let len = (samples * bytes).min(plane.len());
+            plane[..len].fill(silence);
+        }
+    } else {
+        let muted: Vec<usize> = muted.collect();
+        let data = frame.data_mut(0);
+        for sample in data.chunks_exact_mut(channels * bytes).take(samples) {
+            for &ch in &muted {
+                sample[ch * bytes..(ch + 1) * bytes].fill(silence);
+            }
+        }
+    }
+}
+
+/// Replace both channels of interleaved stereo samples with half their
+/// difference, cancelling what is centered in the mix
+pub fn cancel_center(samples: &mut [f32]) {
+    for frame in samples.chunks_exact_mut(2) {
+        let side = (frame[0] - frame[1]) * 0.5;
+        frame[0] = side;
+        frame[1] = side;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_for_each_layout() {
+        let cases = [
+            (ChannelLayout::MONO, VocalRemovalMode::Unavailable),
+            (ChannelLayout::STEREO, VocalRemovalMode::PhaseCancel),
+            (ChannelLayout::SURROUND, VocalRemovalMode::CenterMuted),
+            (ChannelLayout::_5POINT1, VocalRemovalMode::CenterMuted),
+            (ChannelLayout::_7POINT1, VocalRemovalMode::CenterMuted),
+            // More than two channels but no center: cancel like stereo
+            (ChannelLayout::QUAD, VocalRemovalMode::PhaseCancel),
+            (ChannelLayout::_2_1, VocalRemovalMode::PhaseCancel),
+        ];
+        for (layout, mode) in cases {
+            assert_eq!(VocalRemovalMode::for_layout(layout), mode, "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn center_channel_index() {
+        let cases = [
+            // Mono is the front center channel alone
+            (ChannelLayout::MONO, Some(0)),
+            (ChannelLayout::STEREO, None),
+            // FL FR FC
+            (ChannelLayout::SURROUND, Some(2)),
+            // FL FR FC LFE BL BR, and the same with side channels
+            (ChannelLayout::_5POINT1_BACK, Some(2)),
+            (ChannelLayout::_5POINT1, Some(2)),
+            // FL FR BC: a back center is not the front one
+            (ChannelLayout::_2_1, None),
+            (ChannelLayout::QUAD, None),
+        ];
+        for (layout, center) in cases {
+            assert_eq!(center_channel(layout), center, "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn cancel_center_removes_what_both_channels_share() {
+        let mut samples = [0.5, 0.5, 0.75, 0.25, -0.2, 0.2];
+        cancel_center(&mut samples);
+        assert_eq!(samples, [0.0, 0.0, 0.25, 0.25, -0.2, -0.2]);
+    }
+}
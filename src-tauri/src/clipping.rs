@@ -0,0 +1,124 @@
+use crate::analysis::{TruePeak, CLIP_LEVEL};
+use crate::events::PlayerEvent;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Least time between two `clipping-detected` events; the overs in between
+/// are summed up in the next one
+const CLIP_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Overs found in the output since the file was loaded
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ClipStats {
+    /// Clip detection is on (see `set_clip_detection`)
+    pub enabled: bool,
+    /// Samples at or above full scale
+    pub clipped_samples: u64,
+    /// Samples under full scale with a reconstructed peak over it next to them
+    pub intersample_overs: u64,
+}
+
+/// The loudest over since the last event
+#[derive(Clone, Copy)]
+struct Over {
+    channel: usize,
+    peak: f32,
+    intersample: bool,
+}
+
+/// Watches interleaved output samples for digital and inter-sample clipping
+#[derive(Default)]
+pub struct ClipMonitor {
+    stats: ClipStats,
+    /// One detector per output channel
+    true_peaks: Vec<TruePeak>,
+    worst: Option<Over>,
+    last_event: Option<Instant>,
+}
+
+impl ClipMonitor {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.stats.enabled = enabled;
+        self.reset_filters();
+    }
+
+    pub fn stats(&self) -> ClipStats {
+        self.stats
+    }
+
+    /// Start counting afresh for a new file
+    pub fn reset(&mut self) {
+        self.stats = ClipStats {
+            enabled: self.stats.enabled,
+            ..ClipStats::default()
+        };
+        self.worst = None;
+        self.last_event = None;
+        self.reset_filters();
+    }
+
+    /// Forget the samples before a discontinuity (seek), which would
+    /// otherwise ring through the interpolation
+    pub fn reset_filters(&mut self) {
+        for true_peak in &mut self.true_peaks {
+            true_peak.reset();
+        }
+    }
+
+    /// Measure `samples` of `channels` interleaved channels; returns the
+    /// event to raise, at most once per `CLIP_EVENT_INTERVAL`
+    pub fn process(
+        &mut self,
+        samples: &[f32],
+        channels: usize,
+        now: Instant,
+    ) -> Option<PlayerEvent> {
+        if !self.stats.enabled || channels == 0 {
+            return None;
+        }
+        self.true_peaks.resize_with(channels, TruePeak::default);
+
+        for frame in samples.chunks_exact(channels) {
+            for (channel, (&x, true_peak)) in frame.iter().zip(&mut self.true_peaks).enumerate() {
+                let magnitude = x.abs();
+                let interpolated = true_peak.push(x);
+                let over = if magnitude >= CLIP_LEVEL {
+                    self.stats.clipped_samples += 1;
+                    Over {
+                        channel,
+                        peak: magnitude.max(interpolated),
+                        intersample: false,
+                    }
+                } else if interpolated >= CLIP_LEVEL {
+                    self.stats.intersample_overs += 1;
+                    Over {
+                        channel,
+                        peak: interpolated,
+                        intersample: true,
+                    }
+                } else {
+                    continue;
+                };
+                if self.worst.is_none_or(|worst| over.peak > worst.peak) {
+                    self.worst = Some(over);
+                }
+            }
+        }
+
+        if self
+            .last_event
+            .is_some_and(|last| now.saturating_duration_since(last) < CLIP_EVENT_INTERVAL)
+        {
+            return None;
+        }
+        let over = self.worst.take()?;
+        self.last_event = Some(now);
+        Some(PlayerEvent::ClippingDetected {
+            channel: over.channel,
+            peak_db: 20.0 * (over.peak as f64).log10(),
+            intersample: over.intersample,
+            clipped_samples: self.stats.clipped_samples,
+            intersample_overs: self.stats.intersample_overs,
+        })
+    }
+}
@@ -0,0 +1,79 @@
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Source of time for sync and scheduling decisions
+pub trait Clock: Send + Sync {
+    /// Current instant
+    fn now(&self) -> Instant;
+
+    /// Block for `duration` (or, for a mock, advance by it)
+    fn sleep(&self, duration: Duration);
+
+    /// Time elapsed since `earlier`
+    fn elapsed(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+/// Shared handle to a clock
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The monotonic system clock, used in production
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Manually driven clock for tests.
+///
+/// Time only moves when `advance` is called; `sleep` advances it immediately
+/// instead of blocking.
+#[derive(Debug)]
+pub struct MockClock {
+    origin: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move time forward
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.origin + *self.offset.lock()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Clock used unless one is injected
+pub fn system_clock() -> SharedClock {
+    Arc::new(MonotonicClock)
+}
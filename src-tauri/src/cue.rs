@@ -0,0 +1,147 @@
+use crate::error::PlayerError;
+use crate::playlist::PlaylistEntry;
+use std::path::{Path, PathBuf};
+
+/// CD frames per second, the unit of cue sheet `INDEX` times
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+
+/// A `TRACK` of a cue sheet while it is being read
+struct CueTrack {
+    file: PathBuf,
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    /// `INDEX 00` (start of the pregap) and `INDEX 01` (start of the track)
+    pregap: Option<f64>,
+    start: Option<f64>,
+}
+
+/// Read a cue sheet into one playlist entry per track
+pub fn load_cue(path: &str) -> Result<Vec<PlaylistEntry>, PlayerError> {
+    let bytes = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    let entries = parse_cue(&text, base);
+    if entries.is_empty() {
+        return Err(PlayerError::Unsupported(format!(
+            "{} has no playable tracks",
+            path
+        )));
+    }
+    Ok(entries)
+}
+
+/// Parse cue sheet text; `FILE` names are resolved against `base`.
+///
+/// Each track plays from its `INDEX 01` (`INDEX 00` if that is missing) to the
+/// start of the next track in the same file, the last one to the file's end.
+/// Tracks without any index are skipped, except that the first track of a
+/// file starts at 0.
+pub fn parse_cue(text: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    let mut file: Option<PathBuf> = None;
+    let mut album_performer = None;
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let in_track = tracks
+            .last()
+            .is_some_and(|t| Some(&t.file) == file.as_ref());
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => file = Some(base.join(cue_value(rest))),
+            "TRACK" => {
+                let Some(ref file) = file else {
+                    eprintln!("Cue track before any FILE, skipped: {}", line);
+                    continue;
+                };
+                tracks.push(CueTrack {
+                    file: file.clone(),
+                    number: rest
+                        .split_whitespace()
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or(tracks.len() as u32 + 1),
+                    title: None,
+                    performer: None,
+                    pregap: None,
+                    start: None,
+                });
+            }
+            "TITLE" if in_track => tracks.last_mut().unwrap().title = Some(cue_value(rest)),
+            "PERFORMER" if in_track => tracks.last_mut().unwrap().performer = Some(cue_value(rest)),
+            "PERFORMER" => album_performer = Some(cue_value(rest)),
+            "INDEX" if in_track => {
+                let mut fields = rest.split_whitespace();
+                let number = fields.next().and_then(|n| n.parse::<u32>().ok());
+                let Some(time) = fields.next().and_then(cue_time) else {
+                    eprintln!("Unreadable cue index, ignored: {}", line);
+                    continue;
+                };
+                let track = tracks.last_mut().unwrap();
+                match number {
+                    Some(0) => track.pregap = Some(time),
+                    Some(1) => track.start = Some(time),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut entries = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let first_in_file = i == 0 || tracks[i - 1].file != track.file;
+        let Some(start) = track
+            .start
+            .or(track.pregap)
+            .or(first_in_file.then_some(0.0))
+        else {
+            eprintln!("Cue track {} has no INDEX, skipped", track.number);
+            continue;
+        };
+        let end = tracks[i + 1..]
+            .iter()
+            .take_while(|next| next.file == track.file)
+            .find_map(|next| next.start.or(next.pregap))
+            .filter(|end| *end > start);
+        let title = track
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Track {:02}", track.number));
+        let performer = track.performer.as_ref().or(album_performer.as_ref());
+        entries.push(PlaylistEntry {
+            path: track.file.to_string_lossy().into_owned(),
+            title: Some(match performer {
+                Some(performer) => format!("{} - {}", performer, title),
+                None => title,
+            }),
+            start: Some(start),
+            end,
+        });
+    }
+    entries
+}
+
+/// A quoted or bare argument (the file type after a `FILE` name is dropped)
+fn cue_value(rest: &str) -> String {
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => rest
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// `mm:ss:ff` (75 frames per second) in seconds
+fn cue_time(value: &str) -> Option<f64> {
+    let mut parts = value.split(':').map(|p| p.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / CUE_FRAMES_PER_SECOND)
+}
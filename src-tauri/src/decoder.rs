@@ -1,14 +1,129 @@
+use crate::adjustments::VideoAdjustments;
+use crate::attachments::{self, SubtitleFont};
+use crate::audio_output::{AudioBuffer, DEFAULT_OUTPUT_SAMPLE_RATE, OUTPUT_CHANNELS};
+use crate::cache::{
+    CacheMode, CacheStatus, CachedItem, FullCache, DEFAULT_CACHE_MAX_BYTES,
+    MAX_FULL_DECODE_DURATION,
+};
+use crate::channels::{cancel_center, center_channel, mute_channels, VocalRemovalMode};
+use crate::clipping::{ClipMonitor, ClipStats};
+use crate::clock::{system_clock, SharedClock};
+use crate::deinterlace::{stream_interlaced, DeinterlaceMode, Deinterlacer};
+use crate::dsp::DspHook;
+use crate::effects::{downmix_mono, AudioEffects, AutoBalance, BalanceCorrection, EffectsChain};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::fileio::{self, FileIo, IoSettings};
+use crate::letterbox::Letterbox;
+use crate::looping::LoopRegion;
+use crate::probe::{self, MediaInfo};
+use crate::recorder::FileSink;
+use crate::speed::Tempo;
+use crate::sync::{
+    AudioClock, DegradationMode, RateMeter, SyncClock, SyncMode, SyncStats, VideoDecision,
+};
+use crate::timebase::{
+    format_duration, format_time, micros_to_seconds, pts_to_micros, pts_to_seconds,
+    seconds_to_micros,
+};
+use crate::tonemap::{bit_depth, ColorInfo, HdrTransfer, PixelFormat, VideoConverter};
 use anyhow::{Context, Result};
 use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use ffmpeg_next as ffmpeg;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Default limit on decoded media queued ahead of the playback clock, in seconds
+pub const DEFAULT_BUFFER_AHEAD: f64 = 2.0;
+/// Range accepted by `set_buffer_ahead`, in seconds
+pub const MIN_BUFFER_AHEAD: f64 = 0.1;
+pub const MAX_BUFFER_AHEAD: f64 = 30.0;
+/// Range accepted by `set_max_fps`, in frames per second
+pub const MAX_FPS_RANGE: std::ops::RangeInclusive<f64> = 1.0..=1000.0;
+/// Silence held back by the skip-silence gate before it is played after all
+/// (a pause too long to be an intro or outro, or an entirely silent file)
+const MAX_HELD_SILENCE: f64 = 30.0;
+/// Largest lip-sync offset accepted by `set_av_offset`, in milliseconds
+pub const MAX_AV_OFFSET_MS: f64 = 2000.0;
+/// Decoding resumes once the queue drains below this fraction of the limit
+const BUFFER_LOW_WATER: f64 = 0.5;
+/// Audio kept decoded ahead under `DegradationMode::QualityFirst`, relative to `buffer_ahead`
+const QUALITY_FIRST_AUDIO_FACTOR: f64 = 2.0;
+/// Capture/playback rate ratio from which video counts as slow motion, so
+/// rounding of the declared rates is not mistaken for it
+const SLOW_MOTION_MIN_FACTOR: f64 = 1.05;
+/// Maximum decoded video frames waiting for presentation (RGB frames are large)
+const MAX_QUEUED_FRAMES: usize = 4;
+/// Upper bound for the reconnect backoff
+const MAX_RECONNECT_BACKOFF_MS: u64 = 8000;
+/// Wait before reading again when the input has nothing ready (EAGAIN)
+const READ_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(5);
+/// Failed packet reads in a row after which the input counts as unreadable
+const MAX_READ_FAILURES: u32 = 16;
+/// Read errors of a network source that mean the connection is gone
+const CONNECTION_ERRNOS: &[i32] = &[
+    ffmpeg::error::EIO,
+    ffmpeg::error::ECONNRESET,
+    ffmpeg::error::ECONNABORTED,
+    ffmpeg::error::ECONNREFUSED,
+    ffmpeg::error::ENOTCONN,
+    ffmpeg::error::EPIPE,
+    ffmpeg::error::ETIMEDOUT,
+    ffmpeg::error::ENETDOWN,
+    ffmpeg::error::ENETRESET,
+    ffmpeg::error::ENETUNREACH,
+    ffmpeg::error::EHOSTUNREACH,
+];
+/// Probe limits for the second attempt when the default probe is inconclusive
+const RETRY_PROBE_SIZE: &str = "50000000";
+const RETRY_ANALYZE_DURATION_US: &str = "30000000";
+/// Capacity of each frame used to drain the resampler at end of stream
+const RESAMPLER_FLUSH_SAMPLES: usize = 4096;
+/// Largest step back in video timestamps taken for frames decoded out of
+/// order; larger ones are discontinuities (timestamp resets) and keep
+/// decode order
+const VIDEO_REORDER_SPAN: f64 = 1.0;
+/// Frames in a row the resampler may reject before it is rebuilt for them
+const RESAMPLER_FAILURE_LIMIT: u32 = 3;
+/// Extra time allowed for queued audio to play out at end of stream
+const END_DRAIN_GRACE: f64 = 1.0;
+/// Packets read after a fast seek while looking for the landing keyframe
+const SEEK_PROBE_PACKETS: usize = 256;
+/// Packets read after a load while looking for the first video frame
+const FIRST_FRAME_PACKETS: usize = 1024;
+/// How often `PlaybackProgress` is emitted while playing
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// Video frames this close before an accurate seek target are still shown
+pub const SEEK_TOLERANCE: f64 = 0.001;
+/// Loudness range covered by the volume control, in dB
+const VOLUME_RANGE_DB: f32 = 60.0;
+
+/// `position` as a fraction (0.0 to 1.0) of `duration`, if the duration is known
+pub fn position_fraction(position: f64, duration: f64) -> Option<f64> {
+    (duration > 0.0 && duration.is_finite()).then(|| (position / duration).clamp(0.0, 1.0))
+}
+
+/// RMS level of samples in dB relative to full scale (`-inf` for silence)
+fn rms_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    10.0 * power.log10()
+}
 
 /// Video frame data
 #[derive(Clone, Debug, serde::Serialize)]
 pub struct VideoFrame {
     pub width: u32,
     pub height: u32,
-    pub data: Vec<u8>, // RGBA data
+    pub data: Vec<u8>, // tightly packed rows of `format` pixels
+    /// Byte layout of `data`
+    pub format: PixelFormat,
     pub timestamp: f64,
+    /// Range, primaries, transfer and matrix of the source frame
+    pub color: ColorInfo,
 }
 
 /// Audio frame data
@@ -28,19 +143,577 @@ pub enum FrameData {
 
 /// Commands sent to decoder thread
 pub enum DecoderCommand {
-    Load(String, Option<Sender<VideoFrame>>), // path + optional video frame sender
+    Load(String, Option<Sender<VideoFrame>>, Option<AudioBuffer>), // path + optional video frame sender / audio sample buffer
     Play,
     Pause,
     Stop,
-    Seek(f64),
+    /// Decode and send the frame at the current position while not playing;
+    /// `true` also raises `FirstFrame` (right after a load)
+    ShowFrame(bool),
+    /// Seek to a time; the optional sender receives the position it landed on
+    Seek(f64, Option<Sender<f64>>),
+    SetSeekMode(SeekMode),
+    SetBufferAhead(f64),
+    /// Emit at most one frame per `1 / fps` refresh interval (`None`: every frame)
+    SetMaxFps(Option<f64>),
+    SetPassthrough(bool),
+    /// Whether an output device is consuming the audio buffer
+    SetAudioOutputConnected(bool),
     SetVolume(f32),
+    SetLiveMode(bool),
+    SetSyncMode(SyncMode),
+    /// Play slow-motion video at the speed it was recorded at
+    SetRealTimeMotion(bool),
+    /// Media seconds played per second, from the next samples on
+    SetPlaybackSpeed(f64),
+    SetDegradationMode(DegradationMode),
+    /// Cache mode and memory cap (bytes) of `FullDecode`
+    SetCacheMode(CacheMode, u64),
+    /// Lip-sync offset in seconds (positive delays video)
+    SetAvOffset(f64),
+    /// RMS level (dB) below which leading/trailing audio is skipped; `None` disables
+    SetSkipSilence(Option<f32>),
+    /// Out point in seconds for the loaded file; cleared by the next load
+    SetOutPoint(Option<f64>),
+    /// Play a region over and over; cleared by the next load
+    SetLoop(Option<LoopRegion>),
+    SetVideoAdjustments(VideoAdjustments),
+    /// Pad frames to a fixed aspect ratio; `None` sends them at the source size
+    SetLetterbox(Option<Letterbox>),
+    SetDeinterlace(DeinterlaceMode),
+    SetOutputSampleRate(u32),
+    /// Record the output to a file, and whether the device still plays it
+    SetOutputSink(Option<FileSink>, bool),
+    SetReconnectPolicy(ReconnectPolicy),
+    SetEffects(AudioEffects),
+    /// Match the level of the stereo channels, measured at the start of each file
+    SetAutoBalance(bool),
+    /// Sum both channels after the effects and play the sum on each
+    SetMonoOutput(bool),
+    SetClipDetection(bool),
+    SetVocalRemoval(bool),
+    /// Source channels to silence, bit 0 being the first channel
+    SetChannelMute(u64),
+    /// Turn every audio processing stage above off at once
+    ResetAudioEffects,
+    SetToneMapping(bool),
+    SetOutputPixelFormat(PixelFormat),
+    SetPreferredAudioLanguage(Option<String>),
+    SetIoSettings(IoSettings),
+    /// Seek to a fraction of the file, snapping to a keyframe if asked
+    SeekPercent(f64, bool, Sender<Result<f64, PlayerError>>),
+    CycleAudioTrack(Sender<Result<AudioTrack, PlayerError>>),
+    SetVideoTrack(usize, Sender<Result<VideoStreamInfo, PlayerError>>),
+    AddDspHook(Box<dyn DspHook>),
+}
+
+/// How network sources recover from read errors
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReconnectPolicy {
+    /// Re-open attempts before giving up (0 disables reconnecting)
+    pub max_attempts: u32,
+    /// Delay before the first attempt; doubled after each failure
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+        }
+    }
+}
+
+/// How precisely seeks land on the requested time
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SeekMode {
+    /// Play from the keyframe at or before the target
+    Fast,
+    /// Decode from the keyframe but discard everything before the target
+    #[default]
+    Accurate,
+}
+
+/// URL schemes that are treated as real-time sources
+const LIVE_SCHEMES: &[&str] = &["rtsp://", "rtmp://", "udp://"];
+/// Path prefix of capture devices (V4L2 cameras and grabbers)
+const CAPTURE_DEVICE_PREFIX: &str = "/dev/video";
+
+/// Whether a path is a real-time source (RTSP, RTMP, UDP or a capture
+/// device). Other streams, such as a file served over plain TCP, may well be
+/// seekable recordings; they only play live with `set_live_mode`.
+pub fn is_live_source(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    LIVE_SCHEMES.iter().any(|scheme| lower.starts_with(scheme))
+        || path.starts_with(CAPTURE_DEVICE_PREFIX)
+}
+
+/// Seconds from a load to when its media was opened and to the first decoded
+/// video frame and audio sample (`None` until decoded or without the stream)
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct LoadTimings {
+    /// Opening and probing the input and setting up the decoders
+    pub open: f64,
+    pub first_video_frame: Option<f64>,
+    pub first_audio_sample: Option<f64>,
+}
+
+/// Items the decoder has sent on that are still waiting in their channels,
+/// sampled on the progress tick: empty channels mean the consumers starve,
+/// growing ones that they fall behind
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct QueueDepth {
+    /// Video frames not yet taken by the frame emitter
+    pub video_frames: usize,
+    /// Sample chunks not yet taken by the audio device
+    pub audio_chunks: usize,
+    /// `FrameData` items not yet taken (audio while no device is open, end
+    /// of file)
+    pub frame_data: usize,
+}
+
+/// Bytes read from a network source so far, against its length if known
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct NetworkStats {
+    pub bytes_read: u64,
+    /// `None` for chunked and live streams
+    pub total_bytes: Option<u64>,
+}
+
+impl NetworkStats {
+    /// Read the counters of the input's I/O context
+    fn from_input(ictx: &ffmpeg::format::context::Input, live: bool) -> Option<Self> {
+        unsafe {
+            let pb = (*ictx.as_ptr()).pb;
+            if pb.is_null() {
+                return None;
+            }
+            let size = ffmpeg::ffi::avio_size(pb);
+            Some(Self {
+                bytes_read: (*pb).bytes_read.max(0) as u64,
+                total_bytes: (size > 0 && !live).then_some(size as u64),
+            })
+        }
+    }
+}
+
+/// Gain for a volume control position in 0..=1.
+///
+/// The position maps logarithmically onto `VOLUME_RANGE_DB`, so equal steps
+/// sound equally loud anywhere on the scale; 0 is silence.
+pub fn volume_gain(volume: f32) -> f32 {
+    if volume <= 0.0 {
+        0.0
+    } else {
+        10f32.powf((volume.min(1.0) - 1.0) * VOLUME_RANGE_DB / 20.0)
+    }
+}
+
+/// Whether a path is read over the network (and may recover by re-opening)
+pub fn is_network_source(path: &str) -> bool {
+    path.contains("://") && !path.to_ascii_lowercase().starts_with("file://")
+}
+
+/// Reasons a decoder may play a stream poorly, for `compatibility-warning` events
+fn compatibility_warnings(decoder: &ffmpeg::decoder::Opened) -> Vec<String> {
+    use ffmpeg::codec::profile::{Profile, AAC, H264, HEVC, MPEG4};
+
+    let mut warnings = Vec::new();
+    if let Some(codec) = decoder.codec() {
+        if codec
+            .capabilities()
+            .contains(ffmpeg::codec::capabilities::Capabilities::EXPERIMENTAL)
+        {
+            warnings.push(format!("The {} decoder is experimental", codec.name()));
+        }
+    }
+
+    let profile_warning = match decoder.profile() {
+        Profile::H264(
+            H264::High422
+            | H264::High422Intra
+            | H264::High444
+            | H264::High444Predictive
+            | H264::High444Intra
+            | H264::CAVLC444,
+        ) => Some("4:2:2/4:4:4 H.264 is slow to decode and may stutter"),
+        Profile::HEVC(HEVC::Rext) => {
+            Some("HEVC range extensions are slow to decode and may stutter")
+        }
+        Profile::MPEG4(MPEG4::SimpleStudio) => {
+            Some("MPEG-4 Studio profile is only partly supported")
+        }
+        Profile::AAC(AAC::SSR) => Some("AAC SSR profile is not supported and may be silent"),
+        Profile::AAC(AAC::LTP) => Some("AAC LTP profile is rarely supported"),
+        _ => None,
+    };
+    if let Some(warning) = profile_warning {
+        warnings.push(format!("{} ({:?})", warning, decoder.profile()));
+    }
+    warnings
+}
+
+/// Whether a video stream is an embedded picture (album art) rather than video
+pub fn is_cover_art(stream: &ffmpeg::format::stream::Stream) -> bool {
+    stream
+        .disposition()
+        .contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+}
+
+/// Pick the initial stream of `medium`.
+///
+/// Prefers a stream tagged with `language` (ISO 639 code, case-insensitive),
+/// then one with the `DEFAULT` disposition, then the first one. Cover art is
+/// never picked as video.
+pub fn choose_stream(
+    ictx: &ffmpeg::format::context::Input,
+    medium: ffmpeg::media::Type,
+    language: Option<&str>,
+) -> Option<usize> {
+    let candidates: Vec<_> = ictx
+        .streams()
+        .filter(|s| s.parameters().medium() == medium && !is_cover_art(s))
+        .collect();
+
+    let by_language = language.and_then(|language| {
+        candidates.iter().find(|s| {
+            s.metadata()
+                .get("language")
+                .is_some_and(|l| l.eq_ignore_ascii_case(language))
+        })
+    });
+    let by_default = || {
+        candidates.iter().find(|s| {
+            s.disposition()
+                .contains(ffmpeg::format::stream::Disposition::DEFAULT)
+        })
+    };
+
+    by_language
+        .or_else(by_default)
+        .or(candidates.first())
+        .map(|s| s.index())
+}
+
+/// Whether probing found usable parameters for every audio and video stream
+fn probe_complete(ictx: &ffmpeg::format::context::Input) -> bool {
+    ictx.streams().all(|stream| {
+        let parameters = stream.parameters();
+        let raw = unsafe { &*parameters.as_ptr() };
+        match parameters.medium() {
+            ffmpeg::media::Type::Audio => {
+                parameters.id() != ffmpeg::codec::Id::None && raw.sample_rate > 0
+            }
+            ffmpeg::media::Type::Video => {
+                parameters.id() != ffmpeg::codec::Id::None && raw.width > 0 && raw.height > 0
+            }
+            _ => true,
+        }
+    })
+}
+
+/// Why video stream `idx` of `width`x`height` cannot be shown, if it cannot
+fn check_video_dimensions(idx: usize, width: u32, height: u32) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err(format!(
+            "Video stream {} has invalid dimensions {}x{}",
+            idx, width, height
+        ));
+    }
+    Ok(())
+}
+
+/// How many times slower than recorded video captured at `capture` fps plays
+/// at `playback` fps, if it counts as slow motion
+fn slow_motion_factor(capture: Option<f64>, playback: Option<f64>) -> Option<f64> {
+    capture
+        .zip(playback)
+        .map(|(capture, playback)| capture / playback)
+        .filter(|factor| *factor > SLOW_MOTION_MIN_FACTOR)
+}
+
+/// Whether opening failed because the probe saw too little of the file,
+/// rather than because the file is missing or unreadable
+fn probe_inconclusive(error: &ffmpeg::Error) -> bool {
+    matches!(
+        error,
+        ffmpeg::Error::InvalidData | ffmpeg::Error::StreamNotFound | ffmpeg::Error::DemuxerNotFound
+    )
+}
+
+/// What a failed packet read (other than the end of the input) calls for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadFailure {
+    /// Nothing is ready yet; read again
+    Retry,
+    /// The network connection is lost; re-open the input
+    Reconnect,
+    /// Skip it like a corrupt packet; many in a row end playback
+    Skip,
+}
+
+fn read_failure(error: &ffmpeg::Error, network: bool) -> ReadFailure {
+    match *error {
+        ffmpeg::Error::Other { errno } if errno == ffmpeg::error::EAGAIN => ReadFailure::Retry,
+        ffmpeg::Error::Other { errno } if network && CONNECTION_ERRNOS.contains(&errno) => {
+            ReadFailure::Reconnect
+        }
+        _ => ReadFailure::Skip,
+    }
+}
+
+/// Open a file by probing its content (the extension is only a hint).
+///
+/// If the format cannot be detected, or some streams still lack parameters
+/// after the default probe, the file is probed once more with much larger
+/// `probesize`/`analyzeduration` limits before giving up.
+pub fn open_probed(path: &str) -> Result<ffmpeg::format::context::Input, ffmpeg::Error> {
+    let first = ffmpeg::format::input(&path);
+    let retry = match first {
+        Ok(ref ictx) => !probe_complete(ictx),
+        Err(ref e) => probe_inconclusive(e),
+    };
+    if !retry {
+        return first;
+    }
+
+    let mut options = ffmpeg::Dictionary::new();
+    options.set("probesize", RETRY_PROBE_SIZE);
+    options.set("analyzeduration", RETRY_ANALYZE_DURATION_US);
+    match ffmpeg::format::input_with_dictionary(&path, options) {
+        Ok(ictx) => Ok(ictx),
+        // Keep the first result (possibly an incomplete but usable input)
+        Err(e) => first.or(Err(e)),
+    }
+}
+
+/// Whether `path` is a local file with at least one audio or video stream
+pub fn is_media_file(path: &str) -> bool {
+    let _ = ffmpeg::init();
+    std::path::Path::new(path).is_file()
+        && open_probed(path).is_ok_and(|ictx| {
+            ictx.streams().any(|stream| {
+                matches!(
+                    stream.parameters().medium(),
+                    ffmpeg::media::Type::Audio | ffmpeg::media::Type::Video
+                )
+            })
+        })
+}
+
+/// Media time origin: the earliest start time of the audio/video streams.
+///
+/// Some files start at a nonzero timestamp; subtracting this keeps frame
+/// timestamps, the clock and the scrubber in seconds from the start of media,
+/// while preserving the offset between streams. The origin is in microseconds,
+/// so it is subtracted from timestamps before they are turned into seconds.
+pub fn media_start_time(ictx: &ffmpeg::format::context::Input) -> i64 {
+    ictx.streams()
+        .filter(|stream| {
+            matches!(
+                stream.parameters().medium(),
+                ffmpeg::media::Type::Audio | ffmpeg::media::Type::Video
+            )
+        })
+        .filter(|stream| stream.start_time() != ffmpeg::ffi::AV_NOPTS_VALUE)
+        .map(|stream| pts_to_micros(stream.start_time(), stream.time_base()))
+        .min()
+        .unwrap_or(0)
 }
 
 /// Decoder thread handle
 pub struct MediaDecoder {
     command_sender: Sender<DecoderCommand>,
     frame_receiver: Receiver<FrameData>,
-    info_receiver: Receiver<DecoderInfo>,
+    info_receiver: Receiver<Result<DecoderInfo, PlayerError>>,
+    audio_clock: Arc<AudioClock>,
+    sync_stats: Arc<Mutex<SyncStats>>,
+    event_sender: Sender<PlayerEvent>,
+    event_receiver: Receiver<PlayerEvent>,
+    display: Arc<Mutex<DisplayDimensions>>,
+    video_size: Arc<Mutex<(u32, u32)>>,
+    decode_backend: Arc<Mutex<Option<DecodeBackend>>>,
+    deinterlacing: Arc<Mutex<bool>>,
+    network_stats: Arc<Mutex<Option<NetworkStats>>>,
+    load_timings: Arc<Mutex<LoadTimings>>,
+    cache_status: Arc<Mutex<CacheStatus>>,
+    vocal_removal: Arc<Mutex<VocalRemovalMode>>,
+    auto_balance: Arc<Mutex<Option<BalanceCorrection>>>,
+    clip_stats: Arc<Mutex<ClipStats>>,
+    queue_depth: Arc<Mutex<QueueDepth>>,
+    clock: SharedClock,
+}
+
+/// What actually decodes the video stream
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+pub enum DecodeBackend {
+    Software,
+    VideoToolbox,
+    Vaapi,
+    Vdpau,
+    /// NVDEC/CUVID
+    Cuda,
+    Qsv,
+    D3d11va,
+    Dxva2,
+    MediaCodec,
+    V4l2m2m,
+    DrmPrime,
+    Mmal,
+}
+
+impl DecodeBackend {
+    /// Backend implied by the decoder implementation (e.g. `h264_cuvid`)
+    fn for_codec(name: &str) -> Self {
+        match name.rsplit('_').next() {
+            Some("cuvid" | "nvdec") => DecodeBackend::Cuda,
+            Some("qsv") => DecodeBackend::Qsv,
+            Some("videotoolbox") => DecodeBackend::VideoToolbox,
+            Some("vaapi") => DecodeBackend::Vaapi,
+            Some("mediacodec") => DecodeBackend::MediaCodec,
+            Some("v4l2m2m") => DecodeBackend::V4l2m2m,
+            Some("mmal") => DecodeBackend::Mmal,
+            _ => DecodeBackend::Software,
+        }
+    }
+
+    /// Backend that produced a frame in a hardware pixel format
+    fn for_frame(format: ffmpeg::format::Pixel) -> Option<Self> {
+        use ffmpeg::format::Pixel;
+
+        match format {
+            Pixel::VIDEOTOOLBOX => Some(DecodeBackend::VideoToolbox),
+            Pixel::VAAPI => Some(DecodeBackend::Vaapi),
+            Pixel::VDPAU => Some(DecodeBackend::Vdpau),
+            Pixel::CUDA => Some(DecodeBackend::Cuda),
+            Pixel::QSV => Some(DecodeBackend::Qsv),
+            Pixel::D3D11 => Some(DecodeBackend::D3d11va),
+            Pixel::DXVA2_VLD => Some(DecodeBackend::Dxva2),
+            Pixel::MEDIACODEC => Some(DecodeBackend::MediaCodec),
+            Pixel::DRM_PRIME => Some(DecodeBackend::DrmPrime),
+            Pixel::MMAL => Some(DecodeBackend::Mmal),
+            _ => None,
+        }
+    }
+}
+
+/// Size a video should be displayed at, after sample aspect ratio and rotation
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
+pub struct DisplayDimensions {
+    /// Display width in pixels (swapped with height for 90/270 degree rotation)
+    pub width: u32,
+    pub height: u32,
+    /// Clockwise rotation in degrees (0, 90, 180 or 270) the frontend should apply
+    pub rotation: u32,
+}
+
+impl DisplayDimensions {
+    /// Compute display dimensions for a coded size, sample aspect ratio and rotation
+    pub fn new(width: u32, height: u32, sar: ffmpeg::Rational, rotation: u32) -> Self {
+        let width = if sar.numerator() > 0 && sar.denominator() > 0 {
+            (width as u64 * sar.numerator() as u64 / sar.denominator() as u64) as u32
+        } else {
+            width
+        };
+        let (width, height) = if rotation % 180 == 90 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        Self {
+            width,
+            height,
+            rotation,
+        }
+    }
+}
+
+/// Clockwise rotation from a stream's display matrix, rounded to a multiple of 90
+pub fn stream_rotation(stream: &ffmpeg::format::stream::Stream) -> u32 {
+    for side_data in stream.side_data() {
+        if side_data.kind() != ffmpeg::codec::packet::side_data::Type::DisplayMatrix {
+            continue;
+        }
+        let bytes = side_data.data();
+        if bytes.len() < 36 {
+            continue;
+        }
+        let m: Vec<f64> = bytes
+            .chunks_exact(4)
+            .take(9)
+            .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        // Same as av_display_rotation_get (counterclockwise), negated
+        let scale0 = m[0].hypot(m[3]);
+        let scale1 = m[1].hypot(m[4]);
+        if scale0 == 0.0 || scale1 == 0.0 {
+            continue;
+        }
+        let ccw = (m[1] / scale1).atan2(m[0] / scale0).to_degrees();
+        let cw = (-ccw / 90.0).round() as i64 * 90;
+        return cw.rem_euclid(360) as u32;
+    }
+    0
+}
+
+/// Outcome of setting up the audio or video stream of a file
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(tag = "state", content = "error", rename_all = "snake_case")]
+pub enum StreamStatus {
+    /// The file has no stream of this kind
+    #[default]
+    Missing,
+    /// The stream is decoding
+    Ready,
+    /// The stream exists but its decoder or converter could not be set up
+    Failed(String),
+}
+
+/// Which streams of a loaded file are playable
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct ReadySummary {
+    pub audio: StreamStatus,
+    pub video: StreamStatus,
+}
+
+/// An audio stream of the loaded file
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AudioTrack {
+    pub stream_index: usize,
+    /// ISO 639 code from the stream metadata
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub codec: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// A video stream of the loaded file
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct VideoTrack {
+    pub stream_index: usize,
+    /// ISO 639 code from the stream metadata
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: Option<f64>,
+    /// This is the stream playing
+    pub active: bool,
+}
+
+/// Picture properties of the video stream set up for playback
+#[derive(Clone, Debug, Default)]
+pub struct VideoStreamInfo {
+    pub width: u32,
+    pub height: u32,
+    pub display: DisplayDimensions,
+    pub hdr: bool,
+    pub bit_depth: u8,
+    pub color: Option<ColorInfo>,
+    pub slow_motion_factor: Option<f64>,
 }
 
 /// Decoder information
@@ -48,27 +721,103 @@ pub struct MediaDecoder {
 pub struct DecoderInfo {
     pub has_video: bool,
     pub has_audio: bool,
-    pub video_width: u32,
-    pub video_height: u32,
+    /// The file embeds cover art (an attached picture, not playable video)
+    pub has_cover_art: bool,
+    /// The video uses an HDR transfer function (PQ or HLG)
+    pub hdr: bool,
+    /// Bits per component of the decoded video (8, 10, 12, ...)
+    pub video_bit_depth: u8,
+    /// Color metadata of the video stream (`None` without video)
+    pub color: Option<ColorInfo>,
+    /// How much slower than recorded slow-motion video plays, if declared
+    pub slow_motion_factor: Option<f64>,
+    /// Indices of the playable video streams (cover art excluded)
+    pub video_streams: Vec<usize>,
+    /// The video stream playing, if any
+    pub video_stream_index: Option<usize>,
+    /// Container, tags, streams and chapters, as `probe` reports them
+    pub media: MediaInfo,
+    /// Fonts attached for styled subtitles
+    pub subtitle_fonts: Vec<SubtitleFont>,
+    /// Why a stream that exists is not playing, if it failed to set up
+    pub ready: ReadySummary,
+    /// Native rate of the audio stream (0 without audio)
+    pub audio_sample_rate: u32,
+    /// Why the audio cannot be passed through unchanged, if it cannot
+    pub passthrough_blocker: Option<String>,
+    /// How the file is read (the defaults when FFmpeg reads it itself)
+    pub io: IoSettings,
     pub duration: f64,
     pub file_path: Option<String>,
 }
 
 impl MediaDecoder {
     pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// Create a decoder whose pacing follows `clock`
+    pub fn with_clock(clock: SharedClock) -> Self {
         let (cmd_tx, cmd_rx) = bounded(32);
         let (frame_tx, frame_rx) = unbounded();
         let (info_tx, info_rx) = bounded(1);
+        let audio_clock = Arc::new(AudioClock::default());
+        let sync_stats = Arc::new(Mutex::new(SyncStats::default()));
+        let (event_tx, event_rx) = unbounded();
+        let display = Arc::new(Mutex::new(DisplayDimensions::default()));
+        let video_size = Arc::new(Mutex::new((0, 0)));
+        let decode_backend = Arc::new(Mutex::new(None));
+        let deinterlacing = Arc::new(Mutex::new(false));
+        let network_stats = Arc::new(Mutex::new(None));
+        let load_timings = Arc::new(Mutex::new(LoadTimings::default()));
+        let cache_status = Arc::new(Mutex::new(CacheStatus::default()));
+        let vocal_removal = Arc::new(Mutex::new(VocalRemovalMode::default()));
+        let auto_balance = Arc::new(Mutex::new(None));
+        let clip_stats = Arc::new(Mutex::new(ClipStats::default()));
+        let queue_depth = Arc::new(Mutex::new(QueueDepth::default()));
 
         // Spawn decoder thread
+        let shared = ThreadShared {
+            audio_clock: audio_clock.clone(),
+            sync_stats: sync_stats.clone(),
+            event_tx: event_tx.clone(),
+            display: display.clone(),
+            video_size: video_size.clone(),
+            decode_backend: decode_backend.clone(),
+            deinterlacing: deinterlacing.clone(),
+            network_stats: network_stats.clone(),
+            load_timings: load_timings.clone(),
+            cache_status: cache_status.clone(),
+            vocal_removal: vocal_removal.clone(),
+            auto_balance: auto_balance.clone(),
+            clip_stats: clip_stats.clone(),
+            queue_depth: queue_depth.clone(),
+            clock: clock.clone(),
+        };
         std::thread::spawn(move || {
-            decoder_thread(cmd_rx, frame_tx, info_tx);
+            decoder_thread(cmd_rx, frame_tx, info_tx, shared);
         });
 
         Self {
             command_sender: cmd_tx,
             frame_receiver: frame_rx,
             info_receiver: info_rx,
+            audio_clock,
+            sync_stats,
+            event_sender: event_tx,
+            event_receiver: event_rx,
+            display,
+            video_size,
+            decode_backend,
+            deinterlacing,
+            network_stats,
+            load_timings,
+            cache_status,
+            vocal_removal,
+            auto_balance,
+            clip_stats,
+            queue_depth,
+            clock,
         }
     }
 
@@ -76,14 +825,20 @@ impl MediaDecoder {
         &self,
         path: &str,
         video_sender: Option<Sender<VideoFrame>>,
+        audio_buffer: Option<AudioBuffer>,
     ) -> Result<DecoderInfo> {
         self.command_sender
-            .send(DecoderCommand::Load(path.to_string(), video_sender))
+            .send(DecoderCommand::Load(
+                path.to_string(),
+                video_sender,
+                audio_buffer,
+            ))
             .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
 
         // Wait for decoder info
         match self.info_receiver.recv() {
-            Ok(info) => Ok(info),
+            Ok(Ok(info)) => Ok(info),
+            Ok(Err(e)) => Err(e.into()),
             Err(_) => Err(anyhow::anyhow!("Decoder info channel closed")),
         }
     }
@@ -95,6 +850,13 @@ impl MediaDecoder {
         Ok(())
     }
 
+    pub fn show_frame(&self, first_frame: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::ShowFrame(first_frame))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
     pub fn pause(&self) -> Result<()> {
         self.command_sender
             .send(DecoderCommand::Pause)
@@ -109,9 +871,87 @@ impl MediaDecoder {
         Ok(())
     }
 
-    pub fn seek(&self, time: f64) -> Result<()> {
+    /// Seek and wait up to `timeout` for the decoder to apply it; returns the
+    /// position playback resumes from
+    pub fn seek_confirmed(&self, time: f64, timeout: std::time::Duration) -> Result<f64> {
+        let (ack, landed) = bounded(1);
+        self.command_sender
+            .send(DecoderCommand::Seek(time, Some(ack)))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+
+        match landed.recv_timeout(timeout) {
+            Ok(position) => Ok(position),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => Err(PlayerError::Internal(
+                "The decoder did not confirm the seek in time".to_string(),
+            )
+            .into()),
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                Err(anyhow::anyhow!("Decoder thread closed"))
+            }
+        }
+    }
+
+    /// Seek to `fraction` (0.0 to 1.0) of the file by time or, for files with
+    /// unreliable timestamps, by bytes; waits up to `timeout` for the position
+    /// playback resumes from
+    pub fn seek_percent(
+        &self,
+        fraction: f64,
+        snap_to_keyframe: bool,
+        timeout: std::time::Duration,
+    ) -> Result<f64> {
+        let (reply, response) = bounded(1);
+        self.command_sender
+            .send(DecoderCommand::SeekPercent(
+                fraction,
+                snap_to_keyframe,
+                reply,
+            ))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+
+        match response.recv_timeout(timeout) {
+            Ok(result) => Ok(result?),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => Err(PlayerError::Internal(
+                "The decoder did not confirm the seek in time".to_string(),
+            )
+            .into()),
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                Err(anyhow::anyhow!("Decoder thread closed"))
+            }
+        }
+    }
+
+    pub fn set_seek_mode(&self, mode: SeekMode) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetSeekMode(mode))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_buffer_ahead(&self, seconds: f64) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetBufferAhead(seconds))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_max_fps(&self, fps: Option<f64>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetMaxFps(fps))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_passthrough(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetPassthrough(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_audio_output_connected(&self, connected: bool) -> Result<()> {
         self.command_sender
-            .send(DecoderCommand::Seek(time))
+            .send(DecoderCommand::SetAudioOutputConnected(connected))
             .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
         Ok(())
     }
@@ -123,310 +963,3971 @@ impl MediaDecoder {
         Ok(())
     }
 
-    pub fn try_recv_frame(&self) -> Option<FrameData> {
-        self.frame_receiver.try_recv().ok()
+    pub fn set_live_mode(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetLiveMode(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
     }
 
-    pub fn recv_frame(&self) -> Result<FrameData> {
-        self.frame_receiver
-            .recv()
-            .map_err(|_| anyhow::anyhow!("Frame channel closed"))
+    pub fn set_real_time_motion(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetRealTimeMotion(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
     }
-}
 
-impl Default for MediaDecoder {
-    fn default() -> Self {
-        Self::new()
+    pub fn set_playback_speed(&self, speed: f64) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetPlaybackSpeed(speed))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
     }
-}
 
-/// Decoder thread function
-fn decoder_thread(
-    cmd_rx: Receiver<DecoderCommand>,
-    frame_tx: Sender<FrameData>,
-    info_tx: Sender<DecoderInfo>,
-) {
-    // Decoder state
-    let mut input_context: Option<ffmpeg::format::context::Input> = None;
-    let mut audio_decoder: Option<ffmpeg::decoder::Audio> = None;
-    let mut video_decoder: Option<ffmpeg::decoder::Video> = None;
-    let mut audio_resampler: Option<ffmpeg::software::resampling::context::Context> = None;
-    let mut video_scaler: Option<ffmpeg::software::scaling::context::Context> = None;
-    let mut audio_stream_index: Option<usize> = None;
-    let mut video_stream_index: Option<usize> = None;
-    let mut audio_time_base: Option<ffmpeg::Rational> = None;
-    let mut video_time_base: Option<ffmpeg::Rational> = None;
-    let mut volume: f32 = 0.8;
-    let mut is_playing = false;
-    let mut _file_path: Option<String> = None;
-    let mut duration: f64 = 0.0;
-    let mut has_video = false;
-    let mut has_audio = false;
-    let mut video_sender: Option<Sender<VideoFrame>> = None;
-
-    loop {
-        // Check for commands (non-blocking)
-        match cmd_rx.try_recv() {
-            Ok(DecoderCommand::Load(path, vsender)) => {
-                video_sender = vsender;
-                // Initialize FFmpeg
-                let _ = ffmpeg::init();
-
-                // Open file
-                match ffmpeg::format::input(&path) {
-                    Ok(mut ictx) => {
-                        // Find streams
-                        let mut audio_idx = None;
-                        let mut video_idx = None;
-
-                        for (i, stream) in ictx.streams().enumerate() {
-                            match stream.parameters().medium() {
-                                ffmpeg::media::Type::Audio if audio_idx.is_none() => {
-                                    audio_idx = Some(i);
-                                }
-                                ffmpeg::media::Type::Video if video_idx.is_none() => {
-                                    video_idx = Some(i);
-                                }
-                                _ => {}
-                            }
-                        }
+    pub fn set_sync_mode(&self, mode: SyncMode) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetSyncMode(mode))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
 
-                        // Setup audio decoder
-                        if let Some(idx) = audio_idx {
-                            let stream = ictx.stream(idx).unwrap();
-                            let codec_params = stream.parameters();
-                            audio_time_base = Some(stream.time_base());
-
-                            let mut decoder_context = ffmpeg::codec::Context::new();
-                            if decoder_context.set_parameters(codec_params).is_ok() {
-                                if let Ok(decoder) = decoder_context.decoder().audio() {
-                                    // Create resampler
-                                    if let Ok(resampler) =
-                                        ffmpeg::software::resampling::context::Context::get(
-                                            decoder.format(),
-                                            decoder.channel_layout(),
-                                            decoder.rate(),
-                                            ffmpeg::format::Sample::F32(
-                                                ffmpeg::format::sample::Type::Planar,
-                                            ),
-                                            ffmpeg::channel_layout::ChannelLayout::STEREO,
-                                            44100,
-                                        )
-                                    {
-                                        audio_decoder = Some(decoder);
-                                        audio_resampler = Some(resampler);
-                                        audio_stream_index = Some(idx);
-                                        has_audio = true;
-                                    }
-                                }
-                            }
-                        }
+    pub fn set_degradation_mode(&self, mode: DegradationMode) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetDegradationMode(mode))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
 
-                        // Setup video decoder
-                        let mut video_width = 0;
-                        let mut video_height = 0;
-                        if let Some(idx) = video_idx {
-                            let stream = ictx.stream(idx).unwrap();
-                            let codec_params = stream.parameters();
-                            video_time_base = Some(stream.time_base());
-
-                            let mut decoder_context = ffmpeg::codec::Context::new();
-                            if decoder_context.set_parameters(codec_params).is_ok() {
-                                if let Ok(decoder) = decoder_context.decoder().video() {
-                                    video_width = decoder.width();
-                                    video_height = decoder.height();
-
-                                    // Create scaler
-                                    if let Ok(scaler) =
-                                        ffmpeg::software::scaling::context::Context::get(
-                                            decoder.format(),
-                                            decoder.width(),
-                                            decoder.height(),
-                                            ffmpeg::format::Pixel::RGBA,
-                                            decoder.width(),
-                                            decoder.height(),
-                                            ffmpeg::software::scaling::flag::Flags::BILINEAR,
-                                        )
-                                    {
-                                        video_decoder = Some(decoder);
-                                        video_scaler = Some(scaler);
-                                        video_stream_index = Some(idx);
-                                        has_video = true;
-                                    }
-                                }
-                            }
-                        }
+    pub fn set_cache_mode(&self, mode: CacheMode, max_bytes: u64) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetCacheMode(mode, max_bytes))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))
+    }
 
-                        duration = ictx.duration() as f64 / 1_000_000.0;
-                        _file_path = Some(path.clone());
-                        input_context = Some(ictx);
-
-                        // Send decoder info
-                        let info = DecoderInfo {
-                            has_video,
-                            has_audio,
-                            video_width,
-                            video_height,
-                            duration,
-                            file_path: Some(path),
-                        };
-                        let _ = info_tx.send(info);
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to open file: {}", e);
-                    }
-                }
-            }
-            Ok(DecoderCommand::Play) => {
-                is_playing = true;
-            }
-            Ok(DecoderCommand::Pause) => {
-                is_playing = false;
-            }
-            Ok(DecoderCommand::Stop) => {
-                is_playing = false;
-                // Reset decoders
-                input_context = None;
-                audio_decoder = None;
-                video_decoder = None;
-                audio_resampler = None;
-                video_scaler = None;
-            }
-            Ok(DecoderCommand::Seek(time)) => {
-                if let Some(ref mut ictx) = input_context {
-                    let timestamp = (time * 1_000_000.0) as i64;
-                    let _ = ictx.seek(timestamp, ..);
+    pub fn set_av_offset(&self, seconds: f64) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetAvOffset(seconds))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
 
-                    // Flush decoders
-                    if let Some(ref mut dec) = audio_decoder {
-                        dec.flush();
-                    }
-                    if let Some(ref mut dec) = video_decoder {
-                        dec.flush();
-                    }
-                }
-            }
-            Ok(DecoderCommand::SetVolume(v)) => {
-                volume = v.clamp(0.0, 1.0);
-            }
-            Err(crossbeam_channel::TryRecvError::Disconnected) => {
-                break;
-            }
-            Err(crossbeam_channel::TryRecvError::Empty) => {}
-        }
-
-        // Decode frames if playing
-        if is_playing {
-            if let Some(ref mut ictx) = input_context {
-                // Get next packet
-                match ictx.packets().next() {
-                    Some((stream, packet)) => {
-                        let stream_idx = stream.index();
-
-                        // Process audio packet
-                        if Some(stream_idx) == audio_stream_index {
-                            if let Some(ref mut decoder) = audio_decoder {
-                                if decoder.send_packet(&packet).is_ok() {
-                                    let mut frame = ffmpeg::frame::Audio::empty();
-                                    while decoder.receive_frame(&mut frame).is_ok() {
-                                        // Resample
-                                        if let Some(ref mut resampler) = audio_resampler {
-                                            let mut resampled = ffmpeg::frame::Audio::empty();
-                                            if resampler.run(&frame, &mut resampled).is_ok() {
-                                                // Extract samples
-                                                let sample_count = resampled.samples();
-                                                let channels =
-                                                    resampled.channel_layout().channels() as usize;
-                                                let mut samples =
-                                                    Vec::with_capacity(sample_count * channels);
-
-                                                for i in 0..sample_count {
-                                                    for ch in 0..channels.min(2) {
-                                                        let plane_data = resampled.data(ch);
-                                                        let offset = i * 4;
-                                                        if offset + 4 <= plane_data.len() {
-                                                            let bytes =
-                                                                &plane_data[offset..offset + 4];
-                                                            let value = f32::from_ne_bytes([
-                                                                bytes[0], bytes[1], bytes[2],
-                                                                bytes[3],
-                                                            ]);
-                                                            samples.push(value * volume);
-                                                        }
-                                                    }
-                                                }
-
-                                                let timestamp = frame
-                                                    .timestamp()
-                                                    .map(|ts| {
-                                                        ts as f64
-                                                            * f64::from(audio_time_base.unwrap())
-                                                    })
-                                                    .unwrap_or(0.0);
-
-                                                let _ =
-                                                    frame_tx.send(FrameData::Audio(AudioFrame {
-                                                        samples,
-                                                        timestamp,
-                                                    }));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+    pub fn set_letterbox(&self, letterbox: Option<Letterbox>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetLetterbox(letterbox))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
 
-                        // Process video packet
-                        if Some(stream_idx) == video_stream_index {
-                            if let Some(ref mut decoder) = video_decoder {
-                                if decoder.send_packet(&packet).is_ok() {
-                                    let mut frame = ffmpeg::frame::Video::empty();
-                                    while decoder.receive_frame(&mut frame).is_ok() {
-                                        // Scale to RGBA
-                                        if let Some(ref mut scaler) = video_scaler {
-                                            let mut scaled = ffmpeg::frame::Video::empty();
-                                            if scaler.run(&frame, &mut scaled).is_ok() {
-                                                let width = scaled.width();
-                                                let height = scaled.height();
-                                                let data = scaled.data(0).to_vec();
-
-                                                let timestamp = frame
-                                                    .timestamp()
-                                                    .map(|ts| {
-                                                        ts as f64
-                                                            * f64::from(video_time_base.unwrap())
-                                                    })
-                                                    .unwrap_or(0.0);
-
-                                                // Send video frame to frontend if sender is available
-                                                if let Some(ref sender) = video_sender {
-                                                    let _ = sender.send(VideoFrame {
-                                                        width,
-                                                        height,
-                                                        data,
-                                                        timestamp,
-                                                    });
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None => {
-                        // End of file
-                        let _ = frame_tx.send(FrameData::EndOfFile);
-                        is_playing = false;
-                    }
-                }
-            } else {
-                // No file loaded, yield
-                std::thread::sleep(std::time::Duration::from_millis(10));
-            }
-        } else {
-            // Not playing, yield
-            std::thread::sleep(std::time::Duration::from_millis(10));
-        }
+    pub fn set_video_adjustments(&self, adjustments: VideoAdjustments) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetVideoAdjustments(adjustments))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_out_point(&self, end: Option<f64>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetOutPoint(end))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_loop(&self, region: Option<LoopRegion>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetLoop(region))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_skip_silence(&self, threshold_db: Option<f32>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetSkipSilence(threshold_db))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_output_sample_rate(&self, rate: u32) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetOutputSampleRate(rate))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_output_sink(&self, file: Option<FileSink>, device: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetOutputSink(file, device))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))
+    }
+
+    pub fn set_reconnect_policy(&self, policy: ReconnectPolicy) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetReconnectPolicy(policy))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_tone_mapping(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetToneMapping(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_output_pixel_format(&self, format: PixelFormat) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetOutputPixelFormat(format))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_preferred_audio_language(&self, language: Option<String>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetPreferredAudioLanguage(language))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    /// Read buffering for local files, applied from the next load
+    pub fn set_io_settings(&self, settings: IoSettings) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetIoSettings(settings))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    /// Switch to the next audio stream (wrapping around), keeping the position
+    pub fn cycle_audio_track(&self) -> Result<AudioTrack> {
+        let (reply, response) = bounded(1);
+        self.command_sender
+            .send(DecoderCommand::CycleAudioTrack(reply))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+
+        match response.recv() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(anyhow::anyhow!("Decoder thread closed")),
+        }
+    }
+
+    /// Play video stream `index` instead, keeping the position
+    pub fn set_video_track(&self, index: usize) -> Result<VideoStreamInfo> {
+        let (reply, response) = bounded(1);
+        self.command_sender
+            .send(DecoderCommand::SetVideoTrack(index, reply))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+
+        match response.recv() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(anyhow::anyhow!("Decoder thread closed")),
+        }
+    }
+
+    pub fn add_dsp_hook(&self, hook: Box<dyn DspHook>) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::AddDspHook(hook))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_effects(&self, effects: AudioEffects) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetEffects(effects))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_mono_output(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetMonoOutput(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn set_clip_detection(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetClipDetection(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))
+    }
+
+    pub fn set_vocal_removal(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetVocalRemoval(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))
+    }
+
+    pub fn set_channel_mute(&self, mask: u64) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetChannelMute(mask))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))
+    }
+
+    pub fn set_auto_balance(&self, enabled: bool) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetAutoBalance(enabled))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn reset_audio_effects(&self) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::ResetAudioEffects)
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    /// Receiver for events to forward to the frontend
+    pub fn events(&self) -> Receiver<PlayerEvent> {
+        self.event_receiver.clone()
+    }
+
+    /// Sender for events raised outside the decoder thread
+    pub fn event_sender(&self) -> Sender<PlayerEvent> {
+        self.event_sender.clone()
+    }
+
+    /// Device clock shared with the audio output callback
+    pub fn audio_clock(&self) -> Arc<AudioClock> {
+        self.audio_clock.clone()
+    }
+
+    /// Clock the decoder paces by, for timing elsewhere in the player
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Latest A/V synchronization statistics
+    pub fn sync_stats(&self) -> SyncStats {
+        self.sync_stats.lock().clone()
+    }
+
+    /// Display size of the current video, kept up to date as frames are decoded
+    pub fn display_dimensions(&self) -> DisplayDimensions {
+        *self.display.lock()
+    }
+
+    /// Coded width and height of the current video, following resolution
+    /// changes mid-stream; zero without video
+    pub fn video_size(&self) -> (u32, u32) {
+        *self.video_size.lock()
+    }
+
+    /// Backend decoding the current video (`None` without video), following
+    /// fallbacks during playback
+    pub fn decode_backend(&self) -> Option<DecodeBackend> {
+        *self.decode_backend.lock()
+    }
+
+    /// Gains auto-balance applies to the current file (`None` while disabled
+    /// or still measuring)
+    pub fn auto_balance(&self) -> Option<BalanceCorrection> {
+        *self.auto_balance.lock()
+    }
+
+    /// Clipping counted in the output of the current file
+    pub fn clip_stats(&self) -> ClipStats {
+        *self.clip_stats.lock()
+    }
+
+    /// Undelivered items in the output channels at the last progress tick
+    pub fn queue_depth(&self) -> QueueDepth {
+        *self.queue_depth.lock()
+    }
+
+    /// Whether decoded video is currently being deinterlaced
+    pub fn deinterlacing(&self) -> bool {
+        *self.deinterlacing.lock()
+    }
+
+    /// Download progress of a network source (`None` for local files)
+    pub fn network_stats(&self) -> Option<NetworkStats> {
+        *self.network_stats.lock()
+    }
+
+    /// How long the current file took to open and to decode its first output
+    pub fn load_timings(&self) -> LoadTimings {
+        *self.load_timings.lock()
+    }
+
+    /// Cache mode in effect and whether the loaded file plays from memory
+    pub fn cache_status(&self) -> CacheStatus {
+        *self.cache_status.lock()
+    }
+
+    /// How vocals are removed from the current audio stream
+    pub fn vocal_removal(&self) -> VocalRemovalMode {
+        *self.vocal_removal.lock()
+    }
+
+    pub fn set_deinterlace(&self, mode: DeinterlaceMode) -> Result<()> {
+        self.command_sender
+            .send(DecoderCommand::SetDeinterlace(mode))
+            .map_err(|_| anyhow::anyhow!("Decoder thread closed"))?;
+        Ok(())
+    }
+
+    pub fn try_recv_frame(&self) -> Option<FrameData> {
+        self.frame_receiver.try_recv().ok()
+    }
+
+    pub fn recv_frame(&self) -> Result<FrameData> {
+        self.frame_receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Frame channel closed"))
+    }
+}
+
+impl Default for MediaDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decoder thread function
+fn decoder_thread(
+    cmd_rx: Receiver<DecoderCommand>,
+    frame_tx: Sender<FrameData>,
+    info_tx: Sender<Result<DecoderInfo, PlayerError>>,
+    shared: ThreadShared,
+) {
+    DecoderThread::new(cmd_rx, frame_tx, info_tx, shared).run();
+}
+
+/// Handles the decoder thread shares with its `MediaDecoder`
+struct ThreadShared {
+    audio_clock: Arc<AudioClock>,
+    sync_stats: Arc<Mutex<SyncStats>>,
+    event_tx: Sender<PlayerEvent>,
+    display: Arc<Mutex<DisplayDimensions>>,
+    video_size: Arc<Mutex<(u32, u32)>>,
+    decode_backend: Arc<Mutex<Option<DecodeBackend>>>,
+    deinterlacing: Arc<Mutex<bool>>,
+    network_stats: Arc<Mutex<Option<NetworkStats>>>,
+    load_timings: Arc<Mutex<LoadTimings>>,
+    cache_status: Arc<Mutex<CacheStatus>>,
+    vocal_removal: Arc<Mutex<VocalRemovalMode>>,
+    auto_balance: Arc<Mutex<Option<BalanceCorrection>>>,
+    clip_stats: Arc<Mutex<ClipStats>>,
+    queue_depth: Arc<Mutex<QueueDepth>>,
+    clock: SharedClock,
+}
+
+/// State owned by the decoder thread
+struct DecoderThread {
+    cmd_rx: Receiver<DecoderCommand>,
+    frame_tx: Sender<FrameData>,
+    info_tx: Sender<Result<DecoderInfo, PlayerError>>,
+    audio_clock: Arc<AudioClock>,
+    sync_stats: Arc<Mutex<SyncStats>>,
+    event_tx: Sender<PlayerEvent>,
+    display: Arc<Mutex<DisplayDimensions>>,
+    video_size: Arc<Mutex<(u32, u32)>>,
+    decode_backend: Arc<Mutex<Option<DecodeBackend>>>,
+    deinterlacing: Arc<Mutex<bool>>,
+    network_stats: Arc<Mutex<Option<NetworkStats>>>,
+    load_timings: Arc<Mutex<LoadTimings>>,
+    /// When the current load started, until its first outputs are timed
+    load_started: Option<std::time::Instant>,
+    cache_status: Arc<Mutex<CacheStatus>>,
+    cache_mode: CacheMode,
+    /// Memory cap of `FullDecode`, in bytes
+    cache_max_bytes: u64,
+    /// The whole file, decoded; playback reads it instead of the input
+    cache: Option<FullCache>,
+    /// Receives what the decoders produce while the cache is being filled
+    filling_cache: Option<FullCache>,
+    /// Published copy of `auto_balance.correction()`
+    shared_auto_balance: Arc<Mutex<Option<BalanceCorrection>>>,
+
+    // Decoder state
+    input_context: Option<ffmpeg::format::context::Input>,
+    /// Reads the file under `input_context` when custom buffering is set;
+    /// dropped after it
+    input_io: Option<FileIo>,
+    io_settings: IoSettings,
+    audio_decoder: Option<ffmpeg::decoder::Audio>,
+    video_decoder: Option<ffmpeg::decoder::Video>,
+    audio_resampler: Option<ffmpeg::software::resampling::context::Context>,
+    /// Frames in a row the resampler failed on (e.g. after a layout change)
+    resampler_failures: u32,
+    /// Scaler chain for the current frame format, rebuilt when frames change
+    video_converter: Option<VideoConverter>,
+    /// The scaler could not be built for this load's frames; video is dropped
+    converter_failed: bool,
+    /// The video decoder discards non-reference frames to catch up
+    skipping_frames: bool,
+    /// Capture rate over playback rate of slow-motion video, if declared
+    slow_motion_factor: Option<f64>,
+    /// Average frame rate of the video stream, what decoding has to keep up with
+    target_fps: Option<f64>,
+    decode_rate: RateMeter,
+    present_rate: RateMeter,
+    /// Cap on emitted frames per second, for displays slower than the video
+    max_fps: Option<f64>,
+    /// Refresh interval (timestamp times `max_fps`, rounded down) of the
+    /// last frame emitted under the cap
+    emitted_slot: Option<i64>,
+    /// Timestamp of the last frame presented since the last seek
+    last_presented: Option<f64>,
+    /// Speed slow-motion video up to its recorded speed
+    real_time_motion: bool,
+    /// Media seconds played per second of output
+    speed: f64,
+    /// `atempo` filter for speeds other than 1x, built on the first samples
+    /// that need it
+    tempo: Option<Tempo>,
+    /// Tone map HDR video to SDR
+    tone_mapping: bool,
+    /// Layout of the frames sent to the frontend
+    output_pixel_format: PixelFormat,
+    video_adjustments: VideoAdjustments,
+    letterbox: Option<Letterbox>,
+    deinterlace_mode: DeinterlaceMode,
+    /// Filter graph for the current video format, built on the first frame
+    /// that needs it
+    deinterlacer: Option<Deinterlacer>,
+    /// The filter could not be built for this load; frames pass unchanged
+    deinterlace_failed: bool,
+    /// The video stream is flagged as interlaced
+    video_interlaced: bool,
+    /// The loaded file is read over the network
+    network_source: bool,
+    /// Audio language chosen on load when the file has several audio tracks
+    preferred_audio_language: Option<String>,
+    audio_stream_index: Option<usize>,
+    video_stream_index: Option<usize>,
+    audio_time_base: Option<ffmpeg::Rational>,
+    video_time_base: Option<ffmpeg::Rational>,
+    /// Backend of the opened video decoder, before looking at frames
+    codec_backend: DecodeBackend,
+    /// Clockwise rotation of the video stream, from its display matrix
+    video_rotation: u32,
+    /// Linear gain derived from the volume control
+    volume: f32,
+    /// Send native-rate stereo frames to the device without resampling or effects
+    passthrough: bool,
+    is_playing: bool,
+    file_path: Option<String>,
+    duration: f64,
+    /// Timestamp (microseconds) that maps to position zero
+    start_time: i64,
+    has_video: bool,
+    has_audio: bool,
+    video_sender: Option<Sender<VideoFrame>>,
+    audio_buffer: Option<AudioBuffer>,
+    /// False while no device plays the buffer; audio is then decoded and dropped
+    /// so video runs on the wall clock
+    audio_output_connected: bool,
+    live_mode: bool,
+    output_sample_rate: u32,
+    effects: EffectsChain,
+    /// Runs before `effects`, so the manual balance applies on top
+    auto_balance: AutoBalance,
+    mono_output: bool,
+    clip_monitor: ClipMonitor,
+    /// Published copy of `clip_monitor.stats()`
+    clip_stats: Arc<Mutex<ClipStats>>,
+    queue_depth: Arc<Mutex<QueueDepth>>,
+    vocal_removal: bool,
+    /// Method in use for `vocal_removal` with the current audio stream
+    vocal_mode: VocalRemovalMode,
+    shared_vocal_removal: Arc<Mutex<VocalRemovalMode>>,
+    channel_mute: u64,
+    /// Source channels silenced before resampling: `channel_mute` plus the
+    /// center channel if vocals are removed that way
+    source_mute_mask: u64,
+    /// Recording of the output, fed the same samples as the device
+    file_sink: Option<FileSink>,
+    /// The device plays silence while only the file gets the audio
+    device_muted: bool,
+    /// User DSP, run in registration order after the equalizer
+    dsp_hooks: Vec<Box<dyn DspHook>>,
+    reconnect_policy: ReconnectPolicy,
+    /// Media time (without the loop offset) of the most recently decoded frame
+    /// or audio chunk, for resuming after a reconnect
+    last_timestamp: f64,
+    /// Packet reads that failed in a row
+    read_failures: u32,
+    seek_mode: SeekMode,
+    /// Media decoded before this time is discarded (accurate seeks)
+    seek_target: Option<f64>,
+
+    // Presentation state
+    sync: SyncClock,
+    /// Time source for pacing sleeps (shared with `sync`)
+    clock: SharedClock,
+    video_queue: VecDeque<VideoFrame>,
+    samples_sent: u64,
+    /// How far decoding may run ahead of the playback clock, in seconds
+    buffer_ahead: f64,
+    /// Decoding until the high water mark; cleared there until the low water mark
+    refilling: bool,
+    end_of_input: bool,
+    /// Latest time EndOfFile waits for queued audio to play out
+    drain_deadline: Option<std::time::Instant>,
+    /// End time of the furthest media decoded and queued, for buffered ranges
+    buffered_until: f64,
+    /// Timestamp of the last video frame sent for display
+    presented_time: f64,
+    last_progress: Option<std::time::Instant>,
+    /// Skip-silence threshold in dB, if enabled
+    skip_silence: Option<f32>,
+    /// Playback ends here (seconds) instead of at the end of the file
+    out_point: Option<f64>,
+    /// Region played over and over; overrides the out point while set
+    loop_region: Option<LoopRegion>,
+    /// Added to decoded timestamps so the presentation timeline keeps running
+    /// across loop passes (the loop length times the passes played)
+    loop_offset: f64,
+    /// `buffered_until` when the current pass started, to notice empty passes
+    loop_pass_start: f64,
+    /// Silent chunks held back until it is known whether they lead or trail
+    held_silence: Vec<(Vec<f32>, f64, bool)>,
+    /// Audible audio has been queued since the file was loaded
+    heard_audio: bool,
+}
+
+impl DecoderThread {
+    fn new(
+        cmd_rx: Receiver<DecoderCommand>,
+        frame_tx: Sender<FrameData>,
+        info_tx: Sender<Result<DecoderInfo, PlayerError>>,
+        shared: ThreadShared,
+    ) -> Self {
+        let ThreadShared {
+            audio_clock,
+            sync_stats,
+            event_tx,
+            display,
+            video_size,
+            decode_backend,
+            deinterlacing,
+            network_stats,
+            load_timings,
+            cache_status,
+            vocal_removal,
+            auto_balance,
+            clip_stats,
+            queue_depth,
+            clock,
+        } = shared;
+        Self {
+            cmd_rx,
+            frame_tx,
+            info_tx,
+            audio_clock,
+            sync_stats,
+            event_tx,
+            display,
+            video_size,
+            decode_backend,
+            deinterlacing,
+            network_stats,
+            load_timings,
+            load_started: None,
+            cache_status,
+            cache_mode: CacheMode::default(),
+            cache_max_bytes: DEFAULT_CACHE_MAX_BYTES,
+            cache: None,
+            filling_cache: None,
+            shared_auto_balance: auto_balance,
+            network_source: false,
+            codec_backend: DecodeBackend::Software,
+            input_context: None,
+            input_io: None,
+            io_settings: IoSettings::default(),
+            audio_decoder: None,
+            video_decoder: None,
+            audio_resampler: None,
+            resampler_failures: 0,
+            video_converter: None,
+            converter_failed: false,
+            skipping_frames: false,
+            slow_motion_factor: None,
+            target_fps: None,
+            decode_rate: RateMeter::default(),
+            present_rate: RateMeter::default(),
+            max_fps: None,
+            emitted_slot: None,
+            last_presented: None,
+            real_time_motion: false,
+            tone_mapping: true,
+            output_pixel_format: PixelFormat::default(),
+            video_adjustments: VideoAdjustments::default(),
+            letterbox: None,
+            deinterlace_mode: DeinterlaceMode::default(),
+            deinterlacer: None,
+            deinterlace_failed: false,
+            speed: 1.0,
+            tempo: None,
+            video_interlaced: false,
+            preferred_audio_language: None,
+            audio_stream_index: None,
+            video_stream_index: None,
+            audio_time_base: None,
+            video_time_base: None,
+            video_rotation: 0,
+            volume: volume_gain(0.8),
+            passthrough: false,
+            is_playing: false,
+            file_path: None,
+            duration: 0.0,
+            start_time: 0,
+            has_video: false,
+            has_audio: false,
+            video_sender: None,
+            audio_buffer: None,
+            audio_output_connected: true,
+            live_mode: false,
+            output_sample_rate: DEFAULT_OUTPUT_SAMPLE_RATE,
+            effects: EffectsChain::new(AudioEffects::default(), DEFAULT_OUTPUT_SAMPLE_RATE),
+            auto_balance: AutoBalance::new(DEFAULT_OUTPUT_SAMPLE_RATE),
+            mono_output: false,
+            clip_monitor: ClipMonitor::default(),
+            clip_stats,
+            queue_depth,
+            vocal_removal: false,
+            vocal_mode: VocalRemovalMode::Off,
+            shared_vocal_removal: vocal_removal,
+            channel_mute: 0,
+            source_mute_mask: 0,
+            file_sink: None,
+            device_muted: false,
+            dsp_hooks: Vec::new(),
+            reconnect_policy: ReconnectPolicy::default(),
+            last_timestamp: 0.0,
+            read_failures: 0,
+            seek_mode: SeekMode::default(),
+            seek_target: None,
+            sync: SyncClock::with_clock(clock.clone()),
+            clock,
+            video_queue: VecDeque::new(),
+            samples_sent: 0,
+            buffer_ahead: DEFAULT_BUFFER_AHEAD,
+            refilling: true,
+            end_of_input: false,
+            drain_deadline: None,
+            buffered_until: 0.0,
+            presented_time: 0.0,
+            last_progress: None,
+            skip_silence: None,
+            out_point: None,
+            loop_region: None,
+            loop_offset: 0.0,
+            loop_pass_start: 0.0,
+            held_silence: Vec::new(),
+            heard_audio: false,
+        }
+    }
+
+    /// Pause takes effect at packet boundaries: every queued command is applied
+    /// before the next packet is read, so a paused thread never advances the
+    /// demuxer (except for the packets a fast seek decodes to find its keyframe)
+    /// and play resumes with the packet right after the last decoded one.
+    /// Frames already decoded stay queued and audio already sent stays in the
+    /// device buffer.
+    fn run(mut self) {
+        loop {
+            // Apply every pending command before touching the input again
+            if !self.apply_pending_commands() {
+                return;
+            }
+
+            // Decode frames if playing
+            if self.is_playing && self.input_context.is_some() {
+                self.step();
+            } else {
+                // Not playing, wait for the next command
+                match self
+                    .cmd_rx
+                    .recv_timeout(std::time::Duration::from_millis(10))
+                {
+                    Ok(command) => self.handle_command(command),
+                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => return,
+                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+                }
+            }
+
+            // Publish sync statistics and buffer depth
+            let mut stats = self.sync.stats.clone();
+            stats.audio_buffered = self.audio_ahead();
+            stats.video_queued = self.video_queue.len();
+            stats.output_latency = self.audio_clock.latency() as f64 / self.samples_per_second();
+            stats.audio_latency = stats.audio_buffered + stats.output_latency;
+            let now = self.clock.now();
+            stats.decode_fps = self.decode_rate.rate(now);
+            stats.present_fps = self.present_rate.rate(now);
+            stats.target_fps = self.target_fps.filter(|_| self.has_video);
+            *self.sync_stats.lock() = stats;
+            *self.network_stats.lock() = self.current_network_stats();
+            self.report_progress();
+        }
+    }
+
+    /// Handle every queued command; false once the `MediaDecoder` is gone
+    fn apply_pending_commands(&mut self) -> bool {
+        loop {
+            match self.cmd_rx.try_recv() {
+                Ok(command) => self.handle_command(command),
+                Err(crossbeam_channel::TryRecvError::Disconnected) => return false,
+                Err(crossbeam_channel::TryRecvError::Empty) => return true,
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: DecoderCommand) {
+        match command {
+            DecoderCommand::Load(path, vsender, abuffer) => {
+                self.load(path, vsender, abuffer);
+            }
+            DecoderCommand::Play => {
+                self.is_playing = true;
+                self.sync.reanchor();
+            }
+            DecoderCommand::Pause => {
+                self.is_playing = false;
+                self.sync.reanchor();
+            }
+            DecoderCommand::Stop => self.stop(),
+            DecoderCommand::ShowFrame(first_frame) => self.show_frame(first_frame),
+            DecoderCommand::Seek(time, ack) => {
+                let landed = self.seek(time);
+                if let Some(ack) = ack {
+                    let _ = ack.send(landed);
+                }
+            }
+            DecoderCommand::SeekPercent(fraction, snap, reply) => {
+                let _ = reply.send(self.seek_percent(fraction, snap));
+            }
+            DecoderCommand::SetSeekMode(mode) => {
+                // Applies from the next seek
+                self.seek_mode = mode;
+            }
+            DecoderCommand::SetBufferAhead(seconds) => {
+                self.buffer_ahead = seconds;
+            }
+            DecoderCommand::SetMaxFps(fps) => {
+                self.max_fps = fps;
+                self.emitted_slot = None;
+            }
+            DecoderCommand::SetPassthrough(enabled) => {
+                self.passthrough = enabled;
+                // Cached audio went through the resampler
+                if enabled && self.cache.is_some() {
+                    self.refill_cache_or_seek(self.presented_time);
+                }
+            }
+            DecoderCommand::SetAudioOutputConnected(connected) => {
+                self.audio_output_connected = connected;
+            }
+            DecoderCommand::SetVolume(v) => {
+                self.volume = volume_gain(v);
+            }
+            DecoderCommand::SetLiveMode(enabled) => {
+                self.live_mode = enabled;
+            }
+            DecoderCommand::SetSyncMode(mode) => {
+                self.sync.set_mode(mode);
+            }
+            DecoderCommand::SetRealTimeMotion(enabled) => {
+                self.real_time_motion = enabled;
+                self.apply_motion_rate();
+            }
+            DecoderCommand::SetPlaybackSpeed(speed) => {
+                self.set_speed(speed);
+            }
+            DecoderCommand::SetDegradationMode(mode) => {
+                self.sync.set_degradation_mode(mode);
+            }
+            DecoderCommand::SetCacheMode(mode, max_bytes) => {
+                let changed = mode != self.cache_mode || max_bytes != self.cache_max_bytes;
+                self.cache_mode = mode;
+                self.cache_max_bytes = max_bytes;
+                if changed && self.input_context.is_some() && !self.live_mode {
+                    self.fill_cache(self.presented_time);
+                } else {
+                    self.publish_cache_status();
+                }
+            }
+            DecoderCommand::SetAvOffset(seconds) => {
+                self.sync.set_av_offset(seconds);
+            }
+            DecoderCommand::SetVideoAdjustments(adjustments) => {
+                // Frames already queued keep the old look
+                self.video_adjustments = adjustments;
+                if let Some(converter) = self.video_converter.as_mut() {
+                    converter.set_adjustments(adjustments);
+                }
+            }
+            DecoderCommand::SetLetterbox(letterbox) => {
+                // Frames already queued keep their size
+                self.letterbox = letterbox;
+            }
+            DecoderCommand::SetDeinterlace(mode) => {
+                // The filter is set up again from the next frame
+                self.deinterlace_mode = mode;
+                self.deinterlacer = None;
+                self.deinterlace_failed = false;
+                if mode == DeinterlaceMode::Off {
+                    *self.deinterlacing.lock() = false;
+                }
+            }
+            DecoderCommand::SetOutPoint(end) => {
+                self.out_point = end;
+            }
+            DecoderCommand::SetLoop(region) => {
+                // The player seeks next, which restarts the timeline
+                self.loop_region = region;
+            }
+            DecoderCommand::SetSkipSilence(threshold_db) => {
+                self.skip_silence = threshold_db;
+                if threshold_db.is_none() {
+                    self.release_held_silence();
+                }
+            }
+            DecoderCommand::SetOutputSampleRate(rate) => {
+                self.set_output_sample_rate(rate);
+            }
+            DecoderCommand::SetOutputSink(file, device) => {
+                // Replacing the sink finalizes the previous recording
+                self.file_sink = file;
+                self.device_muted = self.file_sink.is_some() && !device;
+            }
+            DecoderCommand::SetReconnectPolicy(policy) => {
+                self.reconnect_policy = policy;
+            }
+            DecoderCommand::SetToneMapping(enabled) => {
+                // The converter is rebuilt for the next frame
+                self.tone_mapping = enabled;
+            }
+            DecoderCommand::SetOutputPixelFormat(format) => {
+                // Frames already queued keep the old layout
+                self.output_pixel_format = format;
+                self.converter_failed = false;
+            }
+            DecoderCommand::SetPreferredAudioLanguage(language) => {
+                // Takes effect on the next load
+                self.preferred_audio_language = language;
+            }
+            DecoderCommand::SetIoSettings(settings) => {
+                // Takes effect on the next load
+                self.io_settings = settings;
+            }
+            DecoderCommand::CycleAudioTrack(reply) => {
+                let _ = reply.send(self.cycle_audio_track());
+            }
+            DecoderCommand::SetVideoTrack(index, reply) => {
+                let _ = reply.send(self.set_video_track(index));
+            }
+            DecoderCommand::AddDspHook(hook) => {
+                self.dsp_hooks.push(hook);
+            }
+            DecoderCommand::SetEffects(effects) => {
+                self.effects = EffectsChain::new(effects, self.output_sample_rate);
+            }
+            DecoderCommand::SetMonoOutput(enabled) => {
+                self.mono_output = enabled;
+            }
+            DecoderCommand::SetClipDetection(enabled) => {
+                self.clip_monitor.set_enabled(enabled);
+                *self.clip_stats.lock() = self.clip_monitor.stats();
+            }
+            DecoderCommand::SetVocalRemoval(enabled) => {
+                self.vocal_removal = enabled;
+                self.channel_muting_changed();
+            }
+            DecoderCommand::SetChannelMute(mask) => {
+                self.channel_mute = mask;
+                self.channel_muting_changed();
+            }
+            DecoderCommand::SetAutoBalance(enabled) => {
+                self.auto_balance.set_enabled(enabled);
+                *self.shared_auto_balance.lock() = None;
+            }
+            DecoderCommand::ResetAudioEffects => {
+                // Between two chunks, so none plays half reset. The new chain
+                // starts with empty filter state; flat, it passes samples as is
+                self.effects = EffectsChain::new(AudioEffects::default(), self.output_sample_rate);
+                self.mono_output = false;
+                self.auto_balance.set_enabled(false);
+                *self.shared_auto_balance.lock() = None;
+                self.vocal_removal = false;
+                self.channel_mute = 0;
+                self.channel_muting_changed();
+            }
+        }
+    }
+
+    /// Interleaved output samples per second of audio
+    fn samples_per_second(&self) -> f64 {
+        self.output_sample_rate as f64 * OUTPUT_CHANNELS as f64
+    }
+
+    /// Forget everything about the previous file; user settings (volume,
+    /// effects, picture options) carry over to the next one
+    fn begin_load(&mut self, vsender: Option<Sender<VideoFrame>>, abuffer: Option<AudioBuffer>) {
+        self.load_started = Some(self.clock.now());
+        *self.load_timings.lock() = LoadTimings::default();
+        self.cache = None;
+        self.publish_cache_status();
+        self.video_sender = vsender;
+        self.audio_buffer = abuffer;
+        self.audio_output_connected = true;
+        self.video_queue.clear();
+        self.samples_sent = 0;
+        self.end_of_input = false;
+        self.read_failures = 0;
+        self.sync.reset();
+        self.emitted_slot = None;
+        self.last_presented = None;
+        self.audio_clock.request_flush();
+        self.last_timestamp = 0.0;
+        self.seek_target = None;
+        self.buffered_until = 0.0;
+        self.presented_time = 0.0;
+        self.held_silence.clear();
+        self.tempo = None;
+        self.heard_audio = false;
+        self.out_point = None;
+        self.loop_region = None;
+        self.loop_offset = 0.0;
+        self.auto_balance.reset();
+        *self.shared_auto_balance.lock() = None;
+        self.clip_monitor.reset();
+        *self.clip_stats.lock() = self.clip_monitor.stats();
+        self.resampler_failures = 0;
+        self.has_audio = false;
+        self.has_video = false;
+        self.audio_stream_index = None;
+        self.video_stream_index = None;
+    }
+
+    fn load(
+        &mut self,
+        path: String,
+        vsender: Option<Sender<VideoFrame>>,
+        abuffer: Option<AudioBuffer>,
+    ) {
+        self.begin_load(vsender, abuffer);
+        // Initialize FFmpeg
+        let _ = ffmpeg::init();
+
+        let (ictx, io) = match self.open_input(&path) {
+            Ok(opened) => opened,
+            Err(e) => {
+                eprintln!("Failed to open file: {}", e);
+                let _ = self.info_tx.send(Err(e.into()));
+                return;
+            }
+        };
+
+        // Find streams
+        let audio_idx = choose_stream(
+            &ictx,
+            ffmpeg::media::Type::Audio,
+            self.preferred_audio_language.as_deref(),
+        );
+        let video_idx = choose_stream(&ictx, ffmpeg::media::Type::Video, None);
+        let has_cover_art = ictx
+            .streams()
+            .any(|s| s.parameters().medium() == ffmpeg::media::Type::Video && is_cover_art(&s));
+
+        // Setup audio decoder
+        let mut ready = ReadySummary::default();
+        let mut audio_sample_rate = 0;
+        let mut passthrough_blocker = None;
+        if let Some(idx) = audio_idx {
+            let stream = ictx.stream(idx).unwrap();
+            let codec_params = stream.parameters();
+            self.audio_time_base = Some(stream.time_base());
+
+            let opened = self.open_audio(idx, codec_params);
+            ready.audio = audio_status(idx, &opened);
+            if let Ok((decoder, resampler)) = opened {
+                audio_sample_rate = decoder.rate();
+                passthrough_blocker = passthrough_blocker_for(&decoder);
+                self.audio_decoder = Some(decoder);
+                self.audio_resampler = Some(resampler);
+                self.audio_stream_index = Some(idx);
+                self.has_audio = true;
+            }
+        }
+
+        self.update_channel_muting();
+
+        // Setup video decoder
+        self.reset_video_stream();
+        let mut video = VideoStreamInfo::default();
+        if let Some(idx) = video_idx {
+            ready.video = match self.setup_video(&ictx, idx) {
+                Ok(stream) => {
+                    video = stream;
+                    StreamStatus::Ready
+                }
+                Err(reason) => StreamStatus::Failed(reason),
+            };
+        }
+        self.warn_video_failed(&ready);
+
+        *self.display.lock() = video.display;
+        *self.video_size.lock() = (video.width, video.height);
+        *self.decode_backend.lock() = self.has_video.then_some(self.codec_backend);
+        let video_streams = ictx
+            .streams()
+            .filter(|s| s.parameters().medium() == ffmpeg::media::Type::Video && !is_cover_art(s))
+            .map(|s| s.index())
+            .collect();
+        let media = probe::describe(&ictx, &path);
+        let subtitle_fonts = attachments::subtitle_fonts(&ictx);
+        self.duration = micros_to_seconds(ictx.duration());
+        self.start_time = media_start_time(&ictx);
+        self.network_source = is_network_source(&path);
+        self.file_path = Some(path.clone());
+        self.input_context = Some(ictx);
+        let io_settings = io
+            .as_ref()
+            .map_or_else(IoSettings::default, |_| self.io_settings);
+        self.input_io = io;
+
+        // Send decoder info
+        let info = DecoderInfo {
+            has_video: self.has_video,
+            has_audio: self.has_audio,
+            has_cover_art,
+            hdr: video.hdr,
+            video_bit_depth: video.bit_depth,
+            color: video.color,
+            slow_motion_factor: video.slow_motion_factor,
+            video_streams,
+            video_stream_index: self.video_stream_index,
+            media,
+            subtitle_fonts,
+            ready,
+            audio_sample_rate,
+            passthrough_blocker,
+            io: io_settings,
+            duration: self.duration,
+            file_path: Some(path),
+        };
+        if let Some(started) = self.load_started {
+            self.load_timings.lock().open = self.clock.elapsed(started).as_secs_f64();
+        }
+        let _ = self.info_tx.send(Ok(info));
+        self.apply_motion_rate();
+        if self.cache_mode == CacheMode::FullDecode {
+            self.fill_cache(0.0);
+        }
+    }
+
+    /// Why the loaded file cannot be decoded into memory, if it cannot
+    fn cache_blocker(&self) -> Option<String> {
+        if self.live_mode || self.network_source {
+            return Some("live and network sources always stream".to_string());
+        }
+        if self.passthrough {
+            return Some("passthrough audio is not cached".to_string());
+        }
+        if self.duration <= 0.0 {
+            return Some("the duration is unknown".to_string());
+        }
+        if self.duration > MAX_FULL_DECODE_DURATION {
+            return Some(format!("it is longer than {} s", MAX_FULL_DECODE_DURATION));
+        }
+        // Output frames at the average rate plus the output samples
+        let bpp = self.output_pixel_format.bytes_per_pixel() as u32;
+        let frame_bytes = self
+            .video_decoder
+            .as_ref()
+            .map_or(0.0, |d| (d.width() * d.height() * bpp) as f64);
+        let frames = self.target_fps.unwrap_or(0.0) * self.duration;
+        let audio_bytes = if self.has_audio {
+            self.samples_per_second() * self.duration * std::mem::size_of::<f32>() as f64
+        } else {
+            0.0
+        };
+        let estimate = frame_bytes * frames + audio_bytes;
+        if estimate > self.cache_max_bytes as f64 {
+            return Some(format!(
+                "it would take about {} MiB, over the {} MiB cap",
+                (estimate / 1048576.0).ceil(),
+                self.cache_max_bytes / 1048576
+            ));
+        }
+        None
+    }
+
+    /// Decode the whole file into memory if the cache mode, the file and the
+    /// memory cap allow it, then continue from `resume_at`. Otherwise, or when
+    /// the cap is hit while decoding, the file streams and a warning is sent.
+    ///
+    /// This blocks the decoder thread until the file is decoded; commands sent
+    /// meanwhile apply afterwards.
+    fn fill_cache(&mut self, resume_at: f64) {
+        self.cache = None;
+        let blocker = match self.cache_mode {
+            CacheMode::Streaming => None,
+            CacheMode::FullDecode => self.cache_blocker(),
+        };
+        if self.cache_mode == CacheMode::FullDecode && blocker.is_none() {
+            match self.decode_all() {
+                Ok(cache) => self.cache = Some(cache),
+                Err(reason) => self.warn_streaming(&reason),
+            }
+        } else if let Some(reason) = blocker {
+            self.warn_streaming(&reason);
+        }
+        self.publish_cache_status();
+        self.seek(resume_at);
+    }
+
+    fn warn_streaming(&self, reason: &str) {
+        let message = format!(
+            "Cannot decode the file into memory ({}), streaming it",
+            reason
+        );
+        eprintln!("{}", message);
+        let _ = self.event_tx.send(PlayerEvent::Warning { message });
+    }
+
+    /// Decode the input from the start into a cache; fails with the reason
+    /// when it outgrows the cap or a read fails before the end of the file
+    fn decode_all(&mut self) -> Result<FullCache, String> {
+        let Some(ictx) = self.input_context.as_mut() else {
+            return Err("no input is open".to_string());
+        };
+        let _ = ictx.seek(self.start_time, ..);
+        if let Some(ref mut dec) = self.audio_decoder {
+            dec.flush();
+        }
+        if let Some(ref mut dec) = self.video_decoder {
+            dec.flush();
+        }
+        self.deinterlacer = None;
+        self.video_queue.clear();
+        self.held_silence.clear();
+        self.seek_target = None;
+        // Cached media is complete and at unity gain: the out point and the
+        // volume apply when it plays
+        let out_point = self.out_point.take();
+        let loop_region = self.loop_region.take();
+        let volume = std::mem::replace(&mut self.volume, 1.0);
+        self.filling_cache = Some(FullCache::default());
+
+        let mut result = Err(format!(
+            "it takes more than the {} MiB cap",
+            self.cache_max_bytes / 1048576
+        ));
+        while self.filling_cache_bytes() <= self.cache_max_bytes {
+            let mut packet = ffmpeg::Packet::empty();
+            let Some(ictx) = self.input_context.as_mut() else {
+                break;
+            };
+            match packet.read(ictx) {
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => {
+                    self.decode_audio_packet(None);
+                    self.flush_resampler();
+                    self.decode_video_packet(None);
+                    self.flush_deinterlacer();
+                    self.cache_queued_frames();
+                    if self.filling_cache_bytes() <= self.cache_max_bytes {
+                        result = Ok(());
+                    }
+                    break;
+                }
+                // Anything but the end (a dropped connection, EAGAIN) leaves
+                // the cache short of the file
+                Err(e) => {
+                    result = Err(format!("reading it failed: {}", e));
+                    break;
+                }
+            }
+            let stream_idx = packet.stream();
+            if Some(stream_idx) == self.audio_stream_index {
+                self.decode_audio_packet(Some(&packet));
+            }
+            if Some(stream_idx) == self.video_stream_index {
+                self.decode_video_packet(Some(&packet));
+            }
+            self.cache_queued_frames();
+        }
+
+        self.out_point = out_point;
+        self.loop_region = loop_region;
+        self.volume = volume;
+        let cache = self.filling_cache.take();
+        result.map(|()| cache.unwrap_or_default())
+    }
+
+    /// Move decoded frames from the presentation queue into the cache being filled
+    fn cache_queued_frames(&mut self) {
+        if let Some(cache) = self.filling_cache.as_mut() {
+            for frame in self.video_queue.drain(..) {
+                cache.push_video(frame);
+            }
+        }
+    }
+
+    fn filling_cache_bytes(&self) -> u64 {
+        self.filling_cache.as_ref().map_or(0, FullCache::bytes)
+    }
+
+    /// Decode the cache again (its audio no longer matches the output), or
+    /// just seek when the file streams
+    fn refill_cache_or_seek(&mut self, position: f64) {
+        if self.cache.is_some() {
+            self.fill_cache(position);
+        } else {
+            self.seek(position);
+        }
+    }
+
+    fn publish_cache_status(&self) {
+        *self.cache_status.lock() = CacheStatus {
+            mode: self.cache_mode,
+            max_bytes: self.cache_max_bytes,
+            active: self.cache.is_some(),
+            bytes: self.cache.as_ref().map_or(0, FullCache::bytes),
+        };
+    }
+
+    /// Queue the next cached frame or audio chunk; false once the cache is
+    /// exhausted or the end point is reached
+    fn feed_from_cache(&mut self) -> bool {
+        let Some(item) = self.cache.as_mut().and_then(FullCache::next) else {
+            return false;
+        };
+        match item {
+            CachedItem::Video(mut frame) => {
+                if self.end_point().is_some_and(|end| frame.timestamp >= end) {
+                    return false;
+                }
+                self.last_timestamp = frame.timestamp;
+                frame.timestamp += self.loop_offset;
+                self.enqueue_video_frame(frame);
+                self.decode_rate.tick(self.clock.now());
+                self.note_first_output(true);
+            }
+            CachedItem::Audio(mut samples, mut timestamp) => {
+                if self.end_point().is_some_and(|end| timestamp >= end) {
+                    return false;
+                }
+                let (target, rate) = (self.seek_target, self.output_sample_rate);
+                if !trim_to_seek_target(&mut samples, &mut timestamp, target, rate) {
+                    return true;
+                }
+                for sample in samples.iter_mut() {
+                    *sample *= self.volume;
+                }
+                self.queue_audio(samples, timestamp, false);
+            }
+        }
+        true
+    }
+
+    /// Choose how vocals are removed from the current audio stream and which
+    /// source channels are silenced; true if the silenced channels changed
+    fn update_channel_muting(&mut self) -> bool {
+        let layout = self
+            .audio_decoder
+            .as_ref()
+            .map(|decoder| source_channel_layout(decoder.channel_layout(), decoder.channels()));
+        self.vocal_mode = match layout {
+            Some(layout) if self.vocal_removal => VocalRemovalMode::for_layout(layout),
+            _ => VocalRemovalMode::Off,
+        };
+        *self.shared_vocal_removal.lock() = self.vocal_mode;
+
+        let center = layout
+            .filter(|_| self.vocal_mode == VocalRemovalMode::CenterMuted)
+            .and_then(center_channel)
+            .map_or(0, |ch| 1 << ch);
+        let mask = self.channel_mute | center;
+        let changed = mask != self.source_mute_mask;
+        self.source_mute_mask = mask;
+        changed
+    }
+
+    /// Apply changed channel settings; cached audio was decoded with the old
+    /// channels silenced, so it is decoded again
+    fn channel_muting_changed(&mut self) {
+        if self.update_channel_muting() && self.cache.is_some() {
+            self.refill_cache_or_seek(self.presented_time);
+        }
+    }
+
+    /// Time the first video frame (`video`) or audio sample of the current
+    /// load, raising `MediaLoaded` once every stream has produced one
+    fn note_first_output(&mut self, video: bool) {
+        let Some(started) = self.load_started else {
+            return;
+        };
+        let elapsed = self.clock.elapsed(started).as_secs_f64();
+        let mut timings = self.load_timings.lock();
+        let first = if video {
+            &mut timings.first_video_frame
+        } else {
+            &mut timings.first_audio_sample
+        };
+        first.get_or_insert(elapsed);
+
+        let complete = (!self.has_video || timings.first_video_frame.is_some())
+            && (!self.has_audio || timings.first_audio_sample.is_some());
+        if complete {
+            self.load_started = None;
+            let _ = self
+                .event_tx
+                .send(PlayerEvent::MediaLoaded { timings: *timings });
+        }
+    }
+
+    /// Pace video at the playback speed, and slow-motion video at its
+    /// recorded speed if asked to; the latter only for files without audio,
+    /// which would otherwise drift away from the picture
+    fn apply_motion_rate(&mut self) {
+        let rate = self
+            .slow_motion_factor
+            .filter(|_| self.real_time_motion && self.has_video && !self.has_audio)
+            .unwrap_or(1.0);
+        self.sync.set_wall_rate(rate * self.speed);
+    }
+
+    /// Play `speed` media seconds per second from the next samples on
+    fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+        self.tempo = None;
+        self.audio_clock.set_speed(speed, self.samples_per_second());
+        self.apply_motion_rate();
+    }
+
+    /// Open the decoder and output resampler for audio stream `idx`
+    fn open_audio(
+        &self,
+        idx: usize,
+        codec_params: ffmpeg::codec::Parameters,
+    ) -> Result<(
+        ffmpeg::decoder::Audio,
+        ffmpeg::software::resampling::context::Context,
+    )> {
+        let decoder = open_decoder(codec_params)?
+            .audio()
+            .context("Cannot open audio decoder")?;
+        self.report_compatibility(idx, &decoder);
+        let resampler = create_resampler(&decoder, self.output_sample_rate)
+            .context("Cannot convert audio to the output format")?;
+        Ok((decoder, resampler))
+    }
+
+    /// Rebuild the audio pipeline on the next audio stream and resume at the
+    /// position currently audible
+    fn cycle_audio_track(&mut self) -> Result<AudioTrack, PlayerError> {
+        let ictx = self.input_context.as_ref().ok_or(PlayerError::NotLoaded)?;
+        let tracks: Vec<usize> = ictx
+            .streams()
+            .filter(|s| s.parameters().medium() == ffmpeg::media::Type::Audio)
+            .map(|s| s.index())
+            .collect();
+        if tracks.len() < 2 {
+            return Err(PlayerError::Unsupported(
+                "The file has only one audio track".to_string(),
+            ));
+        }
+
+        let next = match self.audio_stream_index {
+            Some(current) => tracks
+                .iter()
+                .copied()
+                .find(|&idx| idx > current)
+                .unwrap_or(tracks[0]),
+            None => tracks[0],
+        };
+        let stream = ictx.stream(next).unwrap();
+        let time_base = stream.time_base();
+        let (decoder, resampler) = self
+            .open_audio(next, stream.parameters())
+            .map_err(|e| PlayerError::DecodeFailed(format!("{:#}", e)))?;
+
+        let codec = decoder
+            .codec()
+            .map(|c| c.name().to_string())
+            .unwrap_or_else(|| format!("{:?}", decoder.id()));
+        let metadata = stream.metadata();
+        let track = AudioTrack {
+            stream_index: next,
+            language: metadata.get("language").map(str::to_string),
+            title: metadata.get("title").map(str::to_string),
+            codec,
+            channels: decoder.channels(),
+            sample_rate: decoder.rate(),
+        };
+
+        let position = self
+            .sync
+            .audio_time(&self.audio_clock, self.samples_per_second())
+            .map_or(self.last_timestamp, |time| self.media_time(time));
+        self.audio_decoder = Some(decoder);
+        self.audio_resampler = Some(resampler);
+        self.resampler_failures = 0;
+        self.audio_stream_index = Some(next);
+        self.audio_time_base = Some(time_base);
+        self.has_audio = true;
+        self.update_channel_muting();
+
+        // Drop audio decoded from the old track; live sources just continue
+        if self.live_mode {
+            if let Some(ref buffer) = self.audio_buffer {
+                buffer.clear();
+                self.audio_clock.request_flush();
+            }
+            self.samples_sent = 0;
+        } else {
+            self.refill_cache_or_seek(position);
+        }
+        Ok(track)
+    }
+
+    /// Forget the per-stream video state before a video stream is set up
+    fn reset_video_stream(&mut self) {
+        self.video_rotation = 0;
+        self.video_interlaced = false;
+        self.deinterlacer = None;
+        self.deinterlace_failed = false;
+        self.converter_failed = false;
+        self.skipping_frames = false;
+        self.slow_motion_factor = None;
+        self.target_fps = None;
+        self.decode_rate.reset();
+        self.present_rate.reset();
+        *self.deinterlacing.lock() = false;
+    }
+
+    /// Raise a warning if the video stream failed to set up; a video file
+    /// playing as audio only would otherwise look like a bug
+    fn warn_video_failed(&self, ready: &ReadySummary) {
+        if let StreamStatus::Failed(ref reason) = ready.video {
+            let message = if self.has_audio {
+                format!("Cannot play the video ({}), playing audio only", reason)
+            } else {
+                format!("Cannot play the video ({})", reason)
+            };
+            let _ = self.event_tx.send(PlayerEvent::Warning { message });
+        }
+    }
+
+    /// Open the decoder and scaler for video stream `idx`; the error says why
+    /// the stream cannot play
+    fn setup_video(
+        &mut self,
+        ictx: &ffmpeg::format::context::Input,
+        idx: usize,
+    ) -> Result<VideoStreamInfo, String> {
+        let stream = ictx.stream(idx).unwrap();
+        let codec_params = stream.parameters();
+        self.video_time_base = Some(stream.time_base());
+        self.video_rotation = stream_rotation(&stream);
+        self.video_interlaced = stream_interlaced(&codec_params);
+        self.slow_motion_factor = slow_motion_factor(
+            probe::capture_frame_rate(ictx, &stream),
+            probe::rational_rate(stream.avg_frame_rate()),
+        );
+        self.target_fps = probe::rational_rate(stream.avg_frame_rate());
+
+        let decoder = open_decoder(codec_params)
+            .and_then(|decoder| decoder.video().context("Cannot open video decoder"))
+            .map_err(|e| {
+                eprintln!("Video stream {} failed to initialize: {:#}", idx, e);
+                format!("{:#}", e)
+            })?;
+        // Malformed files can declare an empty picture; play their audio only
+        if let Err(message) = check_video_dimensions(idx, decoder.width(), decoder.height()) {
+            eprintln!("{}", message);
+            return Err(message);
+        }
+
+        self.report_compatibility(idx, &decoder);
+        let width = decoder.width();
+        let height = decoder.height();
+        let transfer = decoder.color_transfer_characteristic();
+        let info = VideoStreamInfo {
+            width,
+            height,
+            display: self.display_dimensions(width, height, decoder.aspect_ratio()),
+            hdr: HdrTransfer::detect(transfer).is_some(),
+            bit_depth: bit_depth(decoder.format()),
+            color: Some(ColorInfo::new(
+                decoder.format(),
+                height,
+                decoder.color_range(),
+                decoder.color_primaries(),
+                transfer,
+                decoder.color_space(),
+            )),
+            slow_motion_factor: self.slow_motion_factor,
+        };
+        self.codec_backend = decoder
+            .codec()
+            .map(|c| DecodeBackend::for_codec(c.name()))
+            .unwrap_or(DecodeBackend::Software);
+
+        // Create scaler; formats only known once frames arrive are set up lazily
+        let converter = if decoder.format() == ffmpeg::format::Pixel::None {
+            None
+        } else {
+            let mut converter = VideoConverter::new(
+                decoder.format(),
+                width,
+                height,
+                decoder.color_space(),
+                decoder.color_range(),
+                HdrTransfer::detect(transfer).filter(|_| self.tone_mapping),
+                self.output_pixel_format,
+            )
+            .map_err(|e| {
+                eprintln!("Video stream {} failed to initialize: {}", idx, e);
+                format!(
+                    "Cannot convert video to {:?}: {}",
+                    self.output_pixel_format, e
+                )
+            })?;
+            converter.set_adjustments(self.video_adjustments);
+            Some(converter)
+        };
+        self.video_decoder = Some(decoder);
+        self.video_converter = converter;
+        self.video_stream_index = Some(idx);
+        self.has_video = true;
+        Ok(info)
+    }
+
+    /// Rebuild the video pipeline on stream `index` and resume at the position
+    /// currently playing; the previous stream stays if the new one fails
+    fn set_video_track(&mut self, index: usize) -> Result<VideoStreamInfo, PlayerError> {
+        let Some(ictx) = self.input_context.take() else {
+            return Err(PlayerError::NotLoaded);
+        };
+        let playable = ictx.stream(index).is_some_and(|s| {
+            s.parameters().medium() == ffmpeg::media::Type::Video && !is_cover_art(&s)
+        });
+        if !playable {
+            self.input_context = Some(ictx);
+            return Err(PlayerError::InvalidArgument(format!(
+                "Stream {} is not a video track",
+                index
+            )));
+        }
+
+        let position = self
+            .sync
+            .audio_time(&self.audio_clock, self.samples_per_second())
+            .map_or(self.presented_time, |time| self.media_time(time));
+        let previous = self.video_stream_index.take();
+        self.video_decoder = None;
+        self.video_converter = None;
+        self.has_video = false;
+        self.reset_video_stream();
+        let result = self.setup_video(&ictx, index);
+        if result.is_err() {
+            if let Some(previous) = previous {
+                self.reset_video_stream();
+                let _ = self.setup_video(&ictx, previous);
+            }
+        }
+        self.input_context = Some(ictx);
+        let info = result.map_err(PlayerError::DecodeFailed)?;
+
+        *self.display.lock() = info.display;
+        *self.video_size.lock() = (info.width, info.height);
+        *self.decode_backend.lock() = Some(self.codec_backend);
+        self.apply_motion_rate();
+        // Drop frames decoded from the old stream; live sources just continue
+        self.video_queue.clear();
+        if !self.live_mode {
+            self.refill_cache_or_seek(position);
+        }
+        Ok(info)
+    }
+
+    /// Emit `compatibility-warning` events for a stream's decoder; playback still proceeds
+    fn report_compatibility(&self, stream_index: usize, decoder: &ffmpeg::decoder::Opened) {
+        let codec = decoder
+            .codec()
+            .map(|c| c.name().to_string())
+            .unwrap_or_else(|| format!("{:?}", decoder.id()));
+        for message in compatibility_warnings(decoder) {
+            eprintln!("Stream {} ({}): {}", stream_index, codec, message);
+            let _ = self.event_tx.send(PlayerEvent::CompatibilityWarning {
+                stream_index,
+                codec: codec.clone(),
+                message,
+            });
+        }
+    }
+
+    /// Open `path` for demuxing. Live sources skip demuxer buffering and probe
+    /// briefly; local files are read through our own buffer when `io_settings`
+    /// differ from FFmpeg's, which is returned with the input
+    fn open_input(
+        &self,
+        path: &str,
+    ) -> Result<(ffmpeg::format::context::Input, Option<FileIo>), ffmpeg::Error> {
+        if self.live_mode {
+            let mut options = ffmpeg::Dictionary::new();
+            options.set("fflags", "nobuffer");
+            options.set("flags", "low_delay");
+            options.set("probesize", "32768");
+            options.set("analyzeduration", "500000");
+            return ffmpeg::format::input_with_dictionary(&path, options).map(|ictx| (ictx, None));
+        }
+        if self.io_settings.is_default() || is_network_source(path) {
+            return open_probed(path).map(|ictx| (ictx, None));
+        }
+        match fileio::open_buffered(path, self.io_settings) {
+            Ok((ictx, io)) if probe_complete(&ictx) => Ok((ictx, Some(io))),
+            // Files that need a longer probe go through the usual retry
+            Ok(_) => open_probed(path).map(|ictx| (ictx, None)),
+            Err(e) => {
+                eprintln!(
+                    "Cannot read {} through a {} byte buffer ({}), using FFmpeg's",
+                    path, self.io_settings.buffer_size, e
+                );
+                open_probed(path).map(|ictx| (ictx, None))
+            }
+        }
+    }
+
+    /// Re-open a network source after a read error, with backoff.
+    ///
+    /// A stop or load command received while waiting cancels the retries.
+    /// Other commands (seeks, track switches) would work on the failed input,
+    /// so they are held back and applied once the input is open again. If
+    /// every attempt fails the input is treated as ended.
+    fn reconnect(&mut self, error: ffmpeg::Error) {
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+        let max_attempts = self.reconnect_policy.max_attempts;
+        let mut backoff = self.reconnect_policy.initial_backoff_ms;
+        eprintln!("Read error on {}: {}, reconnecting", path, error);
+
+        let mut deferred = Vec::new();
+        let mut reconnected = false;
+        for attempt in 1..=max_attempts {
+            let _ = self.event_tx.send(PlayerEvent::Buffering {
+                attempt,
+                max_attempts,
+            });
+
+            // Wait out the backoff while still taking commands
+            let deadline = self.clock.now() + std::time::Duration::from_millis(backoff);
+            while self.clock.now() < deadline {
+                match self.cmd_rx.try_recv() {
+                    Ok(command @ (DecoderCommand::Stop | DecoderCommand::Load(..))) => {
+                        self.handle_command(command);
+                        return;
+                    }
+                    Ok(command) => deferred.push(command),
+                    Err(_) => self.clock.sleep(std::time::Duration::from_millis(10)),
+                }
+            }
+
+            match self.open_input(&path) {
+                Ok((mut ictx, io)) => {
+                    // Live sources resume at the live edge; others where they stopped
+                    if !self.live_mode {
+                        let timestamp = seconds_to_micros(self.last_timestamp, self.start_time);
+                        let _ = ictx.seek(timestamp, ..);
+                    } else if let Some(ref buffer) = self.audio_buffer {
+                        // Queued audio is behind the new live edge; restart the clock there
+                        buffer.clear();
+                        self.audio_clock.request_flush();
+                        self.samples_sent = 0;
+                    }
+                    if let Some(ref mut dec) = self.audio_decoder {
+                        dec.flush();
+                    }
+                    if let Some(ref mut dec) = self.video_decoder {
+                        dec.flush();
+                    }
+                    self.input_context = Some(ictx);
+                    self.input_io = io;
+                    self.sync.reset();
+                    eprintln!("Reconnected to {} after {} attempt(s)", path, attempt);
+                    reconnected = true;
+                    break;
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Reconnect attempt {}/{} failed: {}",
+                        attempt, max_attempts, e
+                    );
+                    backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF_MS);
+                }
+            }
+        }
+
+        if !reconnected {
+            let _ = self.event_tx.send(PlayerEvent::Warning {
+                message: format!("Lost connection to {}", path),
+            });
+            self.end_of_input = true;
+        }
+        for command in deferred {
+            self.handle_command(command);
+        }
+    }
+
+    /// Returns the position playback resumes from: `time` for accurate seeks,
+    /// the keyframe the demuxer landed on for fast ones
+    fn seek(&mut self, time: f64) -> f64 {
+        let time = self
+            .loop_region
+            .map_or(time, |region| region.seek_target(time));
+        self.loop_offset = 0.0;
+        self.loop_pass_start = time;
+        self.last_timestamp = time;
+        self.buffered_until = time;
+        self.presented_time = time;
+        self.decode_rate.reset();
+        self.present_rate.reset();
+        if let Some(ref mut ictx) = self.input_context {
+            let timestamp = seconds_to_micros(time, self.start_time);
+            let _ = ictx.seek(timestamp, ..);
+            self.discard_decoded();
+
+            // The demuxer lands on a keyframe; accurate seeks decode up to `time` silently
+            self.seek_target = match self.seek_mode {
+                SeekMode::Fast => None,
+                SeekMode::Accurate => Some(time),
+            };
+
+            // Everything is decoded already: land exactly on `time` in any mode
+            if let Some(cache) = self.cache.as_mut() {
+                cache.seek(time);
+                self.seek_target = Some(time);
+                self.buffered_until = self.duration;
+                return time;
+            }
+            if self.seek_mode == SeekMode::Fast {
+                if let Some(landed) = self.decode_to_keyframe() {
+                    self.presented_time = landed;
+                    return landed;
+                }
+            }
+        }
+        time
+    }
+
+    /// Flush the decoders and drop media decoded before a seek point
+    fn discard_decoded(&mut self) {
+        if let Some(ref mut dec) = self.audio_decoder {
+            dec.flush();
+        }
+        if let Some(ref mut dec) = self.video_decoder {
+            dec.flush();
+        }
+        self.video_queue.clear();
+        self.deinterlacer = None;
+        self.tempo = None;
+        if let Some(ref buffer) = self.audio_buffer {
+            buffer.clear();
+            self.audio_clock.request_flush();
+        }
+        self.samples_sent = 0;
+        self.held_silence.clear();
+        self.end_of_input = false;
+        self.sync.reset();
+        self.emitted_slot = None;
+        self.last_presented = None;
+        self.clip_monitor.reset_filters();
+    }
+
+    /// Size of the file if fractions of it are better found by bytes than by
+    /// time: the duration is unknown, or the format's timestamps may jump
+    /// (MPEG-TS and the like), so a time would land somewhere else
+    fn byte_seek_size(&self) -> Option<u64> {
+        // Both need a known duration and address the file by time
+        if self.cache.is_some() || self.loop_region.is_some() {
+            return None;
+        }
+        let ictx = self.input_context.as_ref()?;
+        unsafe {
+            let flags =
+                ffmpeg::format::Flags::from_bits_truncate((*(*ictx.as_ptr()).iformat).flags);
+            let unreliable =
+                self.duration <= 0.0 || flags.contains(ffmpeg::format::Flags::TS_DISCONT);
+            if !unreliable || flags.contains(ffmpeg::format::Flags::NO_BYTE_SEEK) {
+                return None;
+            }
+            let pb = (*ictx.as_ptr()).pb;
+            if pb.is_null() {
+                return None;
+            }
+            let size = ffmpeg::ffi::avio_size(pb);
+            (size > 0).then_some(size as u64)
+        }
+    }
+
+    /// Seek to `fraction` of the file; returns the position playback resumes
+    /// from.
+    ///
+    /// Files with a known duration and reliable timestamps seek by time, with
+    /// `snap` to the keyframe before it whatever the seek mode. Others seek to
+    /// that fraction of their bytes and resume from the first keyframe after it.
+    fn seek_percent(&mut self, fraction: f64, snap: bool) -> Result<f64, PlayerError> {
+        if self.input_context.is_none() {
+            return Err(PlayerError::NotLoaded);
+        }
+        if self.live_mode {
+            return Err(PlayerError::NotSeekable);
+        }
+        let Some(size) = self.byte_seek_size() else {
+            if self.duration <= 0.0 {
+                return Err(PlayerError::NotSeekable);
+            }
+            let time = fraction * self.duration;
+            if !snap {
+                return Ok(self.seek(time));
+            }
+            let mode = std::mem::replace(&mut self.seek_mode, SeekMode::Fast);
+            let landed = self.seek(time);
+            self.seek_mode = mode;
+            return Ok(landed);
+        };
+
+        let ictx = self.input_context.as_mut().unwrap();
+        let position = (fraction * size as f64) as i64;
+        let result = unsafe {
+            ffmpeg::ffi::avformat_seek_file(
+                ictx.as_mut_ptr(),
+                -1,
+                i64::MIN,
+                position,
+                i64::MAX,
+                ffmpeg::ffi::AVSEEK_FLAG_BYTE as std::ffi::c_int,
+            )
+        };
+        if result < 0 {
+            eprintln!(
+                "Byte seek to {} failed: {}",
+                position,
+                ffmpeg::Error::from(result)
+            );
+            return Err(PlayerError::NotSeekable);
+        }
+        self.discard_decoded();
+        self.seek_target = None;
+        self.loop_offset = 0.0;
+        self.decode_rate.reset();
+        self.present_rate.reset();
+
+        // Where it landed is only known from the first timestamp read there
+        let landed = self
+            .decode_to_keyframe()
+            .unwrap_or(fraction * self.duration.max(0.0));
+        self.loop_pass_start = landed;
+        self.last_timestamp = landed;
+        self.buffered_until = landed;
+        self.presented_time = landed;
+        Ok(landed)
+    }
+
+    /// Decode up to the next video frame and send it without playing, e.g.
+    /// after a load or a seek while paused.
+    ///
+    /// Packets read on the way are decoded as usual and the frame stays queued,
+    /// so playback later starts from here without skipping anything; neither
+    /// the position nor the clock moves.
+    fn show_frame(&mut self, first_frame: bool) {
+        if !self.has_video || self.live_mode {
+            return;
+        }
+        for _ in 0..FIRST_FRAME_PACKETS {
+            if !self.video_queue.is_empty() {
+                break;
+            }
+            if self.cache.is_some() {
+                if !self.feed_from_cache() {
+                    break;
+                }
+                continue;
+            }
+            let Some(ictx) = self.input_context.as_mut() else {
+                return;
+            };
+            let mut packet = ffmpeg::Packet::empty();
+            match packet.read(ictx) {
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => break,
+                // Skipped like a corrupt packet during playback; the packet
+                // limit stops a source that keeps failing
+                Err(_) => continue,
+            }
+            let stream_idx = packet.stream();
+            if Some(stream_idx) == self.audio_stream_index {
+                self.decode_audio_packet(Some(&packet));
+            }
+            if Some(stream_idx) == self.video_stream_index {
+                self.decode_video_packet(Some(&packet));
+            }
+        }
+
+        let Some(frame) = self.video_queue.front().cloned() else {
+            eprintln!("No video frame decoded to show while paused");
+            return;
+        };
+        let frame = VideoFrame {
+            timestamp: self.media_time(frame.timestamp),
+            ..frame
+        };
+        self.presented_time = frame.timestamp;
+        if let Some(ref sender) = self.video_sender {
+            let _ = sender.send(frame.clone());
+        }
+        if first_frame {
+            let _ = self.event_tx.send(PlayerEvent::FirstFrame { frame });
+        }
+    }
+
+    /// Decode packets after a fast seek until the main stream (video, else
+    /// audio) yields a timestamp; returns that time
+    fn decode_to_keyframe(&mut self) -> Option<f64> {
+        let (main_index, time_base) = if self.has_video {
+            (self.video_stream_index, self.video_time_base)
+        } else {
+            (self.audio_stream_index, self.audio_time_base)
+        };
+        let time_base = time_base?;
+
+        for _ in 0..SEEK_PROBE_PACKETS {
+            let mut packet = ffmpeg::Packet::empty();
+            packet.read(self.input_context.as_mut()?).ok()?;
+
+            let stream_idx = packet.stream();
+            if Some(stream_idx) == self.audio_stream_index {
+                self.decode_audio_packet(Some(&packet));
+            }
+            if Some(stream_idx) == self.video_stream_index {
+                self.decode_video_packet(Some(&packet));
+            }
+            if Some(stream_idx) == main_index {
+                if let Some(pts) = packet.pts() {
+                    return Some(pts_to_seconds(pts, time_base, self.start_time));
+                }
+            }
+        }
+        None
+    }
+
+    /// Publish the backend that decoded the latest frame, warning on a fallback to software
+    fn track_backend(&self, backend: DecodeBackend) {
+        let mut active = self.decode_backend.lock();
+        if *active == Some(backend) {
+            return;
+        }
+        if let Some(previous) = active.filter(|&b| b != DecodeBackend::Software) {
+            if backend == DecodeBackend::Software {
+                let _ = self.event_tx.send(PlayerEvent::Warning {
+                    message: format!("Hardware decoding ({:?}) fell back to software", previous),
+                });
+            }
+        }
+        *active = Some(backend);
+    }
+
+    /// Seconds of decoded audio sent to the device but not yet played
+    fn audio_ahead(&self) -> f64 {
+        self.samples_sent.saturating_sub(self.audio_clock.played()) as f64
+            / self.samples_per_second()
+    }
+
+    /// Emit a throttled `PlaybackProgress` event while playing
+    fn report_progress(&mut self) {
+        if !self.is_playing || self.input_context.is_none() {
+            return;
+        }
+        let now = self.clock.now();
+        if self
+            .last_progress
+            .is_some_and(|last| now.saturating_duration_since(last) < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.last_progress = Some(now);
+        *self.queue_depth.lock() = QueueDepth {
+            video_frames: self.video_sender.as_ref().map_or(0, Sender::len),
+            audio_chunks: self
+                .audio_buffer
+                .as_ref()
+                .map_or(0, AudioBuffer::queued_chunks),
+            frame_data: self.frame_tx.len(),
+        };
+
+        let timeline = self
+            .sync
+            .audio_time(&self.audio_clock, self.samples_per_second())
+            .unwrap_or(self.presented_time)
+            .max(0.0);
+        let mut position = self.media_time(timeline);
+        if self.duration > 0.0 {
+            position = position.min(self.duration);
+        }
+        let _ = self.event_tx.send(PlayerEvent::PlaybackProgress {
+            position,
+            duration: self.duration,
+            buffered: (self.buffered_until - timeline).max(0.0),
+            position_fraction: position_fraction(position, self.duration),
+            position_text: format_time(position),
+            duration_text: format_duration(self.duration),
+        });
+        if let Some(stats) = self.current_network_stats() {
+            let _ = self.event_tx.send(PlayerEvent::NetworkProgress {
+                bytes_read: stats.bytes_read,
+                total_bytes: stats.total_bytes,
+            });
+        }
+    }
+
+    /// Byte counters of the input, for network sources only
+    fn current_network_stats(&self) -> Option<NetworkStats> {
+        if !self.network_source {
+            return None;
+        }
+        NetworkStats::from_input(self.input_context.as_ref()?, self.live_mode)
+    }
+
+    /// Resample to a new device rate, resuming from the position currently audible
+    fn set_output_sample_rate(&mut self, rate: u32) {
+        if rate == self.output_sample_rate || rate == 0 {
+            return;
+        }
+        let position = self
+            .sync
+            .audio_time(&self.audio_clock, self.samples_per_second())
+            .map_or(self.presented_time, |time| self.media_time(time));
+        self.output_sample_rate = rate;
+        // A WAV file has a single rate
+        if let Some(sink) = self.file_sink.take() {
+            sink.abort(&format!("The output rate changed to {} Hz", rate));
+            self.device_muted = false;
+        }
+        self.effects.set_sample_rate(rate);
+        self.auto_balance.set_sample_rate(rate);
+
+        if let Some(ref decoder) = self.audio_decoder {
+            match create_resampler(decoder, rate) {
+                Ok(resampler) => self.audio_resampler = Some(resampler),
+                Err(e) => eprintln!("Failed to rebuild resampler at {} Hz: {}", rate, e),
+            }
+            // Queued (and cached) audio was resampled for the old rate, also
+            // when none of it has played yet (the device rate is only known
+            // once the output is open). Live sources cannot seek and catch up
+            if self.input_context.is_some() && !self.live_mode {
+                self.refill_cache_or_seek(position);
+            }
+        }
+    }
+
+    /// Close the input and go idle until the next load
+    fn stop(&mut self) {
+        self.is_playing = false;
+        self.video_queue.clear();
+        self.held_silence.clear();
+        self.end_of_input = false;
+        self.sync.reset();
+        self.cache = None;
+        self.publish_cache_status();
+        self.file_sink = None;
+        self.device_muted = false;
+        self.loop_region = None;
+        self.loop_offset = 0.0;
+        // Reset decoders
+        self.input_context = None;
+        self.input_io = None;
+        self.audio_decoder = None;
+        self.video_decoder = None;
+        self.audio_resampler = None;
+        self.video_converter = None;
+        self.deinterlacer = None;
+        // Drop the outputs so the frame emitter thread of this load exits
+        self.video_sender = None;
+        self.audio_buffer = None;
+    }
+
+    /// Nobody receives the frames of this load any more: stop decoding rather
+    /// than producing into a dead channel
+    fn consumer_gone(&mut self, channel: &str) {
+        if self.input_context.is_some() || self.cache.is_some() {
+            eprintln!("{} receiver dropped, stopping decoder", channel);
+            self.stop();
+        }
+    }
+
+    /// End of input: drain frames still buffered in the decoders, then the
+    /// presentation queue
+    fn finish_input(&mut self) {
+        self.decode_audio_packet(None);
+        self.flush_resampler();
+        self.decode_video_packet(None);
+        self.flush_deinterlacer();
+        self.start_drain();
+    }
+
+    /// Nothing more is queued: play out the presentation queue and the audio
+    /// buffer, then end
+    fn start_drain(&mut self) {
+        self.skip_trailing_silence();
+        if let Some((samples, timestamp)) = self.change_tempo(None, self.buffered_until) {
+            self.play_audio(samples, timestamp, false);
+        }
+        self.end_of_input = true;
+        self.drain_deadline = Some(
+            self.clock.now()
+                + std::time::Duration::from_secs_f64(self.buffer_ahead + END_DRAIN_GRACE),
+        );
+    }
+
+    /// Where the current pass ends: the loop end, else the out point
+    fn end_point(&self) -> Option<f64> {
+        self.loop_region.map(|region| region.end).or(self.out_point)
+    }
+
+    /// Media time at `time` on the presentation timeline, which runs on
+    /// across loop passes
+    fn media_time(&self, time: f64) -> f64 {
+        self.loop_region.map_or(time, |region| region.fold(time))
+    }
+
+    /// The input reached the end point: start the next loop pass, or drain
+    /// and end
+    fn end_pass(&mut self) {
+        if self.loop_region.is_some() {
+            self.wrap_loop();
+        } else {
+            self.finish_input();
+        }
+    }
+
+    /// Seek back to the loop start while the end of this pass is still queued.
+    ///
+    /// Decoding runs up to `buffer_ahead` ahead of the clock, so the seek and
+    /// the decoding up to the loop start (from the keyframe before it) happen
+    /// while the last frames of the pass play. Nothing is flushed: the next
+    /// pass is cut to start exactly at the loop start and its timestamps
+    /// continue where this pass ends, so neither clock sees the boundary.
+    fn wrap_loop(&mut self) {
+        let Some(region) = self.loop_region else {
+            return;
+        };
+        if self.cache.is_none() {
+            // What the decoders still hold belongs to this pass
+            self.decode_audio_packet(None);
+            self.flush_resampler();
+            self.decode_video_packet(None);
+            self.flush_deinterlacer();
+        }
+        // A region nothing decodes in would be sought over and over
+        if self.buffered_until <= self.loop_pass_start {
+            let message = format!(
+                "Nothing plays between {:.3} s and {:.3} s, stopping the loop",
+                region.start, region.end
+            );
+            eprintln!("{}", message);
+            let _ = self.event_tx.send(PlayerEvent::Warning { message });
+            self.start_drain();
+            return;
+        }
+        self.loop_pass_start = self.buffered_until;
+        self.loop_offset += region.length();
+        self.seek_target = Some(region.start);
+
+        if let Some(cache) = self.cache.as_mut() {
+            cache.seek(region.start);
+            return;
+        }
+        if let Some(ref mut ictx) = self.input_context {
+            let timestamp = seconds_to_micros(region.start, self.start_time);
+            let _ = ictx.seek(timestamp, ..timestamp);
+        }
+        if let Some(ref mut dec) = self.audio_decoder {
+            dec.flush();
+        }
+        if let Some(ref mut dec) = self.video_decoder {
+            dec.flush();
+        }
+        self.deinterlacer = None;
+    }
+
+    /// Whether `packet` starts at or after the end point, ending the pass there
+    fn past_out_point(&self, packet: &ffmpeg::Packet) -> bool {
+        let Some(end) = self.end_point() else {
+            return false;
+        };
+        let time_base = if Some(packet.stream()) == self.audio_stream_index {
+            self.audio_time_base
+        } else if Some(packet.stream()) == self.video_stream_index {
+            self.video_time_base
+        } else {
+            None
+        };
+        match (time_base, packet.pts()) {
+            (Some(time_base), Some(pts)) => pts_to_seconds(pts, time_base, self.start_time) >= end,
+            _ => false,
+        }
+    }
+
+    /// One iteration of presentation and decoding while playing
+    fn step(&mut self) {
+        self.present_due_frames();
+        // Presenting stops playback if the frame receiver is gone
+        if !self.is_playing {
+            return;
+        }
+        self.update_frame_skipping();
+
+        // Keep a bounded amount of decoded media ahead of the clock: fill up to
+        // `buffer_ahead`, then wait until half of it has played
+        let ahead = self.audio_ahead();
+        let audio_ahead_limit = match self.sync.degradation_mode() {
+            DegradationMode::QualityFirst => self.buffer_ahead * QUALITY_FIRST_AUDIO_FACTOR,
+            DegradationMode::SmoothnessFirst => self.buffer_ahead,
+        };
+        let limit = if self.refilling {
+            audio_ahead_limit
+        } else {
+            audio_ahead_limit * BUFFER_LOW_WATER
+        };
+        self.refilling = ahead < limit;
+        let need_audio = self.has_audio
+            && self.audio_buffer.is_some()
+            && self.audio_output_connected
+            && self.refilling
+            && !self.audio_clock.flush_pending();
+        let need_video = self.has_video
+            && self.video_queue.len() < MAX_QUEUED_FRAMES
+            && self.video_queue_span() < self.buffer_ahead;
+        let starving = need_audio || need_video || (!self.has_audio && !self.has_video);
+
+        if self.end_of_input {
+            // End of file once everything queued has been presented and heard
+            let audio_queued = self.audio_buffer.is_some()
+                && self.audio_output_connected
+                && self.samples_sent > self.audio_clock.played()
+                && self
+                    .drain_deadline
+                    .is_some_and(|deadline| self.clock.now() < deadline);
+            if self.video_queue.is_empty() && !audio_queued {
+                self.is_playing = false;
+                self.end_of_input = false;
+                let _ = self.event_tx.send(PlayerEvent::PlaybackEnded);
+                if self.frame_tx.send(FrameData::EndOfFile).is_err() {
+                    self.consumer_gone("Frame");
+                }
+            } else {
+                self.clock.sleep(std::time::Duration::from_millis(2));
+            }
+            return;
+        }
+        if !starving {
+            // Buffers are full, wait for the clock to catch up
+            self.clock.sleep(std::time::Duration::from_millis(2));
+            return;
+        }
+
+        if self.cache.is_some() {
+            if !self.feed_from_cache() {
+                if self.loop_region.is_some() {
+                    self.wrap_loop();
+                } else {
+                    self.start_drain();
+                }
+            }
+            return;
+        }
+
+        // Get next packet
+        let mut packet = ffmpeg::Packet::empty();
+        let read = match self.input_context.as_mut() {
+            Some(ictx) => packet.read(ictx),
+            None => return,
+        };
+
+        if read.is_ok() {
+            self.read_failures = 0;
+        }
+        match read {
+            Ok(()) if self.past_out_point(&packet) => self.end_pass(),
+            Ok(()) => {
+                let stream_idx = packet.stream();
+                if Some(stream_idx) == self.audio_stream_index {
+                    self.decode_audio_packet(Some(&packet));
+                }
+                if Some(stream_idx) == self.video_stream_index {
+                    self.decode_video_packet(Some(&packet));
+                }
+            }
+            Err(ffmpeg::Error::Eof) => self.end_pass(),
+            Err(e) => self.read_failed(e),
+        }
+    }
+
+    /// A lost connection is re-opened and EAGAIN read again; other errors
+    /// are skipped like a corrupt packet until too many in a row end playback
+    fn read_failed(&mut self, error: ffmpeg::Error) {
+        match read_failure(&error, self.network_source) {
+            ReadFailure::Retry => self.clock.sleep(READ_RETRY_DELAY),
+            ReadFailure::Reconnect => self.reconnect(error),
+            ReadFailure::Skip => {
+                self.read_failures += 1;
+                if self.read_failures < MAX_READ_FAILURES {
+                    return;
+                }
+                self.read_failures = 0;
+                let path = self.file_path.clone().unwrap_or_default();
+                eprintln!("Reading {} failed: {}, ending playback", path, error);
+                let _ = self.event_tx.send(PlayerEvent::Warning {
+                    message: format!("Cannot read {}: {}", path, error),
+                });
+                self.finish_input();
+            }
+        }
+    }
+
+    /// Under `SmoothnessFirst`, skip decoding frames no other frame refers to
+    /// while late frames are dropped anyway, so the decoder catches up and
+    /// audio keeps its share of the CPU
+    fn update_frame_skipping(&mut self) {
+        let skip = self.sync.degradation_mode() == DegradationMode::SmoothnessFirst
+            && self.sync.is_dropping();
+        if skip == self.skipping_frames {
+            return;
+        }
+        let Some(decoder) = self.video_decoder.as_mut() else {
+            return;
+        };
+        decoder.skip_frame(if skip {
+            ffmpeg::Discard::NonReference
+        } else {
+            ffmpeg::Discard::Default
+        });
+        self.skipping_frames = skip;
+    }
+
+    /// Seconds between the first and last queued video frames
+    fn video_queue_span(&self) -> f64 {
+        match (self.video_queue.front(), self.video_queue.back()) {
+            (Some(first), Some(last)) => (last.timestamp - first.timestamp).max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Present queued video frames that are due on the master clock
+    fn present_due_frames(&mut self) {
+        let samples_per_second = self.samples_per_second();
+        while let Some(frame) = self.video_queue.front() {
+            let has_next = self.video_queue.len() > 1;
+            match self.sync.video_decision(
+                &self.audio_clock,
+                samples_per_second,
+                frame.timestamp,
+                has_next,
+            ) {
+                VideoDecision::Wait => break,
+                VideoDecision::Drop => {
+                    self.video_queue.pop_front();
+                }
+                VideoDecision::Present => {
+                    if let Some(mut frame) = self.video_queue.pop_front() {
+                        self.last_presented = Some(frame.timestamp);
+                        // Under a frame rate cap, only the first frame due in
+                        // each refresh interval is emitted
+                        if let Some(fps) = self.max_fps {
+                            // The epsilon keeps frames exactly on a boundary in it
+                            let slot = (frame.timestamp * fps + 1e-6).floor() as i64;
+                            if self.emitted_slot == Some(slot) {
+                                self.presented_time = self.media_time(frame.timestamp);
+                                self.sync.stats.frames_throttled += 1;
+                                continue;
+                            }
+                            self.emitted_slot = Some(slot);
+                        }
+                        // Send video frame to frontend if sender is available.
+                        // In live mode a frame still waiting to be emitted is
+                        // already late, so drop this one instead of queueing.
+                        frame.timestamp = self.media_time(frame.timestamp);
+                        self.presented_time = frame.timestamp;
+                        self.present_rate.tick(self.clock.now());
+                        if let Some(ref sender) = self.video_sender {
+                            if (!self.live_mode || sender.is_empty()) && sender.send(frame).is_err()
+                            {
+                                self.consumer_gone("Video frame");
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Feed a packet (`None` for end of stream) to the audio decoder and queue
+    /// all the audio it produces
+    fn decode_audio_packet(&mut self, packet: Option<&ffmpeg::Packet>) {
+        let mut input = Some(packet);
+        while let Some(packet) = input {
+            let Some(decoder) = self.audio_decoder.as_mut() else {
+                return;
+            };
+            match send_input(decoder, packet) {
+                Ok(true) => input = None,
+                // Output is full: take frames out, then offer the packet again
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Audio decoder rejected packet: {}", e);
+                    return;
+                }
+            }
+            if self.receive_audio_frames() == 0 && input.is_some() {
+                eprintln!("Audio decoder stalled, dropping packet");
+                return;
+            }
+        }
+    }
+
+    /// Resample and queue every frame the audio decoder has ready; returns the count
+    fn receive_audio_frames(&mut self) -> usize {
+        let (Some(decoder), Some(resampler)) =
+            (self.audio_decoder.as_mut(), self.audio_resampler.as_mut())
+        else {
+            return 0;
+        };
+
+        let mut received = 0;
+        let mut chunks = Vec::new();
+        let mut audio_lost = None;
+        let mut frame = ffmpeg::frame::Audio::empty();
+        while receive_output(decoder, &mut frame, "Audio") {
+            received += 1;
+            let mut timestamp = frame
+                .timestamp()
+                .map(|ts| pts_to_seconds(ts, self.audio_time_base.unwrap(), self.start_time))
+                .unwrap_or(0.0);
+
+            // Passthrough sends matching frames as they are; anything else is resampled
+            let unchanged = if self.passthrough {
+                passthrough_samples(&frame, self.output_sample_rate)
+            } else {
+                None
+            };
+            let passthrough = unchanged.is_some();
+            let mut samples = match unchanged {
+                Some(samples) => samples,
+                None => {
+                    // Frames must carry the layout the resampler was configured with
+                    if frame.channel_layout().is_empty() {
+                        let layout =
+                            source_channel_layout(frame.channel_layout(), frame.channels());
+                        frame.set_channel_layout(layout);
+                    }
+
+                    if self.source_mute_mask != 0 {
+                        mute_channels(&mut frame, self.source_mute_mask);
+                    }
+
+                    // Resample (and downmix) to stereo
+                    let mut resampled = ffmpeg::frame::Audio::empty();
+                    if let Err(e) = resampler.run(&frame, &mut resampled) {
+                        self.resampler_failures += 1;
+                        if self.resampler_failures < RESAMPLER_FAILURE_LIMIT {
+                            continue;
+                        }
+                        // The stream changed format or layout mid-way: start
+                        // over from what the frames carry now
+                        match resampler_for_frame(&frame, self.output_sample_rate) {
+                            Ok(rebuilt) => {
+                                eprintln!(
+                                    "Resampler failed {} times ({}), rebuilt for {} channels at {} Hz",
+                                    self.resampler_failures,
+                                    e,
+                                    frame.channels(),
+                                    frame.rate()
+                                );
+                                *resampler = rebuilt;
+                            }
+                            Err(e) => {
+                                audio_lost = Some(e);
+                                break;
+                            }
+                        }
+                        resampled = ffmpeg::frame::Audio::empty();
+                        if let Err(e) = resampler.run(&frame, &mut resampled) {
+                            audio_lost = Some(e);
+                            break;
+                        }
+                    }
+                    self.resampler_failures = 0;
+                    if resampled.samples() == 0 {
+                        continue;
+                    }
+                    interleave(&resampled, self.volume)
+                }
+            };
+
+            // Cut audio decoded ahead of an accurate seek target
+            let (target, rate) = (self.seek_target, self.output_sample_rate);
+            if !trim_to_seek_target(&mut samples, &mut timestamp, target, rate) {
+                continue;
+            }
+            chunks.push((samples, timestamp, passthrough));
+        }
+
+        for (samples, timestamp, passthrough) in chunks {
+            self.queue_audio(samples, timestamp, passthrough);
+        }
+        if let Some(e) = audio_lost {
+            self.abandon_audio(e);
+        }
+        received
+    }
+
+    /// Stop decoding audio whose resampler cannot be rebuilt; video plays on
+    /// against the wall clock
+    fn abandon_audio(&mut self, error: ffmpeg::Error) {
+        let message = if self.has_video {
+            format!(
+                "Cannot convert audio any more ({}), playing video only",
+                error
+            )
+        } else {
+            format!("Cannot convert audio any more ({})", error)
+        };
+        eprintln!("{}", message);
+        let _ = self.event_tx.send(PlayerEvent::Warning { message });
+        self.audio_decoder = None;
+        self.audio_resampler = None;
+        self.audio_stream_index = None;
+        self.has_audio = false;
+        // Without new samples the device clock stops; hand over to the wall clock
+        if let Some(ref buffer) = self.audio_buffer {
+            buffer.clear();
+        }
+        self.audio_clock.request_flush();
+        self.samples_sent = 0;
+        self.sync.reanchor();
+        self.apply_motion_rate();
+    }
+
+    /// Queue the samples the resampler still buffers internally (end of stream)
+    fn flush_resampler(&mut self) {
+        let Some(resampler) = self.audio_resampler.as_mut() else {
+            return;
+        };
+
+        let mut chunks = Vec::new();
+        loop {
+            let mut resampled = ffmpeg::frame::Audio::new(
+                ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
+                RESAMPLER_FLUSH_SAMPLES,
+                ffmpeg::channel_layout::ChannelLayout::STEREO,
+            );
+            if resampler.flush(&mut resampled).is_err() || resampled.samples() == 0 {
+                break;
+            }
+            chunks.push(interleave(&resampled, self.volume));
+        }
+
+        for samples in chunks {
+            self.queue_audio(samples, self.last_timestamp, false);
+        }
+    }
+
+    /// Queue interleaved output samples, holding back silence while skip
+    /// silence is on.
+    ///
+    /// Silent chunks are kept until the next audible one: before the first
+    /// sound of the file they are dropped (leading silence), otherwise played.
+    /// Whatever is still held at the end of the file is dropped (trailing
+    /// silence). Video files are never gated, skipping would break A/V sync.
+    fn queue_audio(&mut self, mut samples: Vec<f32>, timestamp: f64, passthrough: bool) {
+        if let Some(cache) = self.filling_cache.as_mut() {
+            self.last_timestamp = timestamp;
+            cache.push_audio(samples, timestamp);
+            return;
+        }
+        // Nothing plays past the end point; the chunk crossing it is cut
+        if let Some(end) = self.end_point() {
+            if timestamp >= end {
+                return;
+            }
+            let keep = ((end - timestamp) * self.output_sample_rate as f64) as usize
+                * OUTPUT_CHANNELS as usize;
+            samples.truncate(keep);
+        }
+        self.last_timestamp = timestamp;
+        let timestamp = timestamp + self.loop_offset;
+        let Some(threshold_db) = self.skip_silence.filter(|_| !self.has_video) else {
+            return self.send_audio(samples, timestamp, passthrough);
+        };
+        // Resampled samples already carry the volume; measure the source level
+        let gain = if passthrough { 1.0 } else { self.volume };
+        if gain <= 0.0 {
+            return self.send_audio(samples, timestamp, passthrough);
+        }
+
+        if rms_db(&samples) - 20.0 * gain.log10() < threshold_db {
+            self.held_silence.push((samples, timestamp, passthrough));
+            if self.held_silence_seconds() > MAX_HELD_SILENCE {
+                self.heard_audio = true;
+                self.release_held_silence();
+            }
+            return;
+        }
+
+        if !self.heard_audio && !self.held_silence.is_empty() {
+            let start = self.held_silence[0].1;
+            self.held_silence.clear();
+            let _ = self.event_tx.send(PlayerEvent::SilenceSkipped {
+                start,
+                end: timestamp,
+            });
+        } else {
+            self.release_held_silence();
+        }
+        self.heard_audio = true;
+        self.send_audio(samples, timestamp, passthrough);
+    }
+
+    /// Seconds of audio held back by the skip-silence gate
+    fn held_silence_seconds(&self) -> f64 {
+        let samples: usize = self.held_silence.iter().map(|(s, _, _)| s.len()).sum();
+        samples as f64 / self.samples_per_second()
+    }
+
+    /// Play the silence held back so far (it was a pause, not an intro or outro)
+    fn release_held_silence(&mut self) {
+        for (samples, timestamp, passthrough) in std::mem::take(&mut self.held_silence) {
+            self.send_audio(samples, timestamp, passthrough);
+        }
+    }
+
+    /// Drop the silence still held at the end of the file
+    fn skip_trailing_silence(&mut self) {
+        let Some((samples, last, _)) = self.held_silence.last() else {
+            return;
+        };
+        let end = last + samples.len() as f64 / self.samples_per_second();
+        let start = self.held_silence[0].1;
+        self.held_silence.clear();
+        let _ = self
+            .event_tx
+            .send(PlayerEvent::SilenceSkipped { start, end });
+    }
+
+    /// Change the speed of interleaved output samples (`None` flushes the
+    /// filter at the end of the stream) when not playing at 1x; returns the
+    /// samples that are ready and the media time of the first one, or `None`
+    /// when they play unchanged. If the filter cannot be set up, playback
+    /// goes on at 1x.
+    fn change_tempo(&mut self, samples: Option<&[f32]>, timestamp: f64) -> Option<(Vec<f32>, f64)> {
+        if self.speed == 1.0 {
+            return None;
+        }
+        let rate = self.output_sample_rate;
+        if !self
+            .tempo
+            .as_ref()
+            .is_some_and(|t| t.matches(rate, self.speed))
+        {
+            // A filter that was never fed has nothing to flush
+            samples?;
+            match Tempo::new(rate, self.speed) {
+                Ok(tempo) => self.tempo = Some(tempo),
+                Err(e) => {
+                    let message = format!("Cannot change the playback speed: {}", e);
+                    eprintln!("{}", message);
+                    let _ = self.event_tx.send(PlayerEvent::Warning { message });
+                    self.set_speed(1.0);
+                    return None;
+                }
+            }
+        }
+        match self.tempo.as_mut()?.process(samples, timestamp) {
+            Ok(output) => Some(output),
+            Err(e) => {
+                eprintln!("Playback speed filter rejected samples: {}", e);
+                self.tempo = None;
+                None
+            }
+        }
+    }
+
+    /// Change the speed of interleaved output samples, then run them through
+    /// the effects and send them to the output
+    fn send_audio(&mut self, samples: Vec<f32>, timestamp: f64, passthrough: bool) {
+        let end = timestamp + samples.len() as f64 / self.samples_per_second();
+        self.buffered_until = self.buffered_until.max(end);
+        // Passed-through samples are not bit-perfect away from 1x
+        let (samples, timestamp) = self
+            .change_tempo(Some(&samples), timestamp)
+            .unwrap_or((samples, timestamp));
+        if !samples.is_empty() {
+            self.play_audio(samples, timestamp, passthrough);
+        }
+    }
+
+    /// Run interleaved output samples through the effects (unless they are
+    /// passed through unchanged) and send them to the output
+    fn play_audio(&mut self, mut samples: Vec<f32>, timestamp: f64, passthrough: bool) {
+        let samples_per_second = self.samples_per_second();
+        if !passthrough {
+            if self.vocal_mode == VocalRemovalMode::PhaseCancel {
+                cancel_center(&mut samples);
+            }
+            if self.auto_balance.process(&mut samples) {
+                *self.shared_auto_balance.lock() = self.auto_balance.correction();
+            }
+            self.effects.process(&mut samples);
+            if self.mono_output {
+                downmix_mono(&mut samples);
+            }
+            for hook in self.dsp_hooks.iter_mut() {
+                hook.process(&mut samples, self.output_sample_rate, OUTPUT_CHANNELS);
+            }
+        }
+
+        self.note_first_output(false);
+        let now = self.clock.now();
+        if let Some(event) = self
+            .clip_monitor
+            .process(&samples, OUTPUT_CHANNELS as usize, now)
+        {
+            let _ = self.event_tx.send(event);
+        }
+        if self.clip_monitor.stats().enabled {
+            *self.clip_stats.lock() = self.clip_monitor.stats();
+        }
+
+        // The recording gets what the device would play; if it failed, the
+        // device takes over again
+        if self
+            .file_sink
+            .as_ref()
+            .is_some_and(|sink| !sink.write(&samples))
+        {
+            self.file_sink = None;
+            self.device_muted = false;
+        }
+        if self.device_muted {
+            samples.fill(0.0);
+        }
+
+        // Feed the audio output directly when connected
+        if let Some(ref buffer) = self.audio_buffer {
+            if !self.audio_output_connected {
+                return;
+            }
+            let samples = self.sync.correct_audio(
+                samples,
+                OUTPUT_CHANNELS as usize,
+                &self.audio_clock,
+                samples_per_second,
+            );
+            self.audio_clock.note_start(timestamp);
+            self.samples_sent += samples.len() as u64;
+            let _ = buffer.push_samples(samples);
+        } else if self
+            .frame_tx
+            .send(FrameData::Audio(AudioFrame {
+                samples,
+                timestamp: self.media_time(timestamp),
+            }))
+            .is_err()
+        {
+            self.consumer_gone("Frame");
+        }
+    }
+
+    /// Feed a packet (`None` for end of stream) to the video decoder and queue
+    /// all the frames it produces
+    fn decode_video_packet(&mut self, packet: Option<&ffmpeg::Packet>) {
+        let mut input = Some(packet);
+        while let Some(packet) = input {
+            let Some(decoder) = self.video_decoder.as_mut() else {
+                return;
+            };
+            match send_input(decoder, packet) {
+                Ok(true) => input = None,
+                // Output is full: take frames out, then offer the packet again
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Video decoder rejected packet: {}", e);
+                    return;
+                }
+            }
+            if self.receive_video_frames() == 0 && input.is_some() {
+                eprintln!("Video decoder stalled, dropping packet");
+                return;
+            }
+        }
+    }
+
+    /// Display size of frames as sent, with letterbox bars if enabled
+    fn display_dimensions(
+        &self,
+        width: u32,
+        height: u32,
+        sar: ffmpeg::Rational,
+    ) -> DisplayDimensions {
+        let (width, height) = match self.letterbox {
+            Some(letterbox) => {
+                let padding = letterbox.padding(width, height, sar, self.video_rotation);
+                (padding.width, padding.height)
+            }
+            None => (width, height),
+        };
+        DisplayDimensions::new(width, height, sar, self.video_rotation)
+    }
+
+    /// Convert and queue every frame the video decoder has ready; returns the count
+    fn receive_video_frames(&mut self) -> usize {
+        let mut received = 0;
+        let mut frame = ffmpeg::frame::Video::empty();
+        while self
+            .video_decoder
+            .as_mut()
+            .is_some_and(|decoder| receive_output(decoder, &mut frame, "Video"))
+        {
+            received += 1;
+            if frame.width() == 0 || frame.height() == 0 {
+                continue;
+            }
+            // Track aspect ratio changes signalled by the stream
+            let display =
+                self.display_dimensions(frame.width(), frame.height(), frame.aspect_ratio());
+            *self.display.lock() = display;
+            let current = *self.video_size.lock();
+            if let Some(size) = resized_to(current, (frame.width(), frame.height())) {
+                self.video_resized(size, display);
+            }
+            self.track_backend(
+                DecodeBackend::for_frame(frame.format()).unwrap_or(self.codec_backend),
+            );
+
+            if self.wants_deinterlace(&frame) {
+                for filtered in self.deinterlace(Some(&frame)) {
+                    self.queue_video_frame(&filtered);
+                }
+            } else {
+                self.queue_video_frame(&frame);
+            }
+        }
+        received
+    }
+
+    /// The stream changed resolution mid-playback (adaptive streams,
+    /// concatenated clips): publish the new size and tell the frontend.
+    ///
+    /// The scaler and deinterlacer are rebuilt for the next frame they get,
+    /// as they no longer match its dimensions; a size the scaler could not
+    /// handle before gets a fresh attempt.
+    fn video_resized(&mut self, (width, height): (u32, u32), display: DisplayDimensions) {
+        let (old_width, old_height) = *self.video_size.lock();
+        eprintln!(
+            "Video resolution changed from {}x{} to {}x{}",
+            old_width, old_height, width, height
+        );
+        *self.video_size.lock() = (width, height);
+        self.converter_failed = false;
+        let _ = self.event_tx.send(PlayerEvent::VideoResized {
+            width,
+            height,
+            display,
+        });
+    }
+
+    /// Whether `frame` goes through the deinterlacer
+    fn wants_deinterlace(&self, frame: &ffmpeg::frame::Video) -> bool {
+        // A filter already running keeps going for frames between flagged ones
+        let flagged = self.video_interlaced || frame.is_interlaced() || self.deinterlacer.is_some();
+        !self.deinterlace_failed && self.deinterlace_mode.applies(flagged)
+    }
+
+    /// Run a decoded frame (`None` to flush at the end of the stream) through
+    /// the deinterlacer, building it for the frame's format; returns the frames
+    /// to show. Frames pass unchanged if the filter cannot be set up.
+    fn deinterlace(&mut self, frame: Option<&ffmpeg::frame::Video>) -> Vec<ffmpeg::frame::Video> {
+        if let Some(frame) = frame {
+            if !self.deinterlacer.as_ref().is_some_and(|d| d.matches(frame)) {
+                let all_frames = self.deinterlace_mode == DeinterlaceMode::On;
+                match Deinterlacer::for_frame(frame, self.video_time_base.unwrap(), all_frames) {
+                    Ok(deinterlacer) => {
+                        self.deinterlacer = Some(deinterlacer);
+                        *self.deinterlacing.lock() = true;
+                    }
+                    Err(e) => {
+                        let message = format!("Cannot deinterlace video: {}", e);
+                        eprintln!("{}", message);
+                        let _ = self.event_tx.send(PlayerEvent::Warning { message });
+                        self.deinterlace_failed = true;
+                        self.deinterlacer = None;
+                        *self.deinterlacing.lock() = false;
+                        return vec![frame.clone()];
+                    }
+                }
+            }
+        }
+        let Some(deinterlacer) = self.deinterlacer.as_mut() else {
+            return Vec::new();
+        };
+        match deinterlacer.process(frame) {
+            Ok(frames) => frames,
+            Err(e) => {
+                eprintln!("Deinterlacer rejected frame: {}", e);
+                self.deinterlacer = None;
+                frame.into_iter().cloned().collect()
+            }
+        }
+    }
+
+    /// Queue the frame the deinterlacer still holds at the end of the stream
+    fn flush_deinterlacer(&mut self) {
+        if self.deinterlacer.is_none() {
+            return;
+        }
+        for filtered in self.deinterlace(None) {
+            self.queue_video_frame(&filtered);
+        }
+        // A flushed graph takes no more frames; seeks build a new one
+        self.deinterlacer = None;
+    }
+
+    /// Convert a decoded (and possibly deinterlaced) frame and queue it
+    fn queue_video_frame(&mut self, frame: &ffmpeg::frame::Video) {
+        let timestamp = frame
+            .timestamp()
+            .map(|ts| pts_to_seconds(ts, self.video_time_base.unwrap(), self.start_time))
+            .unwrap_or(0.0);
+        // Frames before an accurate seek target are decoded but never shown,
+        // neither are frames past the end point
+        if self
+            .seek_target
+            .is_some_and(|target| timestamp + SEEK_TOLERANCE < target)
+            || self.end_point().is_some_and(|end| timestamp >= end)
+        {
+            return;
+        }
+
+        // (Re)build the scaler chain if the frame format changed
+        if self.converter_failed {
+            return;
+        }
+        if !self
+            .video_converter
+            .as_ref()
+            .is_some_and(|c| c.matches(frame, self.tone_mapping, self.output_pixel_format))
+        {
+            match VideoConverter::for_frame(frame, self.tone_mapping, self.output_pixel_format) {
+                Ok(mut converter) => {
+                    converter.set_adjustments(self.video_adjustments);
+                    self.video_converter = Some(converter);
+                }
+                Err(e) => {
+                    let message = format!(
+                        "Cannot convert video to {:?} ({}), playing without video",
+                        self.output_pixel_format, e
+                    );
+                    eprintln!("{}", message);
+                    let _ = self.event_tx.send(PlayerEvent::Warning { message });
+                    self.video_converter = None;
+                    self.converter_failed = true;
+                    return;
+                }
+            }
+        }
+        let Some(converter) = self.video_converter.as_mut() else {
+            return;
+        };
+
+        // Scale to the output pixel format
+        let Ok(mut data) = converter.convert(frame) else {
+            return;
+        };
+        let mut width = frame.width();
+        let mut height = frame.height();
+        if let Some(letterbox) = self.letterbox {
+            let padding =
+                letterbox.padding(width, height, frame.aspect_ratio(), self.video_rotation);
+            data = letterbox.apply(&data, width, height, padding, self.output_pixel_format);
+            width = padding.width;
+            height = padding.height;
+        }
+
+        self.last_timestamp = timestamp;
+        let timestamp = timestamp + self.loop_offset;
+        self.buffered_until = self.buffered_until.max(timestamp);
+
+        // Queue for presentation on the master clock
+        self.enqueue_video_frame(VideoFrame {
+            width,
+            height,
+            data,
+            format: self.output_pixel_format,
+            timestamp,
+            color: ColorInfo::for_frame(frame),
+        });
+        self.decode_rate.tick(self.clock.now());
+        self.note_first_output(true);
+    }
+
+    /// Queue a frame in presentation (timestamp) order.
+    ///
+    /// Decoders return frames reordered from B-frame decode order already,
+    /// but streams with broken reordering can still hand one out after its
+    /// successor. Such a frame is sorted in among the frames queued ahead of
+    /// the clock, which act as the reorder window, or dropped if a later
+    /// frame has already been shown.
+    fn enqueue_video_frame(&mut self, frame: VideoFrame) {
+        let queued = self.video_queue.iter().map(|queued| queued.timestamp);
+        match reorder_slot(queued, self.last_presented, frame.timestamp) {
+            Some(index) => self.video_queue.insert(index, frame),
+            None => self.sync.stats.frames_out_of_order += 1,
+        }
+    }
+}
+
+/// The size of a decoded `frame` when it differs from the `current` video
+/// size; frames without dimensions are not a resize
+fn resized_to(current: (u32, u32), frame: (u32, u32)) -> Option<(u32, u32)> {
+    (frame.0 > 0 && frame.1 > 0 && frame != current).then_some(frame)
+}
+
+/// Index in the queue (timestamps `queued`) a frame at `timestamp` goes to,
+/// after every frame it does not precede; `None` when a frame it precedes
+/// was presented already
+fn reorder_slot(
+    mut queued: impl DoubleEndedIterator<Item = f64> + ExactSizeIterator,
+    last_presented: Option<f64>,
+    timestamp: f64,
+) -> Option<usize> {
+    let reordered =
+        |successor: f64| timestamp < successor && successor - timestamp <= VIDEO_REORDER_SPAN;
+    if last_presented.is_some_and(reordered) {
+        return None;
+    }
+    Some(
+        queued
+            .rposition(|queued| !reordered(queued))
+            .map_or(0, |i| i + 1),
+    )
+}
+
+/// Cut interleaved output samples starting before `target` so they start at
+/// it; false if the whole chunk lies before it
+fn trim_to_seek_target(
+    samples: &mut Vec<f32>,
+    timestamp: &mut f64,
+    target: Option<f64>,
+    rate: u32,
+) -> bool {
+    let Some(target) = target.filter(|target| *timestamp < *target) else {
+        return true;
+    };
+    // Rounded, as the difference of two times is rarely a whole frame count
+    let frames = ((target - *timestamp) * rate as f64).round() as usize;
+    let skip = frames * OUTPUT_CHANNELS as usize;
+    if skip >= samples.len() {
+        return false;
+    }
+    samples.drain(..skip);
+    *timestamp = target;
+    true
+}
+
+/// Interleave the planes of a resampled stereo frame, applying `gain`
+fn interleave(resampled: &ffmpeg::frame::Audio, gain: f32) -> Vec<f32> {
+    let sample_count = resampled.samples();
+    let channels = OUTPUT_CHANNELS as usize;
+    let mut samples = Vec::with_capacity(sample_count * channels);
+    let planes: Vec<&[f32]> = (0..channels).map(|ch| resampled.plane::<f32>(ch)).collect();
+    for i in 0..sample_count {
+        for plane in planes.iter() {
+            samples.push(plane[i] * gain);
+        }
+    }
+    samples
+}
+
+/// Why a stream's samples cannot reach the device unchanged, if they cannot
+fn passthrough_blocker_for(decoder: &ffmpeg::decoder::Audio) -> Option<String> {
+    use ffmpeg::format::Sample;
+
+    if decoder.channels() != OUTPUT_CHANNELS {
+        return Some(format!(
+            "the source has {} channels and the output is stereo",
+            decoder.channels()
+        ));
+    }
+    match decoder.format() {
+        Sample::I16(_) | Sample::I32(_) | Sample::F32(_) => None,
+        format => Some(format!(
+            "{:?} samples cannot be converted losslessly",
+            format
+        )),
+    }
+}
+
+/// A decoded frame as interleaved `f32`, if it can be sent to the device
+/// unchanged (stereo, at the output rate, in a format `f32` holds exactly;
+/// 32-bit integer sources are exact up to 24 significant bits)
+fn passthrough_samples(frame: &ffmpeg::frame::Audio, rate: u32) -> Option<Vec<f32>> {
+    use ffmpeg::format::Sample;
+
+    if frame.rate() != rate || frame.channels() != OUTPUT_CHANNELS {
+        return None;
+    }
+    match frame.format() {
+        Sample::F32(_) => Some(native_stereo::<f32>(frame, |s| s)),
+        Sample::I16(_) => Some(native_stereo::<i16>(frame, |s| s as f32 / 32768.0)),
+        Sample::I32(_) => Some(native_stereo::<i32>(frame, |s| s as f32 / 2147483648.0)),
+        _ => None,
+    }
+}
+
+/// Interleave a packed or planar stereo frame of `T` samples
+fn native_stereo<T>(frame: &ffmpeg::frame::Audio, convert: fn(T) -> f32) -> Vec<f32>
+where
+    T: ffmpeg::frame::audio::Sample + Copy,
+    (T, T): ffmpeg::frame::audio::Sample,
+{
+    let mut samples = Vec::with_capacity(frame.samples() * 2);
+    if frame.is_packed() {
+        for &(left, right) in frame.plane::<(T, T)>(0) {
+            samples.push(convert(left));
+            samples.push(convert(right));
+        }
+    } else {
+        for (&left, &right) in frame.plane::<T>(0).iter().zip(frame.plane::<T>(1)) {
+            samples.push(convert(left));
+            samples.push(convert(right));
+        }
+    }
+    samples
+}
+
+/// Offer a packet (`None` to start draining) to a decoder.
+///
+/// Returns `Ok(false)` if the decoder is full (EAGAIN) and frames must be
+/// received before the packet is accepted.
+fn send_input(
+    decoder: &mut ffmpeg::decoder::Opened,
+    packet: Option<&ffmpeg::Packet>,
+) -> Result<bool, ffmpeg::Error> {
+    let sent = match packet {
+        Some(packet) => decoder.send_packet(packet),
+        None => decoder.send_eof(),
+    };
+    input_accepted(sent)
+}
+
+/// Whether a decoder took its input, from the result of offering it
+fn input_accepted(sent: Result<(), ffmpeg::Error>) -> Result<bool, ffmpeg::Error> {
+    match sent {
+        Ok(()) => Ok(true),
+        Err(ffmpeg::Error::Other {
+            errno: ffmpeg::error::EAGAIN,
+        }) => Ok(false),
+        // Already draining
+        Err(ffmpeg::Error::Eof) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Receive the next decoded frame, returning false once the decoder needs more
+/// input or is drained. Genuine decode errors are logged and end the batch.
+fn receive_output(
+    decoder: &mut ffmpeg::decoder::Opened,
+    frame: &mut ffmpeg::Frame,
+    kind: &str,
+) -> bool {
+    output_received(decoder.receive_frame(frame), kind)
+}
+
+/// Whether a frame came out, from the result of asking for one
+fn output_received(received: Result<(), ffmpeg::Error>, kind: &str) -> bool {
+    match received {
+        Ok(()) => true,
+        Err(ffmpeg::Error::Other {
+            errno: ffmpeg::error::EAGAIN,
+        })
+        | Err(ffmpeg::Error::Eof) => false,
+        Err(e) => {
+            eprintln!("{} decode error: {}", kind, e);
+            false
+        }
+    }
+}
+
+/// Channel layout to resample from; unspecified layouts (e.g. WAV without a
+/// channel mask) get the default layout for their channel count so the
+/// downmix knows which channel is which
+pub fn source_channel_layout(
+    layout: ffmpeg::channel_layout::ChannelLayout,
+    channels: u16,
+) -> ffmpeg::channel_layout::ChannelLayout {
+    if layout.is_empty() || layout.channels() <= 0 {
+        ffmpeg::channel_layout::ChannelLayout::default(channels as i32)
+    } else {
+        layout
+    }
+}
+
+/// Create a decoder for a stream, naming the codec if none is available
+fn open_decoder(parameters: ffmpeg::codec::Parameters) -> Result<ffmpeg::decoder::Decoder> {
+    let codec = parameters.id();
+    let context =
+        ffmpeg::codec::Context::from_parameters(parameters).context("Invalid codec parameters")?;
+    if ffmpeg::decoder::find(codec).is_none() {
+        return Err(unsupported_codec(codec));
+    }
+    Ok(context.decoder())
+}
+
+/// Status of audio stream `idx` of a file once its decoder was opened, or
+/// failed to; files without audio stay `Missing`
+fn audio_status<T>(idx: usize, opened: &Result<T>) -> StreamStatus {
+    match opened {
+        Ok(_) => StreamStatus::Ready,
+        Err(e) => {
+            eprintln!("Audio stream {} failed to initialize: {:#}", idx, e);
+            StreamStatus::Failed(format!("{:#}", e))
+        }
+    }
+}
+
+/// Why a stream whose codec has no decoder in this FFmpeg build cannot play
+fn unsupported_codec(codec: ffmpeg::codec::Id) -> anyhow::Error {
+    anyhow::anyhow!("Unsupported codec {:?}, not built into this FFmpeg", codec)
+}
+
+/// Create a resampler converting the decoder's output to planar f32 stereo at `rate`
+///
+/// The resampler's channel conversion downmixes sources with more channels
+/// (center and surrounds are folded into left/right).
+fn create_resampler(
+    decoder: &ffmpeg::decoder::Audio,
+    rate: u32,
+) -> Result<ffmpeg::software::resampling::context::Context, ffmpeg::Error> {
+    ffmpeg::software::resampling::context::Context::get(
+        decoder.format(),
+        source_channel_layout(decoder.channel_layout(), decoder.channels()),
+        decoder.rate(),
+        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
+        ffmpeg::channel_layout::ChannelLayout::STEREO,
+        rate,
+    )
+}
+
+/// Like `create_resampler`, for the format, layout and rate `frame` carries
+fn resampler_for_frame(
+    frame: &ffmpeg::frame::Audio,
+    rate: u32,
+) -> Result<ffmpeg::software::resampling::context::Context, ffmpeg::Error> {
+    ffmpeg::software::resampling::context::Context::get(
+        frame.format(),
+        source_channel_layout(frame.channel_layout(), frame.channels()),
+        frame.rate(),
+        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
+        ffmpeg::channel_layout::ChannelLayout::STEREO,
+        rate,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    /// A decoder thread with nothing loaded, driven by the test instead of
+    /// `run`, with the sender of its commands and the receiver of its events
+    fn idle_thread() -> (DecoderThread, Sender<DecoderCommand>, Receiver<PlayerEvent>) {
+        let (cmd_tx, cmd_rx) = bounded(32);
+        let (frame_tx, _) = unbounded();
+        let (info_tx, _) = bounded(1);
+        let (event_tx, event_rx) = unbounded();
+        let shared = ThreadShared {
+            audio_clock: Arc::default(),
+            sync_stats: Arc::default(),
+            event_tx,
+            display: Arc::default(),
+            video_size: Arc::default(),
+            decode_backend: Arc::default(),
+            deinterlacing: Arc::default(),
+            network_stats: Arc::default(),
+            load_timings: Arc::default(),
+            cache_status: Arc::default(),
+            vocal_removal: Arc::default(),
+            auto_balance: Arc::default(),
+            clip_stats: Arc::default(),
+            queue_depth: Arc::default(),
+            clock: Arc::new(MockClock::new()),
+        };
+        let thread = DecoderThread::new(cmd_rx, frame_tx, info_tx, shared);
+        (thread, cmd_tx, event_rx)
+    }
+
+    #[test]
+    fn stop_releases_the_frame_outputs() {
+        let (mut thread, commands, _events) = idle_thread();
+        let (video_tx, video_rx) = unbounded();
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.video_sender = Some(video_tx);
+        thread.audio_buffer = Some(AudioBuffer::new(audio_tx, audio_rx));
+
+        commands.send(DecoderCommand::Stop).unwrap();
+        thread.apply_pending_commands();
+        // The frame emitter's receive loop ends instead of waiting forever
+        assert!(video_rx.recv().is_err());
+        assert!(thread.audio_buffer.is_none());
+    }
+
+    #[test]
+    fn load_and_stop_cycles_leave_no_emitter_running() {
+        let (mut thread, commands, _events) = idle_thread();
+        let (exited_tx, exited_rx) = unbounded();
+        let timeout = std::time::Duration::from_secs(5);
+        let mut previous_running = false;
+
+        for cycle in 0..10 {
+            // The same receive loop as the emitter each load spawns
+            let (video_tx, video_rx) = unbounded::<VideoFrame>();
+            let exited = exited_tx.clone();
+            std::thread::spawn(move || {
+                while video_rx.recv().is_ok() {}
+                let _ = exited.send(());
+            });
+            thread.begin_load(Some(video_tx), None);
+            if previous_running {
+                // Loading over a file ends the emitter of that file
+                assert!(exited_rx.recv_timeout(timeout).is_ok(), "cycle {}", cycle);
+            }
+
+            previous_running = cycle % 2 == 0;
+            if !previous_running {
+                commands.send(DecoderCommand::Stop).unwrap();
+                thread.apply_pending_commands();
+                assert!(exited_rx.recv_timeout(timeout).is_ok(), "cycle {}", cycle);
+            }
+        }
+        // Each of the ten emitters exited exactly once
+        assert!(exited_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn every_queued_command_applies_before_the_next_packet() {
+        let (mut thread, commands, _events) = idle_thread();
+        commands.send(DecoderCommand::Play).unwrap();
+        commands.send(DecoderCommand::Pause).unwrap();
+        assert!(thread.apply_pending_commands());
+        // A pause right after play holds before anything is read
+        assert!(!thread.is_playing);
+        assert!(thread.cmd_rx.is_empty());
+
+        commands.send(DecoderCommand::Pause).unwrap();
+        commands.send(DecoderCommand::Play).unwrap();
+        assert!(thread.apply_pending_commands());
+        assert!(thread.is_playing);
+
+        drop(commands);
+        assert!(!thread.apply_pending_commands());
+    }
+
+    /// Start a new file that plays `chunk` from memory and return what reaches
+    /// the audio output
+    fn play_cached_chunk(thread: &mut DecoderThread, chunk: Vec<f32>) -> Vec<f32> {
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.begin_load(None, Some(AudioBuffer::new(audio_tx, audio_rx.clone())));
+        let mut cache = FullCache::default();
+        cache.push_audio(chunk, 0.0);
+        thread.cache = Some(cache);
+        assert!(thread.feed_from_cache());
+        audio_rx.try_recv().unwrap()
+    }
+
+    /// Play a cached audio-only file until its input ends; returns the
+    /// receiver the audio went to
+    fn play_cached_audio_to_the_end(thread: &mut DecoderThread) -> Receiver<Vec<f32>> {
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.begin_load(None, Some(AudioBuffer::new(audio_tx, audio_rx.clone())));
+        // The device takes the flush a load requests
+        thread.audio_clock.take_flush();
+        thread.has_audio = true;
+        let mut cache = FullCache::default();
+        for i in 0..4 {
+            cache.push_audio(vec![0.25; 2 * 480], i as f64 * 0.01);
+        }
+        thread.cache = Some(cache);
+        thread.is_playing = true;
+        for _ in 0..8 {
+            thread.step();
+        }
+        assert!(thread.end_of_input);
+        audio_rx
+    }
+
+    #[test]
+    fn an_audio_codec_missing_from_ffmpeg_fails_rather_than_missing() {
+        let opened: Result<()> = Err(unsupported_codec(ffmpeg::codec::Id::OPUS));
+        assert_eq!(
+            audio_status(1, &opened),
+            StreamStatus::Failed("Unsupported codec OPUS, not built into this FFmpeg".to_string())
+        );
+        assert_eq!(audio_status(1, &Ok(())), StreamStatus::Ready);
+        // Only files without an audio stream report it missing
+        assert_eq!(ReadySummary::default().audio, StreamStatus::Missing);
+    }
+
+    #[test]
+    fn a_video_codec_missing_from_ffmpeg_is_reported() {
+        let (mut thread, _commands, events) = idle_thread();
+        thread.has_audio = true;
+        let reason = format!("{:#}", unsupported_codec(ffmpeg::codec::Id::AV1));
+        assert_eq!(reason, "Unsupported codec AV1, not built into this FFmpeg");
+        let ready = ReadySummary {
+            video: StreamStatus::Failed(reason.clone()),
+            ..ReadySummary::default()
+        };
+        thread.warn_video_failed(&ready);
+        let warning = events.try_iter().find_map(|event| match event {
+            PlayerEvent::Warning { message } => Some(message),
+            _ => None,
+        });
+        assert_eq!(
+            warning,
+            Some(format!(
+                "Cannot play the video ({}), playing audio only",
+                reason
+            ))
+        );
+
+        // Files whose video plays raise nothing
+        thread.warn_video_failed(&ReadySummary::default());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn audio_is_produced_at_the_device_rate() {
+        let (mut thread, commands, _events) = idle_thread();
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.begin_load(None, Some(AudioBuffer::new(audio_tx, audio_rx.clone())));
+        commands
+            .send(DecoderCommand::SetOutputSampleRate(48000))
+            .unwrap();
+        // An unknown rate keeps the current one
+        commands
+            .send(DecoderCommand::SetOutputSampleRate(0))
+            .unwrap();
+        thread.apply_pending_commands();
+        assert_eq!(thread.output_sample_rate, 48000);
+        assert_eq!(thread.samples_per_second(), 96000.0);
+
+        // The out point cuts chunks at the new rate: 10 ms is 480 frames
+        thread.out_point = Some(0.01);
+        thread.queue_audio(vec![0.25; 2 * 960], 0.0, false);
+        assert_eq!(audio_rx.try_recv().unwrap().len(), 2 * 480);
+    }
+
+    #[test]
+    fn timestamps_stay_in_media_time_across_loop_passes() {
+        let (mut thread, _commands, _events) = idle_thread();
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.begin_load(None, Some(AudioBuffer::new(audio_tx, audio_rx.clone())));
+        thread.loop_region = Some(LoopRegion {
+            start: 2.0,
+            end: 6.0,
+        });
+        // Second pass
+        thread.loop_offset = 4.0;
+        thread.queue_audio(vec![0.25; 2 * 480], 5.9, false);
+        assert_eq!(thread.last_timestamp, 5.9);
+
+        // The resampler tail is queued at the last timestamp and still plays
+        thread.queue_audio(vec![0.25; 2 * 48], thread.last_timestamp, false);
+        assert_eq!(audio_rx.try_iter().count(), 2);
+    }
+
+    fn ended(events: &Receiver<PlayerEvent>) -> bool {
+        events
+            .try_iter()
+            .any(|event| matches!(event, PlayerEvent::PlaybackEnded))
+    }
+
+    #[test]
+    fn playback_ends_once_the_queued_audio_is_heard() {
+        let (mut thread, _commands, events) = idle_thread();
+        let audio = play_cached_audio_to_the_end(&mut thread);
+        let sent: usize = audio.try_iter().map(|chunk| chunk.len()).sum();
+        assert_eq!(sent, 4 * 2 * 480);
+
+        thread.step();
+        assert!(thread.is_playing);
+        assert!(!ended(&events));
+
+        thread.audio_clock.add_played(thread.samples_sent);
+        thread.step();
+        assert!(!thread.is_playing);
+        assert!(ended(&events));
+    }
+
+    #[test]
+    fn playback_ends_when_queued_audio_stops_playing() {
+        let (mut thread, _commands, events) = idle_thread();
+        let _audio = play_cached_audio_to_the_end(&mut thread);
+        // The device never reports progress; the deadline ends the wait
+        let deadline = thread.drain_deadline.unwrap();
+        while thread.is_playing && thread.clock.now() <= deadline {
+            thread.step();
+        }
+        assert!(!thread.is_playing);
+        assert!(thread.clock.now() >= deadline);
+        assert!(ended(&events));
+    }
+
+    #[test]
+    fn audio_that_cannot_be_converted_leaves_the_video_playing() {
+        let (mut thread, _commands, events) = idle_thread();
+        let (audio_tx, audio_rx) = crate::audio_output::create_sample_channel();
+        thread.begin_load(None, Some(AudioBuffer::new(audio_tx, audio_rx.clone())));
+        thread.audio_clock.take_flush();
+        (thread.has_audio, thread.has_video) = (true, true);
+        thread.audio_stream_index = Some(1);
+        thread.send_audio(vec![0.25; 960], 0.0, false);
+        assert!(thread.samples_sent > 0);
+
+        thread.abandon_audio(ffmpeg::Error::InvalidData);
+        assert!(!thread.has_audio && thread.has_video);
+        assert_eq!(thread.audio_stream_index, None);
+        // Queued samples go, and the device clock no longer paces the video
+        assert!(audio_rx.is_empty());
+        assert_eq!(thread.samples_sent, 0);
+        assert!(thread.audio_clock.flush_pending());
+        assert!(events.try_iter().any(|event| matches!(
+            event,
+            PlayerEvent::Warning { message } if message.ends_with("playing video only")
+        )));
+    }
+
+    #[test]
+    fn settings_carry_over_to_the_next_file() {
+        let (mut thread, commands, _events) = idle_thread();
+        commands.send(DecoderCommand::SetVolume(0.5)).unwrap();
+        commands.send(DecoderCommand::SetMonoOutput(true)).unwrap();
+        commands
+            .send(DecoderCommand::SetToneMapping(false))
+            .unwrap();
+        thread.apply_pending_commands();
+
+        let played = play_cached_chunk(&mut thread, vec![1.0, 0.0, 1.0, 0.0]);
+        let mono = volume_gain(0.5) * std::f32::consts::FRAC_1_SQRT_2;
+        assert_eq!(played, vec![mono; 4]);
+        assert!(!thread.tone_mapping);
+    }
+
+    #[test]
+    fn reset_audio_effects_plays_samples_unprocessed() {
+        let (mut thread, commands, _events) = idle_thread();
+        let effects = AudioEffects {
+            preamp_db: 6.0,
+            balance: 1.0,
+            ..AudioEffects::default()
+        };
+        commands.send(DecoderCommand::SetVolume(1.0)).unwrap();
+        commands.send(DecoderCommand::SetEffects(effects)).unwrap();
+        commands.send(DecoderCommand::SetMonoOutput(true)).unwrap();
+        commands.send(DecoderCommand::SetAutoBalance(true)).unwrap();
+        commands
+            .send(DecoderCommand::SetVocalRemoval(true))
+            .unwrap();
+        thread.apply_pending_commands();
+        let chunk = vec![0.25, -0.5, 0.125, 0.5];
+        assert_ne!(play_cached_chunk(&mut thread, chunk.clone()), chunk);
+
+        commands.send(DecoderCommand::ResetAudioEffects).unwrap();
+        thread.apply_pending_commands();
+        assert_eq!(play_cached_chunk(&mut thread, chunk.clone()), chunk);
+    }
+
+    fn cached_frame(timestamp: f64) -> VideoFrame {
+        VideoFrame {
+            width: 2,
+            height: 2,
+            data: vec![0; 2 * 2 * 4],
+            format: PixelFormat::default(),
+            timestamp,
+            color: ColorInfo {
+                range: "limited",
+                primaries: "bt709",
+                transfer: "bt709",
+                matrix: "bt709",
+                tagged: false,
+            },
+        }
+    }
+
+    #[test]
+    fn a_dropped_consumer_stops_the_decoder() {
+        let (mut thread, _commands, _events) = idle_thread();
+        let (video_tx, video_rx) = unbounded();
+        thread.begin_load(Some(video_tx), None);
+        thread.has_video = true;
+        let mut cache = FullCache::default();
+        for i in 0..8 {
+            cache.push_video(cached_frame(i as f64 * 0.04));
+        }
+        thread.cache = Some(cache);
+        thread.is_playing = true;
+        drop(video_rx);
+
+        // The first step queues a frame, the second fails to send it
+        thread.step();
+        thread.step();
+        assert!(!thread.is_playing);
+        assert!(thread.cache.is_none());
+        assert!(thread.video_sender.is_none());
+        // The run loop only steps while playing, so nothing more is produced
+        assert!(thread.video_queue.is_empty());
+    }
+
+    #[test]
+    fn a_frame_at_the_seek_position_is_shown_while_paused() {
+        let (mut thread, commands, events) = idle_thread();
+        let (video_tx, video_rx) = unbounded();
+        thread.begin_load(Some(video_tx), None);
+        thread.has_video = true;
+        let mut cache = FullCache::default();
+        for i in 0..8 {
+            cache.push_video(cached_frame(i as f64 * 0.5));
+        }
+        // Where a seek to 2.0 leaves a cached file
+        cache.seek(2.0);
+        thread.cache = Some(cache);
+        thread.seek_target = Some(2.0);
+
+        commands.send(DecoderCommand::ShowFrame(false)).unwrap();
+        thread.apply_pending_commands();
+        assert_eq!(video_rx.try_recv().unwrap().timestamp, 2.0);
+        assert!(video_rx.try_recv().is_err());
+        assert_eq!(thread.presented_time, 2.0);
+        assert!(!thread.is_playing);
+        // Only the preview after a load counts as the first frame
+        assert!(!events
+            .try_iter()
+            .any(|event| matches!(event, PlayerEvent::FirstFrame { .. })));
+    }
+
+    #[test]
+    fn empty_pictures_are_rejected() {
+        assert!(check_video_dimensions(0, 1920, 1080).is_ok());
+        assert!(check_video_dimensions(0, 1, 1).is_ok());
+        assert_eq!(
+            check_video_dimensions(1, 0, 1080),
+            Err("Video stream 1 has invalid dimensions 0x1080".to_string())
+        );
+        assert!(check_video_dimensions(1, 1920, 0).is_err());
+        assert!(check_video_dimensions(1, 0, 0).is_err());
+    }
+
+    #[test]
+    fn a_full_decoder_asks_for_the_packet_again() {
+        let again = ffmpeg::Error::Other {
+            errno: ffmpeg::error::EAGAIN,
+        };
+        assert_eq!(input_accepted(Ok(())), Ok(true));
+        assert_eq!(input_accepted(Err(again)), Ok(false));
+        // Offering the end again while draining is not a failure
+        assert_eq!(input_accepted(Err(ffmpeg::Error::Eof)), Ok(true));
+        assert_eq!(
+            input_accepted(Err(ffmpeg::Error::InvalidData)),
+            Err(ffmpeg::Error::InvalidData)
+        );
+    }
+
+    #[test]
+    fn frames_are_received_until_more_input_is_needed() {
+        let again = ffmpeg::Error::Other {
+            errno: ffmpeg::error::EAGAIN,
+        };
+        assert!(output_received(Ok(()), "Video"));
+        assert!(!output_received(Err(again), "Video"));
+        assert!(!output_received(Err(ffmpeg::Error::Eof), "Video"));
+        assert!(!output_received(Err(ffmpeg::Error::InvalidData), "Video"));
+    }
+
+    #[test]
+    fn slow_motion_needs_a_clearly_higher_capture_rate() {
+        assert_eq!(slow_motion_factor(Some(240.0), Some(30.0)), Some(8.0));
+        // 30000/1001 played back at 29.97 is rounding, not slow motion
+        assert_eq!(slow_motion_factor(Some(30.0), Some(30000.0 / 1001.0)), None);
+        assert_eq!(slow_motion_factor(Some(24.0), Some(30.0)), None);
+        assert_eq!(slow_motion_factor(None, Some(30.0)), None);
+        assert_eq!(slow_motion_factor(Some(240.0), None), None);
+    }
+
+    #[test]
+    fn only_real_time_sources_are_live() {
+        assert!(is_live_source("rtsp://camera.local/stream"));
+        assert!(is_live_source("RTMP://example.com/live/key"));
+        assert!(is_live_source("udp://239.0.0.1:1234"));
+        assert!(is_live_source("/dev/video0"));
+        // A recording over TCP can be buffered and sought like a file
+        assert!(!is_live_source("tcp://media.local:9000"));
+        assert!(!is_live_source("https://example.com/video.mp4"));
+        assert!(!is_live_source("/home/user/video.mp4"));
+    }
+
+    #[test]
+    fn only_lost_connections_reconnect() {
+        use ffmpeg::error::{EAGAIN, ECONNRESET, EIO, ETIMEDOUT};
+        use ffmpeg::Error;
+        let eagain = Error::Other { errno: EAGAIN };
+        assert_eq!(read_failure(&eagain, true), ReadFailure::Retry);
+        assert_eq!(read_failure(&eagain, false), ReadFailure::Retry);
+        for errno in [EIO, ECONNRESET, ETIMEDOUT] {
+            let error = Error::Other { errno };
+            assert_eq!(read_failure(&error, true), ReadFailure::Reconnect);
+            // A local file has no connection to re-open
+            assert_eq!(read_failure(&error, false), ReadFailure::Skip);
+        }
+        // A corrupt packet is no reason to drop a working connection
+        assert_eq!(read_failure(&Error::InvalidData, true), ReadFailure::Skip);
+        assert_eq!(
+            read_failure(&Error::Other { errno: 0 }, true),
+            ReadFailure::Skip
+        );
+    }
+
+    #[test]
+    fn persistent_read_failures_end_playback_with_a_warning() {
+        let (mut thread, _commands, events) = idle_thread();
+        thread.file_path = Some("/media/broken.mkv".to_string());
+        for _ in 1..MAX_READ_FAILURES {
+            thread.read_failed(ffmpeg::Error::Other {
+                errno: ffmpeg::error::EIO,
+            });
+        }
+        // Skipped so far, like corrupt packets
+        assert!(!thread.end_of_input);
+        assert!(events.try_recv().is_err());
+
+        thread.read_failed(ffmpeg::Error::Other {
+            errno: ffmpeg::error::EIO,
+        });
+        assert!(thread.end_of_input);
+        let warned = events.try_iter().any(|event| {
+            matches!(event, PlayerEvent::Warning { ref message } if message.contains("broken.mkv"))
+        });
+        assert!(warned);
+    }
+
+    #[test]
+    fn only_inconclusive_probes_are_retried() {
+        use ffmpeg::Error;
+        assert!(probe_inconclusive(&Error::InvalidData));
+        assert!(probe_inconclusive(&Error::StreamNotFound));
+        assert!(probe_inconclusive(&Error::DemuxerNotFound));
+        // Missing or unreadable files fail the same way with larger limits
+        for error in [
+            Error::Other {
+                errno: ffmpeg::error::ENOENT,
+            },
+            Error::Other {
+                errno: ffmpeg::error::EACCES,
+            },
+            Error::ProtocolNotFound,
+            Error::Exit,
+        ] {
+            assert!(!probe_inconclusive(&error), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn stream_status_serializes_as_documented() {
+        let json = |status: StreamStatus| serde_json::to_value(status).unwrap();
+        assert_eq!(
+            json(StreamStatus::Missing),
+            serde_json::json!({ "state": "missing" })
+        );
+        assert_eq!(
+            json(StreamStatus::Ready),
+            serde_json::json!({ "state": "ready" })
+        );
+        assert_eq!(
+            json(StreamStatus::Failed("no decoder for opus".to_string())),
+            serde_json::json!({ "state": "failed", "error": "no decoder for opus" })
+        );
+        let summary = serde_json::to_value(ReadySummary::default()).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({ "audio": { "state": "missing" }, "video": { "state": "missing" } })
+        );
+    }
+
+    #[test]
+    fn declared_layouts_are_resampled_as_they_are() {
+        use ffmpeg::channel_layout::ChannelLayout;
+        let layouts = [
+            ChannelLayout::MONO,
+            ChannelLayout::STEREO,
+            ChannelLayout::_5POINT1,
+            ChannelLayout::_5POINT1_BACK,
+            ChannelLayout::_7POINT1,
+        ];
+        for layout in layouts {
+            // Not replaced by the default for the count (5.1 back stays 5.1 back)
+            let channels = layout.channels() as u16;
+            let source = source_channel_layout(layout, channels);
+            assert_eq!(source.bits(), layout.bits(), "{:?}", layout);
+            assert_eq!(source.channels(), layout.channels());
+        }
+    }
+
+    #[test]
+    fn resize_detection() {
+        let cases = [
+            ((1920, 1080), (1920, 1080), None),
+            ((1920, 1080), (1280, 720), Some((1280, 720))),
+            ((1280, 720), (1920, 1080), Some((1920, 1080))),
+            // Only one dimension changing is a resize too
+            ((1920, 1080), (1920, 800), Some((1920, 800))),
+            ((1920, 1080), (1440, 1080), Some((1440, 1080))),
+            // Nothing published yet (no size in the stream parameters)
+            ((0, 0), (640, 480), Some((640, 480))),
+            ((1920, 1080), (0, 1080), None),
+            ((1920, 1080), (0, 0), None),
+        ];
+        for (current, frame, resized) in cases {
+            assert_eq!(
+                resized_to(current, frame),
+                resized,
+                "{:?} -> {:?}",
+                current,
+                frame
+            );
+        }
+    }
+
+    fn slot(queued: &[f64], last_presented: Option<f64>, timestamp: f64) -> Option<usize> {
+        reorder_slot(queued.iter().copied(), last_presented, timestamp)
+    }
+
+    #[test]
+    fn frames_in_order_are_appended() {
+        assert_eq!(slot(&[], None, 0.0), Some(0));
+        assert_eq!(slot(&[0.0, 0.04], Some(-0.04), 0.08), Some(2));
+        // Equal timestamps keep decode order
+        assert_eq!(slot(&[0.0, 0.04], None, 0.04), Some(2));
+    }
+
+    #[test]
+    fn late_frames_are_sorted_into_the_queue() {
+        assert_eq!(slot(&[0.0, 0.08, 0.12], None, 0.04), Some(1));
+        assert_eq!(slot(&[0.08, 0.12], Some(0.0), 0.04), Some(0));
+    }
+
+    #[test]
+    fn frames_behind_the_presented_one_are_dropped() {
+        assert_eq!(slot(&[0.12], Some(0.08), 0.04), None);
+        assert_eq!(slot(&[], Some(0.04), 0.04), Some(0));
+    }
+
+    #[test]
+    fn timestamp_resets_keep_decode_order() {
+        // A jump back by more than the reorder span is a discontinuity
+        let back = VIDEO_REORDER_SPAN + 0.5;
+        assert_eq!(slot(&[10.0, 10.04], Some(9.96), 10.0 - back), Some(2));
+    }
+
+    #[test]
+    fn volume_gain_ends_and_clamping() {
+        assert_eq!(volume_gain(0.0), 0.0);
+        assert_eq!(volume_gain(-0.5), 0.0);
+        assert_eq!(volume_gain(1.0), 1.0);
+        assert_eq!(volume_gain(2.0), 1.0);
+        // Just above silence is the bottom of the range, not zero
+        let floor = 10f32.powf(-VOLUME_RANGE_DB / 20.0);
+        assert!((volume_gain(f32::MIN_POSITIVE) - floor).abs() < 1e-6);
+    }
+
+    #[test]
+    fn volume_gain_steps_are_equal_in_db() {
+        let db = |volume: f32| 20.0 * volume_gain(volume).log10();
+        // Each tenth of the scale is a tenth of the range
+        for step in 1..=10 {
+            let volume = step as f32 / 10.0;
+            let expected = (volume - 1.0) * VOLUME_RANGE_DB;
+            assert!(
+                (db(volume) - expected).abs() < 1e-3,
+                "{}: {}",
+                volume,
+                db(volume)
+            );
+        }
+        assert!((volume_gain(0.5) - 0.001f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn trim_keeps_chunks_at_or_after_the_target() {
+        let mut samples = vec![0.0; 400];
+        let mut timestamp = 0.5;
+        assert!(trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            None,
+            1000
+        ));
+        assert!(trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            Some(0.5),
+            1000
+        ));
+        assert!(trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            Some(0.25),
+            1000
+        ));
+        assert_eq!((samples.len(), timestamp), (400, 0.5));
+    }
+
+    #[test]
+    fn trim_cuts_whole_frames_before_the_target() {
+        // 200 stereo frames at 1 kHz from 0.5 s; the target is 125 frames in
+        let mut samples: Vec<f32> = (0..400).map(|i| i as f32).collect();
+        let mut timestamp = 0.5;
+        assert!(trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            Some(0.625),
+            1000
+        ));
+        assert_eq!(timestamp, 0.625);
+        assert_eq!(samples.len(), 150);
+        // Still starting on a left sample
+        assert_eq!(samples[0], 250.0);
+    }
+
+    #[test]
+    fn trim_drops_chunks_ending_before_the_target() {
+        let mut samples = vec![0.0; 400];
+        let mut timestamp = 0.5;
+        assert!(!trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            Some(0.7),
+            1000
+        ));
+        assert!(!trim_to_seek_target(
+            &mut samples,
+            &mut timestamp,
+            Some(9.0),
+            1000
+        ));
     }
 }
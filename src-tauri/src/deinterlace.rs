@@ -0,0 +1,151 @@
+use ffmpeg_next as ffmpeg;
+use ffmpeg_next::codec::field_order::FieldOrder;
+
+/// When decoded video is deinterlaced
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum DeinterlaceMode {
+    /// Only sources flagged as interlaced (by the stream or the frame)
+    #[default]
+    Auto,
+    /// Every frame, for interlaced content that is not flagged
+    On,
+    Off,
+}
+
+impl DeinterlaceMode {
+    /// Whether a frame is deinterlaced, given whether the source is flagged
+    /// as interlaced
+    pub fn applies(self, flagged: bool) -> bool {
+        match self {
+            DeinterlaceMode::Auto => flagged,
+            DeinterlaceMode::On => true,
+            DeinterlaceMode::Off => false,
+        }
+    }
+}
+
+/// Whether stream parameters describe interlaced video
+pub fn stream_interlaced(parameters: &ffmpeg::codec::Parameters) -> bool {
+    let order = unsafe { (*parameters.as_ptr()).field_order };
+    field_order_interlaced(FieldOrder::from(order))
+}
+
+/// Whether video with field order `order` is interlaced
+fn field_order_interlaced(order: FieldOrder) -> bool {
+    !matches!(order, FieldOrder::Progressive | FieldOrder::Unknown)
+}
+
+/// `bwdif` filter graph for frames of one format and size.
+///
+/// Outputs one frame per input frame, one frame late (the filter looks at
+/// the next field), so the decoder flushes it at the end of the stream.
+pub struct Deinterlacer {
+    format: ffmpeg::format::Pixel,
+    width: u32,
+    height: u32,
+    graph: ffmpeg::filter::Graph,
+}
+
+impl Deinterlacer {
+    /// Build a graph for `frame`; `all_frames` also deinterlaces frames not
+    /// flagged as interlaced
+    pub fn for_frame(
+        frame: &ffmpeg::frame::Video,
+        time_base: ffmpeg::Rational,
+        all_frames: bool,
+    ) -> Result<Self, ffmpeg::Error> {
+        let aspect = frame.aspect_ratio();
+        let (sar_num, sar_den) = if aspect.numerator() > 0 && aspect.denominator() > 0 {
+            (aspect.numerator(), aspect.denominator())
+        } else {
+            (1, 1)
+        };
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+            frame.width(),
+            frame.height(),
+            ffmpeg::ffi::AVPixelFormat::from(frame.format()) as i32,
+            time_base.numerator(),
+            time_base.denominator(),
+            sar_num,
+            sar_den
+        );
+
+        let mut graph = ffmpeg::filter::Graph::new();
+        let buffer = ffmpeg::filter::find("buffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let sink = ffmpeg::filter::find("buffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+        graph.add(&buffer, "in", &args)?;
+        graph.add(&sink, "out", "")?;
+        let deint = if all_frames { "all" } else { "interlaced" };
+        let spec = format!("bwdif=mode=send_frame:parity=auto:deint={}", deint);
+        graph.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        graph.validate()?;
+
+        Ok(Self {
+            format: frame.format(),
+            width: frame.width(),
+            height: frame.height(),
+            graph,
+        })
+    }
+
+    /// Whether this graph was built for frames like `frame`
+    pub fn matches(&self, frame: &ffmpeg::frame::Video) -> bool {
+        self.format == frame.format()
+            && self.width == frame.width()
+            && self.height == frame.height()
+    }
+
+    /// Feed a decoded frame (`None` at the end of the stream) and return the
+    /// deinterlaced frames that are ready
+    pub fn process(
+        &mut self,
+        frame: Option<&ffmpeg::frame::Video>,
+    ) -> Result<Vec<ffmpeg::frame::Video>, ffmpeg::Error> {
+        {
+            let mut source = self.graph.get("in").ok_or(ffmpeg::Error::FilterNotFound)?;
+            match frame {
+                Some(frame) => source.source().add(frame)?,
+                None => source.source().flush()?,
+            }
+        }
+
+        let mut sink = self.graph.get("out").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let mut frames = Vec::new();
+        loop {
+            let mut filtered = ffmpeg::frame::Video::empty();
+            if sink.sink().frame(&mut filtered).is_err() {
+                break;
+            }
+            frames.push(filtered);
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_follows_the_interlaced_flag() {
+        assert!(DeinterlaceMode::Auto.applies(true));
+        assert!(!DeinterlaceMode::Auto.applies(false));
+        assert!(DeinterlaceMode::On.applies(false));
+        assert!(!DeinterlaceMode::Off.applies(true));
+    }
+
+    #[test]
+    fn only_field_orders_with_fields_are_interlaced() {
+        assert!(!field_order_interlaced(FieldOrder::Progressive));
+        assert!(!field_order_interlaced(FieldOrder::Unknown));
+        for order in [
+            FieldOrder::TT,
+            FieldOrder::BB,
+            FieldOrder::TB,
+            FieldOrder::BT,
+        ] {
+            assert!(field_order_interlaced(order));
+        }
+    }
+}
@@ -0,0 +1,12 @@
+/// Custom audio processing inserted after the equalizer.
+///
+/// Hooks run on the decoder thread for every decoded chunk, before samples
+/// are queued for the device. They share that thread with demuxing and
+/// decoding, so `process` must be real-time safe: no allocation, no locks, no
+/// I/O and no blocking. A slow hook delays decoding and the device underruns.
+pub trait DspHook: Send {
+    /// Modify interleaved samples in place.
+    ///
+    /// `samples.len()` is a multiple of `channels`; the length must not change.
+    fn process(&mut self, samples: &mut [f32], sample_rate: u32, channels: u16);
+}
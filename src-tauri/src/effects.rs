@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+
+/// Centre frequencies (Hz) of the graphic equalizer bands
+pub const EQ_BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// Bandwidth of each equalizer band (about one octave)
+const EQ_Q: f32 = 1.41;
+
+/// Limits for user-supplied gains
+const MAX_GAIN_DB: f32 = 24.0;
+
+/// User-facing audio effect settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioEffects {
+    /// Gain applied before the equalizer, in dB
+    pub preamp_db: f32,
+    /// Stereo balance from -1.0 (left only) to 1.0 (right only)
+    pub balance: f32,
+    /// Gain for each of `EQ_BANDS`, in dB
+    pub eq_gains_db: [f32; EQ_BANDS.len()],
+}
+
+impl AudioEffects {
+    /// Clamp values into their valid ranges
+    pub fn clamped(mut self) -> Self {
+        self.preamp_db = self.preamp_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        self.balance = self.balance.clamp(-1.0, 1.0);
+        for gain in self.eq_gains_db.iter_mut() {
+            *gain = gain.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        }
+        self
+    }
+
+    /// Whether the settings leave the signal untouched
+    pub fn is_flat(&self) -> bool {
+        self.preamp_db == 0.0 && self.balance == 0.0 && self.eq_gains_db.iter().all(|g| *g == 0.0)
+    }
+}
+
+/// Peaking biquad filter (RBJ cookbook), direct form I
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn peaking(frequency: f32, gain_db: f32, sample_rate: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * frequency / sample_rate;
+        let alpha = w0.sin() / (2.0 * EQ_Q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * cos / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha / a) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Effect chain applied to interleaved stereo samples on the decoder thread
+pub struct EffectsChain {
+    settings: AudioEffects,
+    sample_rate: u32,
+    /// Active equalizer bands, one filter per channel
+    filters: Vec<[Biquad; 2]>,
+}
+
+impl EffectsChain {
+    pub fn new(settings: AudioEffects, sample_rate: u32) -> Self {
+        let filters = EQ_BANDS
+            .iter()
+            .zip(settings.eq_gains_db.iter())
+            // Bands above Nyquist would be unstable
+            .filter(|(freq, gain)| **gain != 0.0 && **freq < sample_rate as f32 / 2.0)
+            .map(|(freq, gain)| [Biquad::peaking(*freq, *gain, sample_rate as f32); 2])
+            .collect();
+        Self {
+            settings,
+            sample_rate,
+            filters,
+        }
+    }
+
+    /// Rebuild the filters for a new output rate
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate != self.sample_rate {
+            *self = Self::new(self.settings.clone(), sample_rate);
+        }
+    }
+
+    /// Process interleaved stereo samples in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if self.settings.is_flat() {
+            return;
+        }
+        let preamp = 10f32.powf(self.settings.preamp_db / 20.0);
+        let balance = self.settings.balance;
+        let gains = [(1.0 - balance).min(1.0), (1.0 + balance).min(1.0)];
+
+        for frame in samples.chunks_exact_mut(2) {
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                let mut value = *sample * preamp;
+                for filter in self.filters.iter_mut() {
+                    value = filter[ch].process(value);
+                }
+                *sample = value * gains[ch];
+            }
+        }
+    }
+}
+
+/// Level of each channel in the mono sum (-3 dB)
+const MONO_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Replace both channels of interleaved stereo samples with their sum at -3 dB
+pub fn downmix_mono(samples: &mut [f32]) {
+    for frame in samples.chunks_exact_mut(2) {
+        let mono = (frame[0] + frame[1]) * MONO_GAIN;
+        frame[0] = mono;
+        frame[1] = mono;
+    }
+}
+
+/// Seconds of audio measured before the auto-balance correction is fixed
+const AUTO_BALANCE_WINDOW: f64 = 5.0;
+
+/// Channel level differences below this are left alone, in dB
+const AUTO_BALANCE_TOLERANCE_DB: f32 = 0.5;
+
+/// Largest correction applied to a channel, in dB
+const MAX_AUTO_BALANCE_DB: f32 = 12.0;
+
+/// Channels quieter than this are treated as silent and not matched, in dB
+const AUTO_BALANCE_FLOOR_DB: f32 = -70.0;
+
+/// Gain auto-balance applies to each channel, in dB (0.0 or below)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct BalanceCorrection {
+    pub left_db: f32,
+    pub right_db: f32,
+}
+
+/// Matches the level of the two stereo channels of files with one channel
+/// recorded too loud.
+///
+/// The RMS of each channel is measured over the first `AUTO_BALANCE_WINDOW`
+/// seconds of a file, then the louder channel is attenuated to the level of
+/// the quieter one for the rest of it. This runs before `EffectsChain`, so
+/// the manual balance still applies on top.
+pub struct AutoBalance {
+    enabled: bool,
+    sample_rate: u32,
+    sum_squares: [f64; 2],
+    frames: u64,
+    /// `None` while measuring
+    correction: Option<BalanceCorrection>,
+    gains: [f32; 2],
+}
+
+impl AutoBalance {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            enabled: false,
+            sample_rate,
+            sum_squares: [0.0; 2],
+            frames: 0,
+            correction: None,
+            gains: [1.0; 2],
+        }
+    }
+
+    /// Enabling starts a new measurement from the next samples
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            self.reset();
+        }
+    }
+
+    /// The measurement window is counted at this rate
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Forget the correction and measure again, e.g. for a new file
+    pub fn reset(&mut self) {
+        self.sum_squares = [0.0; 2];
+        self.frames = 0;
+        self.correction = None;
+        self.gains = [1.0; 2];
+    }
+
+    /// Correction in use, `None` while disabled or still measuring
+    pub fn correction(&self) -> Option<BalanceCorrection> {
+        self.correction.filter(|_| self.enabled)
+    }
+
+    /// Measure or correct interleaved stereo samples in place; returns true
+    /// when this call finished the measurement
+    pub fn process(&mut self, samples: &mut [f32]) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.correction.is_some() {
+            if self.gains != [1.0; 2] {
+                for frame in samples.chunks_exact_mut(2) {
+                    frame[0] *= self.gains[0];
+                    frame[1] *= self.gains[1];
+                }
+            }
+            return false;
+        }
+
+        for frame in samples.chunks_exact(2) {
+            self.sum_squares[0] += (frame[0] as f64).powi(2);
+            self.sum_squares[1] += (frame[1] as f64).powi(2);
+        }
+        self.frames += (samples.len() / 2) as u64;
+        if (self.frames as f64) < AUTO_BALANCE_WINDOW * self.sample_rate as f64 {
+            return false;
+        }
+
+        let level_db = |sum: f64| 10.0 * (sum / self.frames as f64).max(1e-12).log10() as f32;
+        let (left, right) = (level_db(self.sum_squares[0]), level_db(self.sum_squares[1]));
+        let difference = left - right;
+        let mut correction = BalanceCorrection::default();
+        // A silent channel is more likely intended than damaged
+        if left.min(right) > AUTO_BALANCE_FLOOR_DB && difference.abs() >= AUTO_BALANCE_TOLERANCE_DB
+        {
+            let cut = -difference.abs().min(MAX_AUTO_BALANCE_DB);
+            if difference > 0.0 {
+                correction.left_db = cut;
+            } else {
+                correction.right_db = cut;
+            }
+        }
+        self.gains = [
+            10f32.powf(correction.left_db / 20.0),
+            10f32.powf(correction.right_db / 20.0),
+        ];
+        self.correction = Some(correction);
+        true
+    }
+}
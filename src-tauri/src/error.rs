@@ -0,0 +1,107 @@
+use ffmpeg_next as ffmpeg;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Error returned to the frontend by player commands.
+///
+/// Serializes as `{ "code": "not_loaded", "message": "..." }` so the UI can
+/// branch on `code` while still showing `message` to the user.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayerError {
+    /// No media is loaded
+    NotLoaded,
+    /// The media could not be demuxed or decoded
+    DecodeFailed(String),
+    /// The file or stream could not be read
+    Io(String),
+    /// Codec, container or protocol not supported by this build
+    Unsupported(String),
+    /// The current source does not support seeking
+    NotSeekable,
+    /// The audio device could not be opened or driven
+    AudioOutput(String),
+    /// A command argument was out of range or referred to something unknown
+    InvalidArgument(String),
+    /// Anything else (closed channels, poisoned state, ...)
+    Internal(String),
+}
+
+impl PlayerError {
+    /// Stable machine-readable code
+    pub fn code(&self) -> &'static str {
+        match self {
+            PlayerError::NotLoaded => "not_loaded",
+            PlayerError::DecodeFailed(_) => "decode_failed",
+            PlayerError::Io(_) => "io",
+            PlayerError::Unsupported(_) => "unsupported",
+            PlayerError::NotSeekable => "not_seekable",
+            PlayerError::AudioOutput(_) => "audio_output",
+            PlayerError::InvalidArgument(_) => "invalid_argument",
+            PlayerError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::NotLoaded => write!(f, "No media loaded"),
+            PlayerError::DecodeFailed(msg) => write!(f, "Failed to decode media: {}", msg),
+            PlayerError::Io(msg) => write!(f, "Failed to read media: {}", msg),
+            PlayerError::Unsupported(msg) => write!(f, "Unsupported media: {}", msg),
+            PlayerError::NotSeekable => write!(f, "Media is not seekable"),
+            PlayerError::AudioOutput(msg) => write!(f, "Audio output error: {}", msg),
+            PlayerError::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            PlayerError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PlayerError {}
+
+impl Serialize for PlayerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PlayerError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<ffmpeg::Error> for PlayerError {
+    fn from(err: ffmpeg::Error) -> Self {
+        match err {
+            ffmpeg::Error::DecoderNotFound
+            | ffmpeg::Error::DemuxerNotFound
+            | ffmpeg::Error::ProtocolNotFound
+            | ffmpeg::Error::StreamNotFound
+            | ffmpeg::Error::PatchWelcome => PlayerError::Unsupported(err.to_string()),
+            ffmpeg::Error::Other { .. } | ffmpeg::Error::Eof | ffmpeg::Error::Exit => {
+                PlayerError::Io(err.to_string())
+            }
+            _ => PlayerError::DecodeFailed(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for PlayerError {
+    fn from(err: std::io::Error) -> Self {
+        PlayerError::Io(err.to_string())
+    }
+}
+
+impl From<anyhow::Error> for PlayerError {
+    fn from(err: anyhow::Error) -> Self {
+        // Keep the category when a PlayerError was propagated through anyhow
+        match err.downcast::<PlayerError>() {
+            Ok(e) => e,
+            Err(err) => match err.downcast::<ffmpeg::Error>() {
+                Ok(e) => e.into(),
+                Err(err) => match err.downcast::<std::io::Error>() {
+                    Ok(e) => e.into(),
+                    Err(err) => PlayerError::Internal(format!("{:#}", err)),
+                },
+            },
+        }
+    }
+}
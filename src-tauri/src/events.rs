@@ -0,0 +1,160 @@
+use crate::analysis::AudioAnalysis;
+use crate::decoder::{DisplayDimensions, LoadTimings, VideoFrame};
+use crate::effects::AudioEffects;
+use crate::registry::PlayerId;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Event payload with the id of the player that raised it added alongside its fields
+#[derive(Clone, Serialize)]
+pub struct Tagged<'a, T: Serialize> {
+    player_id: PlayerId,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+impl<'a, T: Serialize> Tagged<'a, T> {
+    pub fn new(player_id: PlayerId, payload: &'a T) -> Self {
+        Self { player_id, payload }
+    }
+}
+
+/// Asynchronous notifications forwarded to the frontend as Tauri events
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum PlayerEvent {
+    /// The source stalled and a reconnect attempt is in progress
+    Buffering { attempt: u32, max_attempts: u32 },
+    /// Something went wrong but playback continues (or stopped gracefully)
+    Warning { message: String },
+    /// A stream uses an experimental decoder or a problematic profile
+    CompatibilityWarning {
+        stream_index: usize,
+        codec: String,
+        message: String,
+    },
+    /// Fraction (0.0 to 1.0) of a range export written so far
+    ExportProgress { progress: f64 },
+    /// A range export completed (`error` is null) or failed/was cancelled
+    ExportFinished {
+        out_path: String,
+        error: Option<String>,
+    },
+    /// Fraction (0.0 to 1.0) of the audio in `path` analyzed so far
+    AnalysisProgress { path: String, progress: f64 },
+    /// The analysis of `path` completed (`error` is null) or failed/was cancelled
+    AnalysisFinished {
+        path: String,
+        analysis: Option<AudioAnalysis>,
+        error: Option<String>,
+    },
+    /// Recording to `path` ended and the file was finalized; `error` says why
+    /// if it ended on its own (disk full, size limit, output rate changed)
+    RecordingStopped { path: String, error: Option<String> },
+    /// A frame of a streamed `decode_frames` request (`index` counts from 0)
+    DecodedFrame { index: usize, frame: VideoFrame },
+    /// A streamed `decode_frames` request ended after `count` frames
+    DecodeFramesFinished { count: usize, error: Option<String> },
+    /// The output reached full scale: `channel` (0 left, 1 right) peaked at
+    /// `peak_db` dBFS, between samples if `intersample`. Raised at most once a
+    /// second for the loudest over since the last one, with the running counts
+    ClippingDetected {
+        channel: usize,
+        peak_db: f64,
+        intersample: bool,
+        clipped_samples: u64,
+        intersample_overs: u64,
+    },
+    /// The audio processing settings changed all together (`reset_audio_effects`)
+    AudioEffectsChanged {
+        effects: AudioEffects,
+        auto_balance: bool,
+        mono_output: bool,
+        vocal_removal: bool,
+        channel_mute: u64,
+    },
+    /// Leading or trailing silence from `start` to `end` seconds was skipped
+    SilenceSkipped { start: f64, end: f64 },
+    /// First video frame of a freshly loaded file, decoded before playback starts
+    FirstFrame { frame: VideoFrame },
+    /// Every stream of a freshly loaded file has decoded its first output
+    MediaLoaded { timings: LoadTimings },
+    /// The video changed resolution mid-playback to `width`x`height` (coded
+    /// size); `display` is the size to show it at from now on
+    VideoResized {
+        width: u32,
+        height: u32,
+        display: DisplayDimensions,
+    },
+    /// Keyframe near the position being scrubbed to
+    ScrubFrame { frame: VideoFrame },
+    /// Periodic position update while playing; `buffered` is the seconds of
+    /// media decoded ahead of `position`, `position_fraction` is `None` when
+    /// the duration is unknown, like `duration_text`
+    PlaybackProgress {
+        position: f64,
+        duration: f64,
+        buffered: f64,
+        position_fraction: Option<f64>,
+        /// `position` and `duration` as `HH:MM:SS.mmm`
+        position_text: String,
+        duration_text: Option<String>,
+    },
+    /// Periodic download progress of a network source while playing;
+    /// `total_bytes` is `None` when the length is unknown
+    NetworkProgress {
+        bytes_read: u64,
+        total_bytes: Option<u64>,
+    },
+    /// Playlist entry `index` was loaded (skipped to or advanced to at the end
+    /// of the previous one); `duration` is the length of the entry (its
+    /// section, for cue sheet tracks), `metadata` the container tags of its file
+    TrackChanged {
+        index: usize,
+        path: String,
+        title: Option<String>,
+        duration: f64,
+        metadata: BTreeMap<String, String>,
+    },
+    /// Everything decoded has been presented and heard; the player applies
+    /// its end behavior
+    PlaybackEnded,
+    /// The end behavior is `Close`: the frontend should close the player
+    CloseRequested,
+    /// Output devices were added or removed, or the default device changed
+    DevicesChanged {
+        devices: Vec<String>,
+        default_device: Option<String>,
+    },
+}
+
+impl PlayerEvent {
+    /// Tauri event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerEvent::Buffering { .. } => "buffering",
+            PlayerEvent::Warning { .. } => "player-warning",
+            PlayerEvent::CompatibilityWarning { .. } => "compatibility-warning",
+            PlayerEvent::ExportProgress { .. } => "export-progress",
+            PlayerEvent::ExportFinished { .. } => "export-finished",
+            PlayerEvent::AnalysisProgress { .. } => "analysis-progress",
+            PlayerEvent::AnalysisFinished { .. } => "analysis-finished",
+            PlayerEvent::RecordingStopped { .. } => "recording-stopped",
+            PlayerEvent::DecodedFrame { .. } => "decoded-frame",
+            PlayerEvent::DecodeFramesFinished { .. } => "decode-frames-finished",
+            PlayerEvent::ClippingDetected { .. } => "clipping-detected",
+            PlayerEvent::AudioEffectsChanged { .. } => "audio-effects-changed",
+            PlayerEvent::SilenceSkipped { .. } => "silence-skipped",
+            PlayerEvent::FirstFrame { .. } => "first-frame",
+            PlayerEvent::MediaLoaded { .. } => "media-loaded",
+            PlayerEvent::VideoResized { .. } => "video-resized",
+            PlayerEvent::ScrubFrame { .. } => "scrub-frame",
+            PlayerEvent::PlaybackProgress { .. } => "playback-progress",
+            PlayerEvent::NetworkProgress { .. } => "network-progress",
+            PlayerEvent::TrackChanged { .. } => "track-changed",
+            PlayerEvent::PlaybackEnded => "playback-ended",
+            PlayerEvent::CloseRequested => "close-requested",
+            PlayerEvent::DevicesChanged { .. } => "devices-changed",
+        }
+    }
+}
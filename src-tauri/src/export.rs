@@ -0,0 +1,301 @@
+use crate::decoder::{media_start_time, open_probed, source_channel_layout};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::timebase::{pts_to_seconds, seconds_to_micros};
+use anyhow::Result;
+use crossbeam_channel::Sender;
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Sample format written to exported WAV files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// 16-bit signed integer PCM
+    #[default]
+    Pcm16,
+    /// 32-bit IEEE float
+    Float32,
+}
+
+impl ExportFormat {
+    fn sample(self) -> ffmpeg::format::Sample {
+        match self {
+            ExportFormat::Pcm16 => {
+                ffmpeg::format::Sample::I16(ffmpeg::format::sample::Type::Packed)
+            }
+            ExportFormat::Float32 => {
+                ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed)
+            }
+        }
+    }
+
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            ExportFormat::Pcm16 => 2,
+            ExportFormat::Float32 => 4,
+        }
+    }
+}
+
+/// Handle to a running export; dropping it does not cancel the export
+pub struct ExportJob {
+    cancel: Arc<AtomicBool>,
+}
+
+impl ExportJob {
+    /// Ask the export thread to stop and remove the partial file
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Release);
+    }
+}
+
+/// Decode `start..end` seconds of the audio in `path` to a WAV file at `out_path`.
+///
+/// The input is opened before returning so bad paths fail immediately; the
+/// decode itself runs on a background thread, reporting `ExportProgress` and
+/// then `ExportFinished` through `events`.
+pub fn export_range(
+    path: &str,
+    start: f64,
+    end: f64,
+    out_path: &str,
+    format: ExportFormat,
+    events: Sender<PlayerEvent>,
+) -> Result<ExportJob> {
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || end <= start {
+        return Err(PlayerError::InvalidArgument(format!(
+            "Invalid export range {:.3}..{:.3}",
+            start, end
+        ))
+        .into());
+    }
+
+    let exporter = Exporter::open(path, start, end, format)?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let thread_cancel = cancel.clone();
+    let out_path = out_path.to_string();
+
+    std::thread::spawn(move || {
+        let result = exporter.run(&out_path, &thread_cancel, &events);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&out_path);
+        }
+        let _ = events.send(PlayerEvent::ExportFinished {
+            out_path,
+            error: result.err().map(|e| PlayerError::from(e).to_string()),
+        });
+    });
+
+    Ok(ExportJob { cancel })
+}
+
+/// Input, decoder and resampler owned by the export thread
+struct Exporter {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Audio,
+    resampler: ffmpeg::software::resampling::context::Context,
+    stream_index: usize,
+    time_base: ffmpeg::Rational,
+    start_time: i64,
+    start: f64,
+    end: f64,
+    format: ExportFormat,
+    rate: u32,
+    channels: u16,
+}
+
+impl Exporter {
+    fn open(path: &str, start: f64, end: f64, format: ExportFormat) -> Result<Self, PlayerError> {
+        let _ = ffmpeg::init();
+        let input = open_probed(path)?;
+
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or_else(|| PlayerError::Unsupported("No audio stream".to_string()))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        let mut decoder_context = ffmpeg::codec::Context::new();
+        decoder_context.set_parameters(stream.parameters())?;
+        let decoder = decoder_context.decoder().audio()?;
+
+        // Keep the source rate and channel count, only convert the sample format
+        let rate = decoder.rate();
+        let channels = decoder.channels();
+        let layout = source_channel_layout(decoder.channel_layout(), channels);
+        let resampler = ffmpeg::software::resampling::context::Context::get(
+            decoder.format(),
+            layout,
+            rate,
+            format.sample(),
+            layout,
+            rate,
+        )?;
+        let start_time = media_start_time(&input);
+
+        Ok(Self {
+            input,
+            decoder,
+            resampler,
+            stream_index,
+            time_base,
+            start_time,
+            start,
+            end,
+            format,
+            rate,
+            channels,
+        })
+    }
+
+    fn run(
+        mut self,
+        out_path: &str,
+        cancel: &AtomicBool,
+        events: &Sender<PlayerEvent>,
+    ) -> Result<()> {
+        let mut writer = WavWriter::create(out_path, self.rate, self.channels, self.format)?;
+
+        let timestamp = seconds_to_micros(self.start, self.start_time);
+        self.input.seek(timestamp, ..timestamp)?;
+
+        let frame_size = self.channels as usize * self.format.bytes_per_sample();
+        let duration = self.end - self.start;
+        let mut last_progress = 0.0;
+        let mut frame = ffmpeg::frame::Audio::empty();
+        let mut resampled = ffmpeg::frame::Audio::empty();
+
+        'packets: for (stream, packet) in self.input.packets() {
+            if cancel.load(Ordering::Acquire) {
+                return Err(PlayerError::Internal("Export cancelled".to_string()).into());
+            }
+            if stream.index() != self.stream_index || self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+
+            while self.decoder.receive_frame(&mut frame).is_ok() {
+                let Some(pts) = frame.timestamp() else {
+                    continue;
+                };
+                let frame_start = pts_to_seconds(pts, self.time_base, self.start_time);
+                if frame.channel_layout().is_empty() {
+                    let layout = source_channel_layout(frame.channel_layout(), frame.channels());
+                    frame.set_channel_layout(layout);
+                }
+                if self.resampler.run(&frame, &mut resampled).is_err() {
+                    continue;
+                }
+
+                // Trim to the requested range with sample accuracy
+                let samples = resampled.samples();
+                let skip = (((self.start - frame_start) * self.rate as f64).max(0.0) as usize)
+                    .min(samples);
+                let keep =
+                    (((self.end - frame_start) * self.rate as f64).max(0.0) as usize).min(samples);
+                if keep > skip {
+                    let data = &resampled.data(0)[skip * frame_size..keep * frame_size];
+                    writer.write(data)?;
+                }
+
+                let position = frame_start + samples as f64 / self.rate as f64;
+                if position >= self.end {
+                    break 'packets;
+                }
+
+                let progress = ((position - self.start) / duration).clamp(0.0, 1.0);
+                if progress - last_progress >= 0.01 {
+                    last_progress = progress;
+                    let _ = events.send(PlayerEvent::ExportProgress { progress });
+                }
+            }
+        }
+
+        writer.finish()?;
+        let _ = events.send(PlayerEvent::ExportProgress { progress: 1.0 });
+        Ok(())
+    }
+}
+
+/// Minimal RIFF/WAVE writer; sizes are patched in `finish`
+pub struct WavWriter {
+    file: BufWriter<File>,
+    format: ExportFormat,
+    channels: u16,
+    data_bytes: u64,
+}
+
+impl WavWriter {
+    pub fn create(path: &str, rate: u32, channels: u16, format: ExportFormat) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let bytes_per_sample = format.bytes_per_sample() as u16;
+        let block_align = channels * bytes_per_sample;
+        let (format_tag, fmt_size): (u16, u32) = match format {
+            ExportFormat::Pcm16 => (1, 16),
+            // Non-PCM formats carry a cbSize field and a fact chunk
+            ExportFormat::Float32 => (3, 18),
+        };
+
+        file.write_all(b"RIFF")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(b"WAVE")?;
+        file.write_all(b"fmt ")?;
+        file.write_all(&fmt_size.to_le_bytes())?;
+        file.write_all(&format_tag.to_le_bytes())?;
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&rate.to_le_bytes())?;
+        file.write_all(&(rate * block_align as u32).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&(bytes_per_sample * 8).to_le_bytes())?;
+        if format == ExportFormat::Float32 {
+            file.write_all(&0u16.to_le_bytes())?;
+            file.write_all(b"fact")?;
+            file.write_all(&4u32.to_le_bytes())?;
+            file.write_all(&0u32.to_le_bytes())?;
+        }
+        file.write_all(b"data")?;
+        file.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            file,
+            format,
+            channels,
+            data_bytes: 0,
+        })
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.file.write_all(data)?;
+        self.data_bytes += data.len() as u64;
+        Ok(())
+    }
+
+    /// Patch the chunk sizes now that the data length is known
+    pub fn finish(mut self) -> Result<()> {
+        let data_bytes = u32::try_from(self.data_bytes)
+            .map_err(|_| PlayerError::InvalidArgument("Export exceeds 4 GB".to_string()))?;
+        let header_bytes: u32 = match self.format {
+            ExportFormat::Pcm16 => 36,
+            ExportFormat::Float32 => 50,
+        };
+
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file
+            .write_all(&(header_bytes + data_bytes).to_le_bytes())?;
+        if self.format == ExportFormat::Float32 {
+            // Sample frames per channel, in the fact chunk
+            let frame_bytes = self.format.bytes_per_sample() as u32 * self.channels as u32;
+            let frames = data_bytes / frame_bytes.max(1);
+            self.file.seek(SeekFrom::Start(46))?;
+            self.file.write_all(&frames.to_le_bytes())?;
+        }
+        self.file.seek(SeekFrom::Start(header_bytes as u64 + 4))?;
+        self.file.write_all(&data_bytes.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_int, c_void, CString};
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+/// Size of the buffer FFmpeg reads files through by default (`IO_BUFFER_SIZE`)
+pub const DEFAULT_IO_BUFFER_SIZE: usize = 32 * 1024;
+/// Accepted AVIO buffer sizes, in bytes
+pub const IO_BUFFER_SIZE_RANGE: std::ops::RangeInclusive<usize> = 4 * 1024..=16 * 1024 * 1024;
+/// How much of the file one disk read fetches with read-ahead on
+const READ_AHEAD_BYTES: usize = 4 * 1024 * 1024;
+
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
+/// How local files are read from disk
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IoSettings {
+    /// Size of the AVIO buffer the demuxer reads through, in bytes
+    pub buffer_size: usize,
+    /// Read the file in large sequential chunks ahead of the demuxer, so
+    /// small reads and short seeks are served from memory
+    pub read_ahead: bool,
+}
+
+impl Default for IoSettings {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_IO_BUFFER_SIZE,
+            read_ahead: false,
+        }
+    }
+}
+
+impl IoSettings {
+    /// FFmpeg's own file reading does the same
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `bytes` as a buffer size, or the default if it is outside
+/// `IO_BUFFER_SIZE_RANGE`
+pub fn checked_buffer_size(bytes: u64) -> usize {
+    usize::try_from(bytes)
+        .ok()
+        .filter(|size| IO_BUFFER_SIZE_RANGE.contains(size))
+        .unwrap_or(DEFAULT_IO_BUFFER_SIZE)
+}
+
+struct Reader {
+    file: BufReader<File>,
+    /// Offset the next read starts at
+    position: u64,
+}
+
+/// A local file read through an AVIO context of our own instead of FFmpeg's
+/// file protocol, which always uses 32 KiB reads.
+///
+/// The input opened on it does not free it: it must be dropped after that
+/// input.
+pub struct FileIo {
+    context: *mut ffmpeg::ffi::AVIOContext,
+}
+
+// The context and its reader are only used by the thread owning the input
+unsafe impl Send for FileIo {}
+
+impl FileIo {
+    fn open(path: &str, settings: IoSettings) -> Result<Self, ffmpeg::Error> {
+        let file = File::open(path).map_err(|e| io_error(&e))?;
+        let capacity = if settings.read_ahead {
+            READ_AHEAD_BYTES
+        } else {
+            0
+        };
+        let reader = Box::into_raw(Box::new(Reader {
+            file: BufReader::with_capacity(capacity, file),
+            position: 0,
+        }));
+        unsafe {
+            let buffer = ffmpeg::ffi::av_malloc(settings.buffer_size) as *mut u8;
+            let context = if buffer.is_null() {
+                std::ptr::null_mut()
+            } else {
+                ffmpeg::ffi::avio_alloc_context(
+                    buffer,
+                    settings.buffer_size as c_int,
+                    0,
+                    reader as *mut c_void,
+                    Some(read_packet),
+                    None,
+                    Some(seek),
+                )
+            };
+            if context.is_null() {
+                ffmpeg::ffi::av_free(buffer as *mut c_void);
+                drop(Box::from_raw(reader));
+                return Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::error::ENOMEM,
+                });
+            }
+            Ok(Self { context })
+        }
+    }
+}
+
+impl Drop for FileIo {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw((*self.context).opaque as *mut Reader));
+            // FFmpeg may have replaced the buffer it was given
+            ffmpeg::ffi::av_freep(&mut (*self.context).buffer as *mut *mut u8 as *mut c_void);
+            ffmpeg::ffi::avio_context_free(&mut self.context);
+        }
+    }
+}
+
+/// Open a local file for demuxing through a read buffer of `settings`.
+///
+/// The returned `FileIo` backs the input and must outlive it.
+pub fn open_buffered(
+    path: &str,
+    settings: IoSettings,
+) -> Result<(ffmpeg::format::context::Input, FileIo), ffmpeg::Error> {
+    let io = FileIo::open(path, settings)?;
+    let c_path = CString::new(path).map_err(|_| ffmpeg::Error::InvalidData)?;
+    unsafe {
+        let mut context = ffmpeg::ffi::avformat_alloc_context();
+        if context.is_null() {
+            return Err(ffmpeg::Error::Other {
+                errno: ffmpeg::error::ENOMEM,
+            });
+        }
+        (*context).pb = io.context;
+
+        // A context that fails to open is freed by FFmpeg
+        match ffmpeg::ffi::avformat_open_input(
+            &mut context,
+            c_path.as_ptr(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ) {
+            0 => match ffmpeg::ffi::avformat_find_stream_info(context, std::ptr::null_mut()) {
+                r if r >= 0 => Ok((ffmpeg::format::context::Input::wrap(context), io)),
+                e => {
+                    ffmpeg::ffi::avformat_close_input(&mut context);
+                    Err(ffmpeg::Error::from(e))
+                }
+            },
+            e => Err(ffmpeg::Error::from(e)),
+        }
+    }
+}
+
+fn io_error(error: &std::io::Error) -> ffmpeg::Error {
+    ffmpeg::Error::from(ffmpeg::ffi::AVERROR(
+        error.raw_os_error().unwrap_or(ffmpeg::error::EIO),
+    ))
+}
+
+unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut Reader);
+    let buf = std::slice::from_raw_parts_mut(buf, size.max(0) as usize);
+    loop {
+        match reader.file.read(buf) {
+            Ok(0) => return ffmpeg::ffi::AVERROR_EOF,
+            Ok(read) => {
+                reader.position += read as u64;
+                return read as c_int;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return io_error(&e).into(),
+        }
+    }
+}
+
+unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = &mut *(opaque as *mut Reader);
+    // Asked again each time: the file may still be growing
+    let len = match reader.file.get_ref().metadata() {
+        Ok(metadata) => metadata.len() as i64,
+        Err(e) => return c_int::from(io_error(&e)) as i64,
+    };
+    if whence & ffmpeg::ffi::AVSEEK_SIZE as c_int != 0 {
+        return len;
+    }
+    let target = match whence & !(ffmpeg::ffi::AVSEEK_FORCE as c_int) {
+        SEEK_SET => offset,
+        SEEK_CUR => reader.position as i64 + offset,
+        SEEK_END => len + offset,
+        _ => return ffmpeg::ffi::AVERROR(ffmpeg::error::EINVAL) as i64,
+    };
+    if target < 0 {
+        return ffmpeg::ffi::AVERROR(ffmpeg::error::EINVAL) as i64;
+    }
+    // Seeks within what was read ahead keep the data in memory
+    match reader.file.seek_relative(target - reader.position as i64) {
+        Ok(()) => {
+            reader.position = target as u64;
+            target
+        }
+        Err(e) => c_int::from(io_error(&e)) as i64,
+    }
+}
@@ -0,0 +1,81 @@
+use crate::decoder::is_media_file;
+use crate::error::PlayerError;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Companion files kept next to media that FFmpeg would still open (cover
+/// art as a one-frame video, text through the `tty` demuxer); never played
+/// when browsing a folder
+const COMPANION_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "txt", "nfo", "log", "cue", "m3u", "m3u8", "pls",
+    "srt", "ass", "ssa", "vtt", "sub", "idx",
+];
+
+/// Which neighbour of the loaded file to open
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum FolderDirection {
+    Next,
+    Previous,
+}
+
+/// The media file after (`Next`) or before (`Previous`) `path` in its folder,
+/// in case-insensitive file name order; `None` at either end.
+///
+/// Files that are not media are passed over, as are companion files such as
+/// cover art and subtitles. `path` itself need not exist any more.
+pub fn adjacent_media_file(
+    path: &str,
+    direction: FolderDirection,
+) -> Result<Option<String>, PlayerError> {
+    let current = Path::new(path);
+    let (Some(folder), Some(name)) = (current.parent(), current.file_name()) else {
+        return Err(PlayerError::InvalidArgument(format!(
+            "{} is not in a folder",
+            path
+        )));
+    };
+    let folder = if folder.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        folder
+    };
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|file| file.is_file() && !is_companion(file))
+        .collect();
+    files.sort_by_cached_key(|file| sort_key(file));
+    let current_key = sort_key(current);
+    // Where the loaded file is, or would be if it was deleted
+    let position = files.partition_point(|file| sort_key(file) < current_key);
+
+    let candidates: Vec<&PathBuf> = match direction {
+        FolderDirection::Next => files[position..]
+            .iter()
+            .filter(|file| file.file_name() != Some(name))
+            .collect(),
+        FolderDirection::Previous => files[..position].iter().rev().collect(),
+    };
+    // Probing opens each file, so stop at the first one that plays
+    Ok(candidates
+        .into_iter()
+        .filter_map(|file| file.to_str())
+        .find(|file| is_media_file(file))
+        .map(str::to_string))
+}
+
+fn is_companion(file: &Path) -> bool {
+    file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| COMPANION_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Order files by name ignoring case, then by the exact name
+fn sort_key(file: &Path) -> (String, String) {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (name.to_lowercase(), name)
+}
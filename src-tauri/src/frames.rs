@@ -0,0 +1,373 @@
+use crate::decoder::{
+    choose_stream, media_start_time, open_probed, stream_rotation, DisplayDimensions, VideoFrame,
+};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::timebase::{micros_to_seconds, pts_to_seconds, seconds_to_micros};
+use crate::tonemap::{ColorInfo, PixelFormat, VideoConverter};
+use crossbeam_channel::Sender;
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+
+/// Requests up to this many frames are answered directly; larger ones stream events
+pub const MAX_INLINE_FRAMES: usize = 16;
+/// Upper bound on the frames decoded for one request
+pub const MAX_DECODED_FRAMES: usize = 1000;
+/// Frames starting this little after a snapshot time still count as shown at it
+const SNAPSHOT_TOLERANCE: f64 = 0.001;
+
+/// A frame written to disk by `snapshot_at`
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    pub out_path: String,
+    /// Presentation time of the captured frame, in seconds
+    pub timestamp: f64,
+    /// Size of the written image, after aspect ratio and rotation
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decode `count` consecutive full-resolution frames starting at `start` seconds
+pub fn decode_frames(
+    path: &str,
+    start: f64,
+    count: usize,
+    tone_mapping: bool,
+) -> Result<Vec<VideoFrame>, PlayerError> {
+    let mut reader = FrameReader::open(path, tone_mapping)?;
+    let mut frames = Vec::with_capacity(count);
+    reader.read(start, count, &mut |frame| frames.push(frame))?;
+    Ok(frames)
+}
+
+/// Like `decode_frames`, but on a background thread.
+///
+/// The file is opened before returning so bad paths fail immediately. Each
+/// frame is sent as `DecodedFrame`, then `DecodeFramesFinished` ends the request.
+pub fn stream_frames(
+    path: &str,
+    start: f64,
+    count: usize,
+    tone_mapping: bool,
+    events: Sender<PlayerEvent>,
+) -> Result<(), PlayerError> {
+    let mut reader = FrameReader::open(path, tone_mapping)?;
+
+    std::thread::spawn(move || {
+        let mut index = 0;
+        let result = reader.read(start, count, &mut |frame| {
+            let _ = events.send(PlayerEvent::DecodedFrame { index, frame });
+            index += 1;
+        });
+        let _ = events.send(PlayerEvent::DecodeFramesFinished {
+            count: index,
+            error: result.err().map(|e| e.to_string()),
+        });
+    });
+    Ok(())
+}
+
+/// Write the frame shown at `time` (the last one starting at or before it) to
+/// `out_path` as a full-resolution PNG, with the sample aspect ratio and
+/// rotation applied
+pub fn snapshot_at(
+    path: &str,
+    time: f64,
+    out_path: &str,
+    tone_mapping: bool,
+) -> Result<Snapshot, PlayerError> {
+    let mut reader = FrameReader::open(path, tone_mapping)?;
+    if !time.is_finite() || time < 0.0 || (reader.duration > 0.0 && time > reader.duration) {
+        return Err(PlayerError::InvalidArgument(format!(
+            "Time {} is outside the media (0 to {:.3}s)",
+            time, reader.duration
+        )));
+    }
+
+    let (frame, timestamp) = reader.frame_at(time)?;
+    let data = convert(&mut None, &frame, tone_mapping)?;
+
+    // Stretch to the display aspect ratio before rotating
+    let display = DisplayDimensions::new(
+        frame.width(),
+        frame.height(),
+        frame.aspect_ratio(),
+        reader.rotation,
+    );
+    let (width, height) = if display.rotation % 180 == 90 {
+        (display.height, display.width)
+    } else {
+        (display.width, display.height)
+    };
+    let data = resize_rgba(&data, frame.width(), frame.height(), width, height)?;
+    let (data, width, height) = rotate_rgba(&data, width, height, display.rotation);
+    write_png(out_path, &data, width, height)?;
+
+    Ok(Snapshot {
+        out_path: out_path.to_string(),
+        timestamp,
+        width,
+        height,
+    })
+}
+
+/// Secondary decoder for frame inspection, independent of playback
+struct FrameReader {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Video,
+    stream_index: usize,
+    time_base: ffmpeg::Rational,
+    start_time: i64,
+    /// Length of the file in seconds (zero if unknown)
+    duration: f64,
+    /// Clockwise rotation of the video stream
+    rotation: u32,
+    tone_mapping: bool,
+}
+
+impl FrameReader {
+    fn open(path: &str, tone_mapping: bool) -> Result<Self, PlayerError> {
+        let _ = ffmpeg::init();
+        let input = open_probed(path)?;
+
+        let stream = choose_stream(&input, ffmpeg::media::Type::Video, None)
+            .and_then(|index| input.stream(index))
+            .ok_or_else(|| PlayerError::Unsupported("No video stream".to_string()))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+        let rotation = stream_rotation(&stream);
+
+        let mut decoder_context = ffmpeg::codec::Context::new();
+        decoder_context.set_parameters(stream.parameters())?;
+        let decoder = decoder_context.decoder().video()?;
+        let start_time = media_start_time(&input);
+        let duration = micros_to_seconds(input.duration()).max(0.0);
+
+        Ok(Self {
+            input,
+            decoder,
+            stream_index,
+            time_base,
+            start_time,
+            duration,
+            rotation,
+            tone_mapping,
+        })
+    }
+
+    /// Seek to the keyframe before `start`, then hand up to `count` frames at or
+    /// after `start` to `sink`, in presentation order. Returns how many were sent.
+    fn read(
+        &mut self,
+        start: f64,
+        count: usize,
+        sink: &mut dyn FnMut(VideoFrame),
+    ) -> Result<usize, PlayerError> {
+        let timestamp = seconds_to_micros(start.max(0.0), self.start_time);
+        self.input.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+
+        // Scalers are not `Send`, so the converter lives on the decoding thread only
+        let mut converter = None;
+        let mut delivered = 0;
+        let mut flushing = false;
+        let mut frame = ffmpeg::frame::Video::empty();
+        let mut packets = self.input.packets();
+
+        while delivered < count {
+            match self.decoder.receive_frame(&mut frame) {
+                Ok(()) => {
+                    let time = frame
+                        .timestamp()
+                        .map(|ts| pts_to_seconds(ts, self.time_base, self.start_time))
+                        .unwrap_or(0.0);
+                    // Frames between the keyframe and `start` are decoded but skipped
+                    if time < start {
+                        continue;
+                    }
+                    let data = convert(&mut converter, &frame, self.tone_mapping)?;
+                    sink(VideoFrame {
+                        width: frame.width(),
+                        height: frame.height(),
+                        data,
+                        format: PixelFormat::Rgba,
+                        timestamp: time,
+                        color: ColorInfo::for_frame(&frame),
+                    });
+                    delivered += 1;
+                }
+                Err(ffmpeg::Error::Eof) => break,
+                Err(_) if flushing => break,
+                // The decoder needs more input
+                Err(_) => match packets.next() {
+                    Some((stream, packet)) => {
+                        if stream.index() == self.stream_index {
+                            let _ = self.decoder.send_packet(&packet);
+                        }
+                    }
+                    None => {
+                        flushing = true;
+                        let _ = self.decoder.send_eof();
+                    }
+                },
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Decode the frame on screen at `time` and its timestamp; the first frame
+    /// if `time` precedes it
+    fn frame_at(&mut self, time: f64) -> Result<(ffmpeg::frame::Video, f64), PlayerError> {
+        let timestamp = seconds_to_micros(time.max(0.0), self.start_time);
+        self.input.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+
+        let mut shown: Option<(ffmpeg::frame::Video, f64)> = None;
+        let mut flushing = false;
+        let mut frame = ffmpeg::frame::Video::empty();
+        let mut packets = self.input.packets();
+
+        loop {
+            match self.decoder.receive_frame(&mut frame) {
+                Ok(()) => {
+                    let frame_time = frame
+                        .timestamp()
+                        .map(|ts| pts_to_seconds(ts, self.time_base, self.start_time))
+                        .unwrap_or(0.0);
+                    // The first frame past `time` replaces the one before it on screen
+                    let past = frame_time > time + SNAPSHOT_TOLERANCE;
+                    if past && shown.is_some() {
+                        break;
+                    }
+                    shown = Some((frame.clone(), frame_time));
+                    if past {
+                        break;
+                    }
+                }
+                Err(ffmpeg::Error::Eof) => break,
+                Err(_) if flushing => break,
+                // The decoder needs more input
+                Err(_) => match packets.next() {
+                    Some((stream, packet)) => {
+                        if stream.index() == self.stream_index {
+                            let _ = self.decoder.send_packet(&packet);
+                        }
+                    }
+                    None => {
+                        flushing = true;
+                        let _ = self.decoder.send_eof();
+                    }
+                },
+            }
+        }
+
+        shown.ok_or_else(|| PlayerError::DecodeFailed(format!("No frame decoded at {:.3}s", time)))
+    }
+}
+
+/// Convert to RGBA, rebuilding the converter when the frame format changes
+fn convert(
+    converter: &mut Option<VideoConverter>,
+    frame: &ffmpeg::frame::Video,
+    tone_mapping: bool,
+) -> Result<Vec<u8>, ffmpeg::Error> {
+    let converter = match converter.take() {
+        Some(c) if c.matches(frame, tone_mapping, PixelFormat::Rgba) => converter.insert(c),
+        _ => converter.insert(VideoConverter::for_frame(
+            frame,
+            tone_mapping,
+            PixelFormat::Rgba,
+        )?),
+    };
+    converter.convert(frame)
+}
+
+/// Copy tightly packed RGBA into a new frame, honouring its stride
+fn rgba_frame(data: &[u8], width: u32, height: u32) -> ffmpeg::frame::Video {
+    let mut frame = ffmpeg::frame::Video::new(ffmpeg::format::Pixel::RGBA, width, height);
+    let stride = frame.stride(0);
+    let row = width as usize * 4;
+    for (y, line) in data.chunks_exact(row).take(height as usize).enumerate() {
+        frame.data_mut(0)[y * stride..y * stride + row].copy_from_slice(line);
+    }
+    frame
+}
+
+/// Scale tightly packed RGBA to `width` x `height`
+fn resize_rgba(
+    data: &[u8],
+    src_width: u32,
+    src_height: u32,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, ffmpeg::Error> {
+    if (src_width, src_height) == (width, height) {
+        return Ok(data.to_vec());
+    }
+    let source = rgba_frame(data, src_width, src_height);
+    let mut scaler = ffmpeg::software::scaling::context::Context::get(
+        ffmpeg::format::Pixel::RGBA,
+        src_width,
+        src_height,
+        ffmpeg::format::Pixel::RGBA,
+        width,
+        height,
+        ffmpeg::software::scaling::flag::Flags::BICUBIC,
+    )?;
+    let mut scaled = ffmpeg::frame::Video::empty();
+    scaler.run(&source, &mut scaled)?;
+
+    let stride = scaled.stride(0);
+    let row = width as usize * 4;
+    let mut resized = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        resized.extend_from_slice(&scaled.data(0)[y * stride..y * stride + row]);
+    }
+    Ok(resized)
+}
+
+/// Rotate tightly packed RGBA clockwise by 0, 90, 180 or 270 degrees; returns
+/// the pixels and the new size
+fn rotate_rgba(data: &[u8], width: u32, height: u32, rotation: u32) -> (Vec<u8>, u32, u32) {
+    let (w, h) = (width as usize, height as usize);
+    let (out_width, out_height) = match rotation % 360 {
+        90 | 270 => (h, w),
+        180 => (w, h),
+        _ => return (data.to_vec(), width, height),
+    };
+    let mut rotated = vec![0u8; data.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let (out_x, out_y) = match rotation % 360 {
+                90 => (h - 1 - y, x),
+                180 => (w - 1 - x, h - 1 - y),
+                _ => (y, w - 1 - x),
+            };
+            let from = (y * w + x) * 4;
+            let to = (out_y * out_width + out_x) * 4;
+            rotated[to..to + 4].copy_from_slice(&data[from..from + 4]);
+        }
+    }
+    (rotated, out_width as u32, out_height as u32)
+}
+
+/// Encode tightly packed RGBA as a PNG file with FFmpeg's PNG encoder
+fn write_png(out_path: &str, data: &[u8], width: u32, height: u32) -> Result<(), PlayerError> {
+    let codec = ffmpeg::encoder::find(ffmpeg::codec::Id::PNG)
+        .ok_or_else(|| PlayerError::Unsupported("PNG encoder not available".to_string()))?;
+    let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec)
+        .encoder()
+        .video()?;
+    encoder.set_width(width);
+    encoder.set_height(height);
+    encoder.set_format(ffmpeg::format::Pixel::RGBA);
+    encoder.set_time_base((1, 1));
+    let mut encoder = encoder.open_as(codec)?;
+
+    encoder.send_frame(&rgba_frame(data, width, height))?;
+    encoder.send_eof()?;
+    let mut packet = ffmpeg::Packet::empty();
+    encoder.receive_packet(&mut packet)?;
+    std::fs::write(out_path, packet.data().unwrap_or_default())?;
+    Ok(())
+}
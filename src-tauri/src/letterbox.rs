@@ -0,0 +1,171 @@
+use crate::tonemap::PixelFormat;
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+/// Aspect ratio frames are padded to unless another one is given
+pub const DEFAULT_LETTERBOX_ASPECT: f64 = 16.0 / 9.0;
+
+/// Accepted aspect ratios, so bars never grow a frame more than tenfold
+pub const LETTERBOX_ASPECT_RANGE: std::ops::RangeInclusive<f64> = 0.1..=10.0;
+
+/// Pads decoded frames with bars to a fixed display aspect ratio, so every
+/// frame of a file has the same size whatever the source aspect
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Letterbox {
+    /// Display width divided by height of the padded frames
+    pub aspect_ratio: f64,
+    /// RGB color of the bars
+    pub color: [u8; 3],
+}
+
+/// Size of a padded frame and where the picture sits in it, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Padding {
+    pub width: u32,
+    pub height: u32,
+    pub left: u32,
+    pub top: u32,
+}
+
+impl Letterbox {
+    /// Padding for `width`x`height` frames with sample aspect ratio `sar`.
+    ///
+    /// The frontend rotates frames after they are padded, so for 90/270 degree
+    /// rotation the bars are added for the inverse aspect ratio.
+    pub fn padding(
+        &self,
+        width: u32,
+        height: u32,
+        sar: ffmpeg::Rational,
+        rotation: u32,
+    ) -> Padding {
+        let sar = if sar.numerator() > 0 && sar.denominator() > 0 {
+            f64::from(sar)
+        } else {
+            1.0
+        };
+        let target = if rotation % 180 == 90 {
+            1.0 / self.aspect_ratio
+        } else {
+            self.aspect_ratio
+        };
+        let aspect = width as f64 * sar / height as f64;
+        let (padded_width, padded_height) = if aspect > target {
+            // Wider than the target: bars above and below
+            let padded = (width as f64 * sar / target).round() as u32;
+            (width, padded.max(height))
+        } else {
+            // Narrower: bars left and right
+            let padded = (height as f64 * target / sar).round() as u32;
+            (padded.max(width), height)
+        };
+        Padding {
+            width: padded_width,
+            height: padded_height,
+            left: (padded_width - width) / 2,
+            top: (padded_height - height) / 2,
+        }
+    }
+
+    /// Place tightly packed `format` pixels `data` of `width`x`height` into a
+    /// frame of `padding`'s size filled with the bar color
+    pub fn apply(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        padding: Padding,
+        format: PixelFormat,
+    ) -> Vec<u8> {
+        let bpp = format.bytes_per_pixel();
+        let mut padded = format
+            .pixel(self.color)
+            .repeat(padding.width as usize * padding.height as usize);
+        let row = width as usize * bpp;
+        let padded_row = padding.width as usize * bpp;
+        for (y, source) in data.chunks_exact(row).take(height as usize).enumerate() {
+            let start = (padding.top as usize + y) * padded_row + padding.left as usize * bpp;
+            padded[start..start + row].copy_from_slice(source);
+        }
+        padded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: ffmpeg::Rational = ffmpeg::Rational(1, 1);
+
+    fn widescreen(color: [u8; 3]) -> Letterbox {
+        Letterbox {
+            aspect_ratio: 16.0 / 9.0,
+            color,
+        }
+    }
+
+    #[test]
+    fn narrow_sources_get_bars_left_and_right() {
+        let padding = widescreen([0; 3]).padding(12, 9, SQUARE, 0);
+        assert_eq!(
+            padding,
+            Padding {
+                width: 16,
+                height: 9,
+                left: 2,
+                top: 0
+            }
+        );
+        // Rotated a quarter turn it has to fill 9:16, so bars go above and below
+        let rotated = widescreen([0; 3]).padding(12, 9, SQUARE, 90);
+        assert_eq!((rotated.width, rotated.height, rotated.top), (12, 21, 6));
+    }
+
+    #[test]
+    fn wide_sources_get_bars_above_and_below() {
+        let four_three = Letterbox {
+            aspect_ratio: 4.0 / 3.0,
+            color: [0; 3],
+        };
+        let padding = four_three.padding(16, 9, SQUARE, 0);
+        assert_eq!((padding.width, padding.height), (16, 12));
+        assert_eq!((padding.left, padding.top), (0, 1));
+    }
+
+    #[test]
+    fn sample_aspect_ratio_counts_towards_the_shape() {
+        // 720x540 anamorphic at 4:3 pixels shows as 16:9: nothing to pad
+        let padding = widescreen([0; 3]).padding(720, 540, ffmpeg::Rational(4, 3), 0);
+        assert_eq!((padding.width, padding.height), (720, 540));
+        // An unset sample aspect ratio means square pixels
+        let unset = widescreen([0; 3]).padding(12, 9, ffmpeg::Rational(0, 1), 0);
+        assert_eq!(unset.width, 16);
+    }
+
+    #[test]
+    fn four_three_in_sixteen_nine_fills_the_bars_with_the_color() {
+        let letterbox = widescreen([10, 20, 30]);
+        let (width, height) = (12, 9);
+        let picture = [200, 201, 202];
+        let data = picture.repeat(width * height);
+        let padding = letterbox.padding(width as u32, height as u32, SQUARE, 0);
+        let padded = letterbox.apply(
+            &data,
+            width as u32,
+            height as u32,
+            padding,
+            PixelFormat::Rgb24,
+        );
+
+        assert_eq!(padded.len(), 16 * 9 * 3);
+        for (i, pixel) in padded.chunks_exact(3).enumerate() {
+            let x = i % 16;
+            let expected = if (2..14).contains(&x) {
+                picture
+            } else {
+                [10, 20, 30]
+            };
+            assert_eq!(pixel, expected, "pixel {} of row {}", x, i / 16);
+        }
+    }
+}
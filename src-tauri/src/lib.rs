@@ -1,145 +1,1670 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod decoder;
+mod adjustments;
+mod analysis;
+mod attachments;
 mod audio_output;
+mod cache;
+mod channels;
+mod clipping;
+pub mod clock;
+mod cue;
+mod deinterlace;
+mod dsp;
+mod effects;
+mod error;
+mod events;
+mod export;
+mod fileio;
+mod folder;
+mod frames;
+mod letterbox;
+mod looping;
 mod player;
+mod playlist;
+mod presets;
+mod probe;
+mod recorder;
+mod registry;
+mod settings;
+mod speed;
+mod sync;
+mod thumbnail;
+mod timebase;
+mod tonemap;
+mod waveform;
 
-use crossbeam_channel::unbounded;
-use decoder::VideoFrame;
-use player::{MediaPlayer, PlayerStatus, PlaybackState};
-use tauri::{State, Emitter};
+pub use audio_output::deinterleave;
+pub use dsp::DspHook;
+
+use adjustments::VideoAdjustments;
+use analysis::{AnalysisCache, AnalysisJob, AudioAnalysis};
+use attachments::SubtitleFont;
+use audio_output::OutputDevices;
+use cache::CacheMode;
+use crossbeam_channel::{unbounded, Receiver};
+use decoder::{
+    AudioTrack, DisplayDimensions, NetworkStats, ReconnectPolicy, SeekMode, VideoFrame, VideoTrack,
+};
+use deinterlace::DeinterlaceMode;
+use effects::AudioEffects;
+use error::PlayerError;
+use events::{PlayerEvent, Tagged};
+use export::{ExportFormat, ExportJob};
+use folder::FolderDirection;
+use frames::Snapshot;
+use letterbox::{Letterbox, DEFAULT_LETTERBOX_ASPECT};
+use looping::LoopRegion;
+use player::{
+    EndBehavior, MediaPlayer, PassthroughStatus, PlaybackStats, PlayerStatus, PlaybackState,
+    VolumeStep, VolumeSteps,
+};
+use playlist::{Playlist, PlaylistEntry};
+use presets::PresetStore;
+use probe::MediaInfo;
+use recorder::OutputSink;
+use registry::{PlayerId, PlayerRegistry, MAIN_PLAYER};
+use settings::{PlayerSettings, SettingsStore};
+use speed::SpeedMemory;
+use sync::{DegradationMode, SyncMode};
+use thumbnail::ChapterPreview;
+use tonemap::PixelFormat;
+use waveform::WaveformWindow;
+use tauri::{State, Emitter, Manager};
 use std::sync::Mutex;
 
-/// Global player instance
-type SharedPlayer = Mutex<MediaPlayer>;
+/// Saved effect presets
+type SharedPresets = Mutex<PresetStore>;
 
-// Default greeting command (kept for reference)
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
+/// Settings restored at startup and applied to new players
+type SharedSettings = Mutex<SettingsStore>;
+
+/// Range export in progress, if any
+type SharedExport = Mutex<Option<ExportJob>>;
+
+/// Audio analysis in progress, if any
+type SharedAnalysis = Mutex<Option<AnalysisJob>>;
+
+/// How often output devices are re-enumerated to catch hotplugs
+const DEVICE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
-/// Load a media file
+/// Load a media file, optionally starting at `start_at` seconds and playing
+/// right away with `autoplay`
 #[tauri::command]
 async fn load_file(
-    path: String, 
-    player: State<'_, SharedPlayer>,
-    app_handle: tauri::AppHandle
-) -> Result<PlayerStatus, String> {
+    path: String,
+    start_at: Option<f64>,
+    autoplay: Option<bool>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerStatus, PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
-    
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    let autoplay = autoplay.unwrap_or(false);
+    load_into(
+        &mut p, player_id, &path, start_at, autoplay, &presets, app_handle,
+    )
+}
+
+/// Load `path` into a locked player, apply its default preset and start
+/// emitting its video frames; with `autoplay`, playback starts (from `start_at`)
+/// once everything is set up
+fn load_into(
+    p: &mut MediaPlayer,
+    player_id: PlayerId,
+    path: &str,
+    start_at: Option<f64>,
+    autoplay: bool,
+    presets: &SharedPresets,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerStatus, PlayerError> {
+    let preset = presets.lock().unwrap().default_for(path);
+
     // Create video frame channel for sending frames to frontend
     let (video_sender, video_receiver) = unbounded::<VideoFrame>();
-    
+
     // Load the file with video sender
-    let status = p.load(&path, Some(video_sender))
-        .map_err(|e| format!("Failed to load file: {}", e))?;
-    
-    // Start video frame emitter thread
+    let status = p.load(path, Some(video_sender), start_at)?;
+
+    // Apply the file's (or its folder's) default preset before playback starts
+    if let Some(preset) = preset {
+        p.set_effects(preset)?;
+    }
+
+    spawn_frame_emitter(p, player_id, video_receiver, app_handle);
+
+    if autoplay {
+        p.play()?;
+        return Ok(p.get_status());
+    }
+    Ok(status)
+}
+
+/// Start the video frame emitter thread of a load; it exits once stop or the
+/// next load drops the sender
+fn spawn_frame_emitter(
+    p: &MediaPlayer,
+    player_id: PlayerId,
+    video_receiver: Receiver<VideoFrame>,
+    app_handle: tauri::AppHandle,
+) {
+    let (last_frame, generation) = p.frame_slot();
     std::thread::spawn(move || {
         while let Ok(frame_data) = video_receiver.recv() {
-            // Emit video frame to frontend
-            let _ = app_handle.emit("video-frame", frame_data);
+            // Emit video frame to frontend, keeping it for get_current_video_frame
+            let _ = app_handle.emit("video-frame", Tagged::new(player_id, &frame_data));
+            last_frame.store(generation, frame_data);
         }
     });
-    
+}
+
+/// Switch the player to another file without closing the audio device,
+/// carrying over its settings and play state
+#[tauri::command]
+async fn replace_media(
+    path: String,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerStatus, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    let preset = presets.lock().unwrap().default_for(&path);
+
+    let (video_sender, video_receiver) = unbounded::<VideoFrame>();
+    let status = p.replace_media(&path, Some(video_sender))?;
+    if let Some(preset) = preset {
+        p.set_effects(preset)?;
+    }
+    spawn_frame_emitter(&p, player_id, video_receiver, app_handle);
     Ok(status)
 }
 
+/// Load and play playlist entry `index`, from its in point to its out point
+fn play_entry(
+    p: &mut MediaPlayer,
+    player_id: PlayerId,
+    index: usize,
+    entry: &PlaylistEntry,
+    presets: &SharedPresets,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerStatus, PlayerError> {
+    load_into(
+        p,
+        player_id,
+        &entry.path,
+        entry.start,
+        false,
+        presets,
+        app_handle,
+    )?;
+    p.set_out_point(entry.end)?;
+    p.set_playlist_current(index);
+    p.play()?;
+    Ok(p.get_status())
+}
+
+/// Outcome of `enqueue_files`
+#[derive(serde::Serialize)]
+struct EnqueueResult {
+    added: usize,
+    /// Paths that are not readable media files
+    skipped: Vec<String>,
+}
+
+/// Append dropped files to the playlist, skipping anything that is not media;
+/// with `play`, the first added file is loaded and played
+#[tauri::command]
+async fn enqueue_files(
+    paths: Vec<String>,
+    play: Option<bool>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<EnqueueResult, PlayerError> {
+    let player = players.get(player_id)?;
+    // Probe without holding the lock, it opens every file
+    let (media, skipped): (Vec<String>, Vec<String>) = paths
+        .into_iter()
+        .partition(|path| decoder::is_media_file(path));
+    let added = media.len();
+
+    let mut p = player.lock().unwrap();
+    let first = p.enqueue(media.into_iter().map(PlaylistEntry::file).collect());
+    if play.unwrap_or(false) && added > 0 {
+        let entry = p.playlist().get(first).cloned().unwrap();
+        let player_id = player_id.unwrap_or(MAIN_PLAYER);
+        play_entry(&mut p, player_id, first, &entry, &presets, app_handle)?;
+    }
+    Ok(EnqueueResult { added, skipped })
+}
+
+/// Append the tracks of a cue sheet to the playlist, each playing its section
+/// of the referenced file, and play the first one
+#[tauri::command]
+async fn load_cue(
+    path: String,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerStatus, PlayerError> {
+    let tracks = cue::load_cue(&path)?;
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    let first = p.enqueue(tracks);
+    let entry = p.playlist().get(first).cloned().unwrap();
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    play_entry(&mut p, player_id, first, &entry, &presets, app_handle)
+}
+
 /// Play the media
 #[tauri::command]
-async fn play(player: State<'_, SharedPlayer>) -> Result<(), String> {
+async fn play(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
-    p.play().map_err(|e| format!("Failed to play: {}", e))
+    Ok(p.play()?)
 }
 
 /// Pause the media
 #[tauri::command]
-async fn pause(player: State<'_, SharedPlayer>) -> Result<(), String> {
+async fn pause(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
-    p.pause().map_err(|e| format!("Failed to pause: {}", e))
+    Ok(p.pause()?)
 }
 
 /// Toggle playback (play/pause)
 #[tauri::command]
-async fn toggle_playback(player: State<'_, SharedPlayer>) -> Result<bool, String> {
+async fn toggle_playback(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<bool, PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
     let is_playing = p.get_state() == PlaybackState::Playing;
 
     if is_playing {
-        p.pause().map_err(|e| format!("Failed to pause: {}", e))?;
+        p.pause()?;
         Ok(false)
     } else {
-        p.play().map_err(|e| format!("Failed to play: {}", e))?;
+        p.play()?;
         Ok(true)
     }
 }
 
 /// Stop playback
 #[tauri::command]
-async fn stop(player: State<'_, SharedPlayer>) -> Result<(), String> {
+async fn stop(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
     p.stop();
     Ok(())
 }
 
-/// Seek to a specific time in seconds
+/// Seek to a specific time in seconds; resolves with the position actually landed on
+#[tauri::command]
+async fn seek_to(
+    position: f64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.seek(position)?)
+}
+
+/// Seek to a fraction (0.0 to 1.0) of the duration
+#[tauri::command]
+async fn seek_fraction(
+    fraction: f64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.seek_fraction(fraction)?)
+}
+
+/// Seek to a percentage (0 to 100) of the file, by bytes if its timestamps
+/// are unreliable; returns the position landed on
 #[tauri::command]
-async fn seek_to(position: f64, player: State<'_, SharedPlayer>) -> Result<f64, String> {
+async fn seek_percent(
+    percent: f64,
+    snap_to_keyframe: Option<bool>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
-    p.seek(position).map_err(|e| format!("Failed to seek: {}", e))?;
-    Ok(position)
+    Ok(p.seek_percent(percent, snap_to_keyframe.unwrap_or(false))?)
+}
+
+/// Loop a region of the file seamlessly, or stop looping with `None`
+#[tauri::command]
+async fn set_loop(
+    region: Option<LoopRegion>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<LoopRegion>, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_loop(region)?)
+}
+
+/// Choose keyframe (fast) or frame-accurate seeking
+#[tauri::command]
+async fn set_seek_mode(
+    mode: SeekMode,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_seek_mode(mode)?)
+}
+
+/// Bound how many seconds the decoder may run ahead of playback
+#[tauri::command]
+async fn set_buffer_ahead(
+    seconds: f64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_buffer_ahead(seconds)?)
+}
+
+/// Cap the video frames emitted per second (e.g. to the display refresh
+/// rate); `null` emits every frame
+#[tauri::command]
+async fn set_max_fps(
+    fps: Option<f64>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_max_fps(fps)?)
 }
 
 /// Set volume (0.0 - 1.0)
 #[tauri::command]
-async fn set_volume(volume: f32, player: State<'_, SharedPlayer>) -> Result<f32, String> {
+async fn set_volume(
+    volume: f32,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f32, PlayerError> {
+    let player = players.get(player_id)?;
     let mut p = player.lock().unwrap();
     p.set_volume(volume);
     Ok(p.get_volume())
 }
 
+/// Step the volume by `delta` (e.g. -0.05/+0.05), or by `delta` configured
+/// steps when `step` is given; returns the new volume
+#[tauri::command]
+async fn adjust_volume(
+    delta: f32,
+    step: Option<VolumeStep>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f32, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.adjust_volume(delta, step)?)
+}
+
+/// Set the fine and coarse step sizes of `adjust_volume`; returns them clamped
+#[tauri::command]
+async fn set_volume_steps(
+    steps: VolumeSteps,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VolumeSteps, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_volume_steps(steps)?)
+}
+
+/// Get the step sizes of `adjust_volume`
+#[tauri::command]
+async fn get_volume_steps(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VolumeSteps, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.volume_steps())
+}
+
+/// Choose what happens at the end of a file: stay on the last frame, loop,
+/// play the next playlist entry, or ask the frontend to close the player
+#[tauri::command]
+async fn set_end_behavior(
+    behavior: EndBehavior,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    p.set_end_behavior(behavior);
+    Ok(())
+}
+
+/// Keep the audio device open while paused for instant resume
+#[tauri::command]
+async fn set_keep_device_warm(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    p.set_keep_device_warm(enabled);
+    Ok(())
+}
+
+/// Raise the audio thread's scheduling priority where the OS allows it
+#[tauri::command]
+async fn set_realtime_audio(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_realtime_audio(enabled)?)
+}
+
+/// Enable or disable the low-latency live mode (applies on next load)
+#[tauri::command]
+async fn set_live_mode(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    p.set_live_mode(enabled);
+    Ok(())
+}
+
+/// Get a small preview frame at `time` seconds without disturbing playback
+#[tauri::command]
+async fn get_thumbnail(
+    time: f64,
+    max_width: Option<u32>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VideoFrame, PlayerError> {
+    let player = players.get(player_id)?;
+    // Only hold the player lock while fetching the cached decoder handle
+    let thumbnailer = player.lock().unwrap().thumbnailer()?;
+    let max_width = max_width.unwrap_or(thumbnail::DEFAULT_THUMBNAIL_WIDTH);
+    Ok(thumbnailer.thumbnail(time, max_width)?)
+}
+
+/// Peak levels of `start..end` seconds in `buckets` buckets, for zoomed
+/// waveform views; decoded without disturbing playback
+#[tauri::command]
+async fn get_waveform_window(
+    start: f64,
+    end: f64,
+    buckets: u32,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<WaveformWindow, PlayerError> {
+    let player = players.get(player_id)?;
+    // Only hold the player lock while fetching the cached decoder handle
+    let waveform = player.lock().unwrap().waveform()?;
+    Ok(waveform.window(start, end, buckets)?)
+}
+
+/// Show a keyframe preview at `time` while dragging the scrubber; `seek_to`
+/// commits the position when the drag ends
+#[tauri::command]
+async fn scrub(
+    time: f64,
+    max_width: Option<u32>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    if !time.is_finite() {
+        return Err(PlayerError::InvalidArgument(format!(
+            "Invalid scrub time {}",
+            time
+        )));
+    }
+    let (thumbnailer, events) = {
+        let mut p = player.lock().unwrap();
+        (p.thumbnailer()?, p.event_sender())
+    };
+    let max_width = max_width.unwrap_or(thumbnail::DEFAULT_SCRUB_WIDTH);
+    Ok(thumbnailer.scrub(time, max_width, events)?)
+}
+
+/// List the chapters of the loaded file, each with a thumbnail of its start
+#[tauri::command]
+async fn get_chapter_previews(
+    max_width: Option<u32>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Vec<ChapterPreview>, PlayerError> {
+    let player = players.get(player_id)?;
+    let (path, thumbnailer) = player.lock().unwrap().chapter_source()?;
+    match thumbnailer {
+        Some(thumbnailer) => {
+            let max_width = max_width.unwrap_or(thumbnail::DEFAULT_THUMBNAIL_WIDTH);
+            Ok(thumbnailer.chapter_previews(max_width)?)
+        }
+        None => thumbnail::chapters(&path),
+    }
+}
+
+/// Get the embedded album art, at full size unless `max_width` is given
+#[tauri::command]
+async fn get_cover_art(
+    max_width: Option<u32>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VideoFrame, PlayerError> {
+    let player = players.get(player_id)?;
+    let path = player.lock().unwrap().cover_art_path()?;
+    thumbnail::cover_art(&path, max_width.unwrap_or(u32::MAX))
+}
+
+/// Decode `count` consecutive full-resolution frames from `start` seconds without
+/// touching playback.
+///
+/// Small requests return the frames. Larger ones (capped at 1000 frames) return
+/// an empty list and stream `decoded-frame` events, then `decode-frames-finished`.
+#[tauri::command]
+async fn decode_frames(
+    start: f64,
+    count: usize,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Vec<VideoFrame>, PlayerError> {
+    let player = players.get(player_id)?;
+    if !start.is_finite() || start < 0.0 {
+        return Err(PlayerError::InvalidArgument(format!(
+            "Invalid start time {}",
+            start
+        )));
+    }
+    let (path, tone_mapping, events) = {
+        let p = player.lock().unwrap();
+        (p.video_path()?, p.tone_mapping(), p.event_sender())
+    };
+
+    let count = count.min(frames::MAX_DECODED_FRAMES);
+    if count <= frames::MAX_INLINE_FRAMES {
+        frames::decode_frames(&path, start, count, tone_mapping)
+    } else {
+        frames::stream_frames(&path, start, count, tone_mapping, events)?;
+        Ok(Vec::new())
+    }
+}
+
+/// Save the exact frame shown at `time` as a full-resolution PNG, using a
+/// separate decoder so playback is not disturbed
+#[tauri::command]
+async fn snapshot_at(
+    time: f64,
+    out_path: String,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Snapshot, PlayerError> {
+    let player = players.get(player_id)?;
+    let (path, tone_mapping) = {
+        let p = player.lock().unwrap();
+        (p.video_path()?, p.tone_mapping())
+    };
+    frames::snapshot_at(&path, time, &out_path, tone_mapping)
+}
+
+/// Change the output sample rate; returns the rate actually in use
+#[tauri::command]
+async fn set_output_sample_rate(
+    rate: u32,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<u32, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_output_sample_rate(rate)?)
+}
+
+/// Send audio to the device bit-perfect when its native rate is supported
+#[tauri::command]
+async fn set_passthrough(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<PassthroughStatus, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_passthrough(enabled)?)
+}
+
+/// Choose the A/V sync master clock
+#[tauri::command]
+async fn set_sync_mode(
+    mode: SyncMode,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_sync_mode(mode)?)
+}
+
+/// Play slow-motion video at its recorded speed
+#[tauri::command]
+async fn set_real_time_motion(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_real_time_motion(enabled)?)
+}
+
+/// Play faster or slower without changing the pitch; returns the speed in use
+#[tauri::command]
+async fn set_playback_speed(
+    speed: f64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_playback_speed(speed)?)
+}
+
+/// Remember the playback speed per file, globally, or not at all
+#[tauri::command]
+async fn set_speed_memory(
+    mode: SpeedMemory,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    p.set_speed_memory(mode);
+    Ok(())
+}
+
+/// Decode short files completely into memory, or stream them
+#[tauri::command]
+async fn set_cache_mode(
+    mode: CacheMode,
+    max_mb: Option<u64>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_cache_mode(mode, max_mb)?)
+}
+
+/// Set the read buffer for local files; returns the size used from the next load
+#[tauri::command]
+async fn set_io_buffer_size(
+    bytes: u64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<usize, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_io_buffer_size(bytes)?)
+}
+
+/// Read local files ahead in large chunks, for slow disks
+#[tauri::command]
+async fn set_read_ahead(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_read_ahead(enabled)?)
+}
+
+/// Choose what gives way when decoding cannot keep up with playback
+#[tauri::command]
+async fn set_degradation_mode(
+    mode: DegradationMode,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_degradation_mode(mode)?)
+}
+
+/// Shift video against audio in milliseconds to fine-tune lip sync
+#[tauri::command]
+async fn set_av_offset(
+    ms: f64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_av_offset(ms)?)
+}
+
+/// Skip silent sections at the start and end of audio files
+#[tauri::command]
+async fn set_skip_silence(
+    enabled: bool,
+    threshold_db: Option<f32>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_skip_silence(enabled, threshold_db.unwrap_or(-60.0))?)
+}
+
+/// Enable or disable HDR to SDR tone mapping
+#[tauri::command]
+async fn set_tone_mapping(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_tone_mapping(enabled)?)
+}
+
+/// Deinterlace flagged sources (Auto), every frame (On) or nothing (Off)
+#[tauri::command]
+async fn set_deinterlace(
+    mode: DeinterlaceMode,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_deinterlace(mode)?)
+}
+
+/// Adjust video brightness, contrast, saturation and gamma
+#[tauri::command]
+async fn set_video_adjustments(
+    adjustments: VideoAdjustments,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VideoAdjustments, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_video_adjustments(adjustments)?)
+}
+
+/// Pad video frames with bars of `color` (RGB, black by default) to a fixed
+/// aspect ratio (16:9 by default); returns the letterbox in use
+#[tauri::command]
+async fn set_letterbox(
+    enabled: bool,
+    color: Option<[u8; 3]>,
+    aspect_ratio: Option<f64>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<Letterbox>, PlayerError> {
+    let letterbox = enabled.then(|| Letterbox {
+        aspect_ratio: aspect_ratio.unwrap_or(DEFAULT_LETTERBOX_ASPECT),
+        color: color.unwrap_or([0, 0, 0]),
+    });
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_letterbox(letterbox)?)
+}
+
+/// Byte layout of `video-frame` payloads: "Rgba" (default), "Bgra" or "Rgb24"
+#[tauri::command]
+async fn set_output_pixel_format(
+    format: PixelFormat,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_output_pixel_format(format)?)
+}
+
+/// Prefer an audio language (e.g. "eng") when loading files with several audio tracks
+#[tauri::command]
+async fn set_preferred_audio_language(
+    code: Option<String>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_preferred_audio_language(code)?)
+}
+
+/// Switch to the next audio track (e.g. another language), keeping the position
+#[tauri::command]
+async fn cycle_audio_track(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<AudioTrack, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.cycle_audio_track()?)
+}
+
+/// List the video streams of the loaded file (cover art excluded)
+#[tauri::command]
+async fn get_video_tracks(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Vec<VideoTrack>, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.get_video_tracks())
+}
+
+/// Play another video stream (e.g. a different camera angle), keeping the position
+#[tauri::command]
+async fn set_video_track(
+    index: usize,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<VideoTrack, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_video_track(index)?)
+}
+
+/// Set preamp, balance and equalizer gains; returns the settings in use
+#[tauri::command]
+async fn set_audio_effects(
+    settings: AudioEffects,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<AudioEffects, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_effects(settings)?)
+}
+
+/// Get the current audio effect settings
+#[tauri::command]
+async fn get_audio_effects(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<AudioEffects, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.effects())
+}
+
+/// Downmix to mono, played on both channels
+#[tauri::command]
+async fn set_mono_output(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_mono_output(enabled)?)
+}
+
+/// Report clipping in the output as it plays
+#[tauri::command]
+async fn set_clip_detection(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_clip_detection(enabled)?)
+}
+
+/// Send the audio to the device, a WAV file, or both
+#[tauri::command]
+async fn set_output_sink(
+    sink: OutputSink,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_output_sink(sink)?)
+}
+
+/// Remove the center of the mix (karaoke)
+#[tauri::command]
+async fn set_vocal_removal(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_vocal_removal(enabled)?)
+}
+
+/// Silence source channels by bit mask
+#[tauri::command]
+async fn set_channel_mute(
+    mask: u64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_channel_mute(mask)?)
+}
+
+/// Match the levels of the stereo channels of each file
+#[tauri::command]
+async fn set_auto_balance(
+    enabled: bool,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_auto_balance(enabled)?)
+}
+
+/// Turn all audio processing back to neutral; returns the effect settings
+#[tauri::command]
+async fn reset_audio_effects(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<AudioEffects, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.reset_audio_effects()?)
+}
+
+/// Save effect settings under `name`, replacing any preset with that name
+#[tauri::command]
+async fn save_preset(
+    name: String,
+    settings: AudioEffects,
+    presets: State<'_, SharedPresets>,
+) -> Result<(), PlayerError> {
+    Ok(presets.lock().unwrap().save_preset(&name, settings)?)
+}
+
+/// Apply a saved preset; returns the settings in use
+#[tauri::command]
+async fn apply_preset(
+    name: String,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+) -> Result<AudioEffects, PlayerError> {
+    let player = players.get(player_id)?;
+    let settings = presets.lock().unwrap().get(&name)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_effects(settings)?)
+}
+
+/// Names of the saved presets
+#[tauri::command]
+async fn list_presets(presets: State<'_, SharedPresets>) -> Result<Vec<String>, PlayerError> {
+    Ok(presets.lock().unwrap().names())
+}
+
+/// Apply preset `name` whenever the file (or anything in the folder) at `path`
+/// is loaded; a null name removes the association
+#[tauri::command]
+async fn set_default_preset_for(
+    path: String,
+    name: Option<String>,
+    presets: State<'_, SharedPresets>,
+) -> Result<(), PlayerError> {
+    Ok(presets
+        .lock()
+        .unwrap()
+        .set_default_for(&path, name.as_deref())?)
+}
+
+/// Decode `start..end` seconds of `path` to a WAV file in the background.
+///
+/// Progress is reported with `export-progress` events and completion with
+/// `export-finished`. Starting a new export cancels the previous one.
+#[tauri::command]
+// Each argument is a named field of the frontend call, and the states are
+// injected by Tauri; grouping them would change the invoke API
+#[allow(clippy::too_many_arguments)]
+async fn export_range(
+    path: String,
+    start: f64,
+    end: f64,
+    out_path: String,
+    format: Option<ExportFormat>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    export: State<'_, SharedExport>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let events = player.lock().unwrap().event_sender();
+    let job = export::export_range(
+        &path,
+        start,
+        end,
+        &out_path,
+        format.unwrap_or_default(),
+        events,
+    )?;
+    if let Some(previous) = export.lock().unwrap().replace(job) {
+        previous.cancel();
+    }
+    Ok(())
+}
+
+/// Cancel the running export and delete its partial output
+#[tauri::command]
+async fn cancel_export(export: State<'_, SharedExport>) -> Result<(), PlayerError> {
+    if let Some(job) = export.lock().unwrap().take() {
+        job.cancel();
+    }
+    Ok(())
+}
+
+/// Measure peaks, loudness and clipping of the audio in `path`.
+///
+/// Returns the result at once if `path` was analyzed before (and has not
+/// changed since); otherwise returns `None` and decodes the file in the
+/// background, reporting `analysis-progress` and then `analysis-finished`.
+/// Starting a new analysis cancels the previous one.
+#[tauri::command]
+async fn analyze(
+    path: String,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    analysis: State<'_, SharedAnalysis>,
+    cache: State<'_, AnalysisCache>,
+) -> Result<Option<AudioAnalysis>, PlayerError> {
+    if let Some(cached) = cache.get(&path) {
+        return Ok(Some(cached));
+    }
+    let player = players.get(player_id)?;
+    let events = player.lock().unwrap().event_sender();
+    let job = analysis::analyze(&path, cache.inner().clone(), events)?;
+    if let Some(previous) = analysis.lock().unwrap().replace(job) {
+        previous.cancel();
+    }
+    Ok(None)
+}
+
+/// Cancel the running analysis
+#[tauri::command]
+async fn cancel_analysis(analysis: State<'_, SharedAnalysis>) -> Result<(), PlayerError> {
+    if let Some(job) = analysis.lock().unwrap().take() {
+        job.cancel();
+    }
+    Ok(())
+}
+
+/// Configure reconnect attempts and initial backoff for network sources
+#[tauri::command]
+async fn set_reconnect_policy(
+    max_attempts: u32,
+    backoff_ms: u64,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_reconnect_policy(ReconnectPolicy {
+        max_attempts,
+        initial_backoff_ms: backoff_ms,
+    })?)
+}
+
+/// Get playback diagnostics (A/V drift, dropped frames, ...)
+#[tauri::command]
+async fn get_playback_stats(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<PlaybackStats, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.get_stats())
+}
+
+/// Get the last frame sent to the frontend (null if none has been decoded yet)
+#[tauri::command]
+async fn get_current_video_frame(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<VideoFrame>, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.current_video_frame()?)
+}
+
+/// Fonts embedded in the loaded file, for registering with an ASS subtitle renderer
+#[tauri::command]
+async fn get_subtitle_fonts(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Vec<SubtitleFont>, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.subtitle_fonts()?)
+}
+
+/// Send the current poster frame (the one at the position, also while paused)
+/// again as `video-frame`, e.g. after the view was rebuilt. Returns `false`
+/// when there is none, such as for a file without video, so a picture left
+/// over from the previous file can be cleared
+#[tauri::command]
+async fn refresh_current_frame(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, PlayerError> {
+    let player = players.get(player_id)?;
+    let frame = player.lock().unwrap().current_video_frame()?;
+    let Some(frame) = frame else {
+        return Ok(false);
+    };
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    let _ = app_handle.emit("video-frame", Tagged::new(player_id, &frame));
+    Ok(true)
+}
+
+/// Get the size to display the video at, honoring sample aspect ratio and rotation
+#[tauri::command]
+async fn get_display_dimensions(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<DisplayDimensions, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.display_dimensions()?)
+}
+
+/// Get the name of the device audio is played on
+#[tauri::command]
+async fn get_current_output_device(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<String>, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.current_output_device())
+}
+
+/// Play on the named output device, or the system default for `None`;
+/// returns the device audio plays on
+#[tauri::command]
+async fn set_output_device(
+    name: Option<String>,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<String>, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.set_output_device(name)?)
+}
+
+/// Re-enumerate output devices now, emitting `devices-changed` if the set differs
+#[tauri::command]
+async fn refresh_output_devices(
+    players: State<'_, PlayerRegistry>,
+) -> Result<OutputDevices, PlayerError> {
+    let devices = OutputDevices::scan();
+    for player in players.all() {
+        let mut p = player.lock().unwrap();
+        p.update_output_devices(devices.clone())?;
+    }
+    Ok(devices)
+}
+
+/// Open the audio device again after it was missing at load time
+#[tauri::command]
+async fn retry_audio_output(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<PlayerStatus, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    Ok(p.retry_audio_output()?)
+}
+
+/// Open the system file manager at the loaded file, with the file selected
+#[tauri::command]
+async fn reveal_current_file(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    let player = players.get(player_id)?;
+    let path = player.lock().unwrap().local_path()?;
+    tauri_plugin_opener::reveal_item_in_dir(&path).map_err(|e| PlayerError::Io(e.to_string()))
+}
+
+/// Read the streams, tags, duration and chapters of any file without loading it
+/// into a player, e.g. to index a library
+#[tauri::command]
+async fn probe(path: String) -> Result<MediaInfo, PlayerError> {
+    probe::probe(&path)
+}
+
+/// Bytes downloaded so far for a network source, and its length if known
+#[tauri::command]
+async fn get_network_stats(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<NetworkStats, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.network_stats()?)
+}
+
+/// Get container and stream properties of the loaded file
+#[tauri::command]
+async fn get_media_info(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<MediaInfo, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.media_info()?)
+}
+
+/// Get the playback position in seconds (cheap enough to poll for a scrubber)
+#[tauri::command]
+async fn get_position(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<f64, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.position())
+}
+
+/// Get the playback position as a fraction of the duration (`None` if unknown)
+#[tauri::command]
+async fn get_position_fraction(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Option<f64>, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.position_fraction())
+}
+
 /// Get the current player status
 #[tauri::command]
-async fn get_player_status(player: State<'_, SharedPlayer>) -> Result<PlayerStatus, String> {
+async fn get_player_status(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<PlayerStatus, PlayerError> {
+    let player = players.get(player_id)?;
     let p = player.lock().unwrap();
     Ok(p.get_status())
 }
 
-/// Previous track (placeholder for playlist support)
+/// Get the settings that persist across sessions, as the player uses them now
 #[tauri::command]
-async fn previous_track() -> Result<(), String> {
-    println!("Previous track requested");
-    Ok(())
+async fn get_settings(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<PlayerSettings, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.settings())
+}
+
+/// Apply settings to a player and save them for the next session; returns the
+/// settings in use
+#[tauri::command]
+async fn update_settings(
+    settings: PlayerSettings,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    store: State<'_, SharedSettings>,
+) -> Result<PlayerSettings, PlayerError> {
+    let player = players.get(player_id)?;
+    let applied = player.lock().unwrap().apply_settings(&settings)?;
+    store.lock().unwrap().update(applied.clone())?;
+    Ok(applied)
+}
+
+/// Create an independent player (own decoder and audio output); returns its id
+#[tauri::command]
+async fn create_player(
+    players: State<'_, PlayerRegistry>,
+    settings: State<'_, SharedSettings>,
+    app_handle: tauri::AppHandle,
+) -> Result<PlayerId, PlayerError> {
+    let mut player = MediaPlayer::new();
+    player.apply_settings(&settings.lock().unwrap().get())?;
+    let events = player.events();
+    let id = players.insert(player);
+    forward_events(app_handle, id, events);
+    Ok(id)
 }
 
-/// Next track (placeholder for playlist support)
+/// Stop and remove a player created with `create_player`
 #[tauri::command]
-async fn next_track() -> Result<(), String> {
-    println!("Next track requested");
+async fn destroy_player(
+    player_id: PlayerId,
+    players: State<'_, PlayerRegistry>,
+) -> Result<(), PlayerError> {
+    players.remove(player_id)
+}
+
+/// Emit a player's events to the frontend, tagged with its id, until it is dropped
+fn forward_events(
+    handle: tauri::AppHandle,
+    player_id: PlayerId,
+    events: crossbeam_channel::Receiver<PlayerEvent>,
+) {
+    std::thread::spawn(move || {
+        while let Ok(event) = events.recv() {
+            let _ = handle.emit(event.name(), Tagged::new(player_id, &event));
+            if let PlayerEvent::PlaybackEnded = event {
+                if let Err(e) = apply_end_behavior(&handle, player_id) {
+                    eprintln!("Failed to apply end behavior: {}", e);
+                }
+            }
+            if let PlayerEvent::RecordingStopped { ref path, .. } = event {
+                if let Ok(player) = handle.state::<PlayerRegistry>().get(Some(player_id)) {
+                    player.lock().unwrap().recording_stopped(path);
+                }
+            }
+        }
+    });
+}
+
+/// Carry out the player's end behavior, loading the next playlist entry if it
+/// asks for one
+fn apply_end_behavior(handle: &tauri::AppHandle, player_id: PlayerId) -> Result<(), PlayerError> {
+    let player = handle.state::<PlayerRegistry>().get(Some(player_id))?;
+    let mut p = player.lock().unwrap();
+    if let Some((index, entry)) = p.handle_end()? {
+        let presets = handle.state::<SharedPresets>();
+        play_entry(&mut p, player_id, index, &entry, &presets, handle.clone())?;
+    }
     Ok(())
 }
 
+/// Get the playlist and the index of the loaded entry
+#[tauri::command]
+async fn get_playlist(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+) -> Result<Playlist, PlayerError> {
+    let player = players.get(player_id)?;
+    let p = player.lock().unwrap();
+    Ok(p.playlist())
+}
+
+/// Play the playlist entry `offset` places from the current one; `None` if
+/// there is no such entry
+fn play_adjacent(
+    offset: isize,
+    player_id: Option<PlayerId>,
+    players: &PlayerRegistry,
+    presets: &SharedPresets,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<PlayerStatus>, PlayerError> {
+    let player = players.get(player_id)?;
+    let mut p = player.lock().unwrap();
+    let Some((index, entry)) = p.playlist().adjacent(offset) else {
+        return Ok(None);
+    };
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    let status = play_entry(&mut p, player_id, index, &entry, presets, app_handle)?;
+    Ok(Some(status))
+}
+
+/// Play the previous playlist entry
+#[tauri::command]
+async fn previous_track(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<PlayerStatus>, PlayerError> {
+    play_adjacent(-1, player_id, &players, &presets, app_handle)
+}
+
+/// Play the next playlist entry
+#[tauri::command]
+async fn next_track(
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<PlayerStatus>, PlayerError> {
+    play_adjacent(1, player_id, &players, &presets, app_handle)
+}
+
+/// Load and play the media file after or before the loaded one in its
+/// folder, without a playlist; `None` at either end of the folder
+#[tauri::command]
+async fn play_adjacent_in_folder(
+    direction: FolderDirection,
+    player_id: Option<PlayerId>,
+    players: State<'_, PlayerRegistry>,
+    presets: State<'_, SharedPresets>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<PlayerStatus>, PlayerError> {
+    let player = players.get(player_id)?;
+    let current = player
+        .lock()
+        .unwrap()
+        .get_status()
+        .file_path
+        .ok_or(PlayerError::NotLoaded)?;
+    if decoder::is_network_source(&current) || decoder::is_live_source(&current) {
+        return Err(PlayerError::Unsupported(
+            "Streams are not in a folder".to_string(),
+        ));
+    }
+    // Probe without holding the lock, it opens the files
+    let Some(path) = folder::adjacent_media_file(&current, direction)? else {
+        return Ok(None);
+    };
+
+    let mut p = player.lock().unwrap();
+    let player_id = player_id.unwrap_or(MAIN_PLAYER);
+    let status = load_into(&mut p, player_id, &path, None, true, &presets, app_handle)?;
+    Ok(Some(status))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let player = Mutex::new(MediaPlayer::new());
+    run_with_dsp_hooks(Vec::new())
+}
+
+/// Run the player with custom DSP stages applied after the equalizer
+pub fn run_with_dsp_hooks(hooks: Vec<Box<dyn DspHook>>) {
+    let mut player = MediaPlayer::new();
+    for hook in hooks {
+        player
+            .add_dsp_hook(hook)
+            .expect("decoder thread is running");
+    }
+    let events = player.events();
+    let players = PlayerRegistry::new(player);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(player)
+        .manage(players)
+        .manage(SharedExport::default())
+        .manage(SharedAnalysis::default())
+        .manage(AnalysisCache::default())
+        .setup(move |app| {
+            let presets_file = app.path().app_data_dir()?.join("presets.json");
+            app.manage(Mutex::new(PresetStore::load(presets_file)));
+
+            // Restore the last session's settings
+            let settings_file = app.path().app_data_dir()?.join("settings.json");
+            let settings = SettingsStore::load(settings_file);
+            let main = app.state::<PlayerRegistry>().get(None)?;
+            if let Err(e) = main.lock().unwrap().apply_settings(&settings.get()) {
+                eprintln!("Failed to apply saved settings: {}", e);
+            }
+            app.manage(Mutex::new(settings));
+
+            // Forward decoder events (buffering, warnings) to the frontend
+            forward_events(app.handle().clone(), MAIN_PLAYER, events);
+
+            // Poll for device hotplug and default device changes
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(DEVICE_POLL_INTERVAL);
+                // Scan without holding the lock, enumeration can be slow
+                let devices = OutputDevices::scan();
+                for player in handle.state::<PlayerRegistry>().all() {
+                    let mut p = player.lock().unwrap();
+                    if let Err(e) = p.update_output_devices(devices.clone()) {
+                        eprintln!("Failed to switch output device: {}", e);
+                    }
+                }
+            });
+            Ok(())
+        })
+        // Every name must be a #[tauri::command] in this file: generate_handler!
+        // fails to compile otherwise, so the list cannot drift from the commands
         .invoke_handler(tauri::generate_handler![
-            greet,
             load_file,
+            replace_media,
             play,
             pause,
             toggle_playback,
             stop,
             seek_to,
+            seek_fraction,
+            seek_percent,
+            set_loop,
+            scrub,
+            set_seek_mode,
+            set_buffer_ahead,
+            set_max_fps,
             set_volume,
+            adjust_volume,
+            set_volume_steps,
+            get_volume_steps,
+            set_keep_device_warm,
+            set_realtime_audio,
+            set_end_behavior,
+            set_live_mode,
+            set_output_sample_rate,
+            set_passthrough,
+            set_sync_mode,
+            set_degradation_mode,
+            set_cache_mode,
+            set_io_buffer_size,
+            set_read_ahead,
+            set_real_time_motion,
+            set_playback_speed,
+            set_speed_memory,
+            set_av_offset,
+            set_skip_silence,
+            set_reconnect_policy,
+            set_tone_mapping,
+            set_deinterlace,
+            set_video_adjustments,
+            set_letterbox,
+            set_output_pixel_format,
+            set_preferred_audio_language,
+            cycle_audio_track,
+            get_video_tracks,
+            set_video_track,
+            set_audio_effects,
+            get_audio_effects,
+            set_mono_output,
+            set_clip_detection,
+            set_output_sink,
+            set_vocal_removal,
+            set_channel_mute,
+            set_auto_balance,
+            reset_audio_effects,
+            save_preset,
+            apply_preset,
+            list_presets,
+            set_default_preset_for,
+            export_range,
+            cancel_export,
+            analyze,
+            cancel_analysis,
+            get_playback_stats,
+            get_thumbnail,
+            get_waveform_window,
+            get_chapter_previews,
+            get_cover_art,
+            decode_frames,
+            snapshot_at,
+            get_display_dimensions,
+            get_current_video_frame,
+            refresh_current_frame,
+            get_subtitle_fonts,
+            get_current_output_device,
+            set_output_device,
+            refresh_output_devices,
+            retry_audio_output,
+            get_position,
+            get_position_fraction,
+            get_media_info,
+            probe,
+            get_network_stats,
+            reveal_current_file,
             get_player_status,
+            get_settings,
+            update_settings,
+            create_player,
+            destroy_player,
+            enqueue_files,
+            load_cue,
+            get_playlist,
             previous_track,
-            next_track
+            next_track,
+            play_adjacent_in_folder
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Keep changes made with the individual setters for the next session
+            if let tauri::RunEvent::Exit = event {
+                let Ok(main) = app.state::<PlayerRegistry>().get(None) else {
+                    return;
+                };
+                let settings = main.lock().unwrap().settings();
+                let store = app.state::<SharedSettings>();
+                let saved = store.lock().unwrap().update(settings);
+                if let Err(e) = saved {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+            }
+        });
 }
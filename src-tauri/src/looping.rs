@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+/// Shortest loop accepted, in seconds; video loops are also at least one
+/// frame long
+pub const MIN_LOOP_LENGTH: f64 = 0.05;
+
+/// A section of the file played over and over (A-B repeat)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    /// First media time played on every pass, in seconds
+    pub start: f64,
+    /// Media time each pass ends before, in seconds
+    pub end: f64,
+}
+
+impl LoopRegion {
+    pub fn length(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// Media time at `time` on the presentation timeline.
+    ///
+    /// While looping, decoded timestamps keep increasing by the loop length
+    /// on every pass, so the clocks run on without a jump at the boundary.
+    /// The first pass may start anywhere before the end.
+    pub fn fold(&self, time: f64) -> f64 {
+        if time < self.end || self.length() <= 0.0 {
+            time
+        } else {
+            self.start + (time - self.start).rem_euclid(self.length())
+        }
+    }
+
+    /// Where a seek to `time` lands: past the end it restarts the loop
+    pub fn seek_target(&self, time: f64) -> f64 {
+        if time >= self.end {
+            self.start
+        } else {
+            time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: LoopRegion = LoopRegion {
+        start: 2.0,
+        end: 5.0,
+    };
+
+    #[test]
+    fn length() {
+        assert_eq!(REGION.length(), 3.0);
+    }
+
+    #[test]
+    fn fold_maps_later_passes_back_into_the_region() {
+        let cases = [
+            // First pass, including a start before the region
+            (0.5, 0.5),
+            (2.0, 2.0),
+            (4.999, 4.999),
+            // The end is the start of the next pass
+            (5.0, 2.0),
+            (6.5, 3.5),
+            (8.0, 2.0),
+            (3000.0 + 2.0, 2.0),
+        ];
+        for (time, folded) in cases {
+            let got = REGION.fold(time);
+            assert!((got - folded).abs() < 1e-9, "fold({}) = {}", time, got);
+        }
+    }
+
+    #[test]
+    fn fold_leaves_an_empty_region_alone() {
+        let empty = LoopRegion {
+            start: 3.0,
+            end: 3.0,
+        };
+        assert_eq!(empty.fold(10.0), 10.0);
+    }
+
+    #[test]
+    fn seek_target_restarts_the_loop_past_the_end() {
+        let cases = [(0.0, 0.0), (2.0, 2.0), (4.9, 4.9), (5.0, 2.0), (60.0, 2.0)];
+        for (time, target) in cases {
+            assert_eq!(REGION.seek_target(time), target, "seek to {}", time);
+        }
+    }
+}
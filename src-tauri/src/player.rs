@@ -1,11 +1,51 @@
-use crate::audio_output::{create_sample_channel, AudioOutput};
-use crate::decoder::{DecoderInfo, FrameData, MediaDecoder, VideoFrame};
+use crate::adjustments::VideoAdjustments;
+use crate::attachments::SubtitleFont;
+use crate::audio_output::{
+    create_sample_channel, nearest_supported_rate, AudioBuffer, AudioOutput, OutputDevices,
+    OutputOptions, DEFAULT_OUTPUT_SAMPLE_RATE, OUTPUT_CHANNELS,
+};
+use crate::cache::{CacheMode, CacheStatus, CACHE_MAX_MB_RANGE, DEFAULT_CACHE_MAX_BYTES};
+use crate::channels::VocalRemovalMode;
+use crate::clipping::ClipStats;
+use crate::decoder::{
+    is_live_source, is_network_source, position_fraction, AudioTrack, DecodeBackend, DecoderInfo,
+    DisplayDimensions, FrameData, LoadTimings, MediaDecoder, NetworkStats, QueueDepth,
+    ReadySummary, ReconnectPolicy, SeekMode, StreamStatus, VideoFrame, VideoTrack,
+    DEFAULT_BUFFER_AHEAD, MAX_AV_OFFSET_MS, MAX_BUFFER_AHEAD, MAX_FPS_RANGE, MIN_BUFFER_AHEAD,
+};
+use crate::deinterlace::DeinterlaceMode;
+use crate::dsp::DspHook;
+use crate::effects::{AudioEffects, BalanceCorrection};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::fileio::{checked_buffer_size, IoSettings};
+use crate::letterbox::{Letterbox, LETTERBOX_ASPECT_RANGE};
+use crate::looping::{LoopRegion, MIN_LOOP_LENGTH};
+use crate::playlist::{Playlist, PlaylistEntry};
+use crate::probe::MediaInfo;
+use crate::recorder::{FileSink, OutputSink};
+use crate::settings::PlayerSettings;
+use crate::speed::{RememberedSpeeds, SpeedMemory, PLAYBACK_SPEED_RANGE};
+use crate::sync::{DegradationMode, SyncMode, SyncStats};
+use crate::thumbnail::Thumbnailer;
+use crate::timebase::{format_duration, format_time};
+use crate::tonemap::{ColorInfo, PixelFormat};
+use crate::waveform::Waveform;
 use anyhow::Result;
-use crossbeam_channel::Sender;
-use serde::Serialize;
+use crossbeam_channel::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+/// Volume of a new player before settings are applied
+pub const DEFAULT_VOLUME: f32 = 0.8;
+/// Accepted sizes of a volume step, as a fraction of the control's travel
+pub const VOLUME_STEP_RANGE: std::ops::RangeInclusive<f32> = 0.001..=0.25;
+
+/// How long `seek` waits for the decoder to confirm where it landed
+const SEEK_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Playback state
 #[derive(Clone, Copy, Debug, PartialEq, Serialize)]
 pub enum PlaybackState {
@@ -15,18 +55,193 @@ pub enum PlaybackState {
     Ended,
 }
 
+/// What the player does when playback reaches the end of the file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum EndBehavior {
+    /// Stay on the last frame in the `Ended` state
+    #[default]
+    Stop,
+    /// Start again from the beginning
+    Loop,
+    /// Play the next playlist entry, or stop after the last one
+    NextTrack,
+    /// Stop and ask the frontend to close the player
+    Close,
+}
+
+/// Which configured step `adjust_volume` moves by
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum VolumeStep {
+    /// Small step for precise control (e.g. with a modifier key held)
+    Fine,
+    /// The usual step of a key press or wheel notch
+    Coarse,
+}
+
+/// Step sizes of `adjust_volume`, in volume control position.
+///
+/// The position is logarithmic in gain, so a step changes the loudness by the
+/// same number of dB anywhere on the scale: 0.01 is 0.6 dB, 0.05 is 3 dB.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSteps {
+    pub fine: f32,
+    pub coarse: f32,
+}
+
+impl Default for VolumeSteps {
+    fn default() -> Self {
+        Self {
+            fine: 0.01,
+            coarse: 0.05,
+        }
+    }
+}
+
+impl VolumeSteps {
+    pub fn size(&self, step: VolumeStep) -> f32 {
+        match step {
+            VolumeStep::Fine => self.fine,
+            VolumeStep::Coarse => self.coarse,
+        }
+    }
+}
+
 /// Player status for frontend
 #[derive(Clone, Serialize)]
 pub struct PlayerStatus {
     pub is_playing: bool,
     pub current_time: f64,
     pub duration: f64,
+    /// `current_time` and `duration` as `HH:MM:SS.mmm`; the duration is
+    /// `None` while unknown (live streams)
+    pub current_time_text: String,
+    pub duration_text: Option<String>,
     pub volume: f32,
     pub file_path: Option<String>,
     pub has_video: bool,
     pub has_audio: bool,
     pub video_width: u32,
     pub video_height: u32,
+    /// Embedded album art is available via `cover_art`
+    pub has_cover_art: bool,
+    /// The video is HDR (PQ or HLG)
+    pub hdr: bool,
+    pub video_bit_depth: u8,
+    /// Color range, primaries, transfer and matrix of the video
+    pub color: Option<ColorInfo>,
+    /// Capture over playback rate of slow-motion video that declares it
+    pub slow_motion_factor: Option<f64>,
+    /// Slow-motion video plays at its recorded speed (see `set_real_time_motion`)
+    pub real_time_motion: bool,
+    /// Media seconds played per second (see `set_playback_speed`)
+    pub playback_speed: f64,
+    /// Whether audio and video are missing, playing, or failed to initialize
+    pub ready: ReadySummary,
+    /// Why a video stream that exists is not shown (`ready.video` failed)
+    pub video_error: Option<String>,
+    pub live_mode: bool,
+    pub seek_mode: SeekMode,
+    /// The loaded audio is reaching an output device
+    pub audio_output: bool,
+    /// Where the audio goes: the device, a recording, or both
+    pub output_sink: OutputSink,
+    /// Both channels play the mono sum
+    pub mono_output: bool,
+    /// How vocals are removed (`Off` unless enabled)
+    pub vocal_removal: VocalRemovalMode,
+    /// Source channels silenced by `set_channel_mute`, bit 0 the first
+    pub channel_mute: u64,
+    pub video_adjustments: VideoAdjustments,
+    /// Bars frames are padded with, `None` for frames at the source size
+    pub letterbox: Option<Letterbox>,
+    /// Byte layout of the `video-frame` payloads
+    pub output_pixel_format: PixelFormat,
+    /// Region played over and over, `None` while not looping
+    pub loop_region: Option<LoopRegion>,
+    /// Cache mode and whether the loaded file plays from memory
+    pub cache: CacheStatus,
+}
+
+/// Whether audio reaches the device without resampling or effects
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PassthroughStatus {
+    /// Passthrough was turned on with `set_passthrough`
+    pub requested: bool,
+    /// Samples are currently sent unchanged
+    pub active: bool,
+    /// Why passthrough is off although requested
+    pub denied: Option<String>,
+}
+
+/// Playback diagnostics for the frontend
+#[derive(Clone, Serialize)]
+pub struct PlaybackStats {
+    #[serde(flatten)]
+    pub sync: SyncStats,
+    pub output_sample_rate: u32,
+    /// Decode-ahead limit set with `set_buffer_ahead`, in seconds
+    pub buffer_ahead: f64,
+    /// Cap on emitted frames per second set with `set_max_fps`
+    pub max_fps: Option<f64>,
+    pub passthrough: PassthroughStatus,
+    /// What decodes the video right now, updated if decoding falls back to software
+    pub active_decode_backend: Option<DecodeBackend>,
+    /// Gains auto-balance applies to the current file, once measured
+    pub auto_balance: Option<BalanceCorrection>,
+    /// Time the current file took to open and to decode its first outputs
+    pub load_timings: LoadTimings,
+    /// Read buffering the loaded file is read with
+    pub io: IoSettings,
+    /// Overs counted in the output since the load, while clip detection is on
+    pub clipping: ClipStats,
+    /// Output channel fill at the last progress tick
+    pub queues: QueueDepth,
+}
+
+/// Latest frame delivered to the frontend, tagged with the load it belongs to
+#[derive(Default)]
+pub struct FrameSlot {
+    inner: parking_lot::Mutex<(u64, Option<VideoFrame>)>,
+    /// Timestamp of the last frame stored since the last seek
+    timestamp: parking_lot::Mutex<Option<f64>>,
+}
+
+impl FrameSlot {
+    /// Forget the stored frame; returns the generation for the next load
+    fn reset(&self) -> u64 {
+        let mut inner = self.inner.lock();
+        inner.0 += 1;
+        inner.1 = None;
+        *self.timestamp.lock() = None;
+        inner.0
+    }
+
+    /// Forget the presentation time after a seek (the frame itself is kept)
+    fn clear_timestamp(&self) {
+        *self.timestamp.lock() = None;
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.lock().0
+    }
+
+    /// Keep `frame` unless it belongs to an earlier load
+    pub fn store(&self, generation: u64, frame: VideoFrame) {
+        let mut inner = self.inner.lock();
+        if inner.0 == generation {
+            *self.timestamp.lock() = Some(frame.timestamp);
+            inner.1 = Some(frame);
+        }
+    }
+
+    fn latest(&self) -> Option<VideoFrame> {
+        self.inner.lock().1.clone()
+    }
+
+    fn latest_timestamp(&self) -> Option<f64> {
+        *self.timestamp.lock()
+    }
 }
 
 /// Main media player supporting both audio and video
@@ -34,15 +249,74 @@ pub struct MediaPlayer {
     decoder: MediaDecoder,
     audio_output: Option<AudioOutput>,
     sample_sender: Option<Sender<Vec<f32>>>,
+    sample_receiver: Option<Receiver<Vec<f32>>>,
+    output_sample_rate: u32,
     state: PlaybackState,
     current_time: f64,
     duration: f64,
     volume: f32,
+    volume_steps: VolumeSteps,
     file_path: Option<String>,
     has_video: bool,
     has_audio: bool,
-    video_width: u32,
-    video_height: u32,
+    has_cover_art: bool,
+    hdr: bool,
+    video_bit_depth: u8,
+    color: Option<ColorInfo>,
+    slow_motion_factor: Option<f64>,
+    video_streams: Vec<usize>,
+    video_stream_index: Option<usize>,
+    real_time_motion: bool,
+    /// Playback speed of the loaded file
+    speed: f64,
+    /// Speeds new loads start at
+    remembered_speeds: RememberedSpeeds,
+    ready: ReadySummary,
+    media_info: Option<MediaInfo>,
+    subtitle_fonts: Vec<SubtitleFont>,
+    effects: AudioEffects,
+    auto_balance: bool,
+    mono_output: bool,
+    channel_mute: u64,
+    output_sink: OutputSink,
+    tone_mapping: bool,
+    video_adjustments: VideoAdjustments,
+    letterbox: Option<Letterbox>,
+    output_pixel_format: PixelFormat,
+    loop_region: Option<LoopRegion>,
+    preferred_audio_language: Option<String>,
+    /// Keep the audio stream running (on silence) while paused
+    keep_device_warm: bool,
+    /// Raise the priority of the audio callback thread
+    realtime_audio: bool,
+    end_behavior: EndBehavior,
+    seek_mode: SeekMode,
+    buffer_ahead: f64,
+    max_fps: Option<f64>,
+    /// Lip-sync offset in milliseconds (positive delays video)
+    av_offset_ms: f64,
+    passthrough: PassthroughStatus,
+    /// Native audio rate of the loaded file, and why it cannot be passed through
+    source_sample_rate: u32,
+    passthrough_blocker: Option<String>,
+    /// Read buffering requested for local files, and the one the loaded file got
+    io_settings: IoSettings,
+    io: IoSettings,
+    /// Live mode requested by the user
+    live_mode: bool,
+    /// Live mode in effect for the loaded source (requested or inferred from the URL)
+    live_active: bool,
+    /// Secondary decoder for thumbnails, opened on first use for the loaded file
+    thumbnailer: Option<Thumbnailer>,
+    /// Secondary audio decoder for waveform windows, opened on first use
+    waveform: Option<Waveform>,
+    playlist: Playlist,
+    last_frame: Arc<FrameSlot>,
+    /// Devices seen by the last scan
+    output_devices: OutputDevices,
+    /// Output device chosen with `set_output_device`; `None` follows the
+    /// system default
+    output_device: Option<String>,
 }
 
 impl MediaPlayer {
@@ -51,57 +325,314 @@ impl MediaPlayer {
             decoder: MediaDecoder::new(),
             audio_output: None,
             sample_sender: None,
+            sample_receiver: None,
+            output_sample_rate: DEFAULT_OUTPUT_SAMPLE_RATE,
             state: PlaybackState::Stopped,
             current_time: 0.0,
             duration: 0.0,
-            volume: 0.8,
+            volume: DEFAULT_VOLUME,
+            volume_steps: VolumeSteps::default(),
             file_path: None,
             has_video: false,
             has_audio: false,
-            video_width: 0,
-            video_height: 0,
+            has_cover_art: false,
+            hdr: false,
+            video_bit_depth: 0,
+            color: None,
+            slow_motion_factor: None,
+            video_streams: Vec::new(),
+            video_stream_index: None,
+            real_time_motion: false,
+            speed: 1.0,
+            remembered_speeds: RememberedSpeeds::default(),
+            ready: ReadySummary::default(),
+            media_info: None,
+            subtitle_fonts: Vec::new(),
+            effects: AudioEffects::default(),
+            auto_balance: false,
+            mono_output: false,
+            channel_mute: 0,
+            output_sink: OutputSink::Device,
+            tone_mapping: true,
+            video_adjustments: VideoAdjustments::default(),
+            letterbox: None,
+            output_pixel_format: PixelFormat::default(),
+            loop_region: None,
+            preferred_audio_language: None,
+            keep_device_warm: false,
+            realtime_audio: true,
+            end_behavior: EndBehavior::default(),
+            seek_mode: SeekMode::default(),
+            buffer_ahead: DEFAULT_BUFFER_AHEAD,
+            max_fps: None,
+            av_offset_ms: 0.0,
+            passthrough: PassthroughStatus::default(),
+            source_sample_rate: 0,
+            passthrough_blocker: None,
+            io_settings: IoSettings::default(),
+            io: IoSettings::default(),
+            live_mode: false,
+            live_active: false,
+            thumbnailer: None,
+            waveform: None,
+            playlist: Playlist::default(),
+            last_frame: Arc::new(FrameSlot::default()),
+            output_devices: OutputDevices::scan(),
+            output_device: None,
         }
     }
 
-    /// Load a media file with optional video frame sender
+    /// Load a media file with optional video frame sender.
+    ///
+    /// `start_at` seeks to a position (clamped to the duration) before the first
+    /// frame is decoded, for resume support and deep links.
     pub fn load(
         &mut self,
         path: &str,
         video_sender: Option<Sender<VideoFrame>>,
+        start_at: Option<f64>,
     ) -> Result<PlayerStatus> {
         // Stop current playback
         self.stop();
+        self.open_media(path, video_sender, start_at)
+    }
+
+    /// Switch to another file on the same decoder thread, keeping the audio
+    /// device stream open (see `load` for a full restart).
+    ///
+    /// Volume, effects and the other settings carry over as with `load`, and
+    /// playback goes on with the new file if it was playing. The stream is
+    /// only closed when the new file has no audio or a live source needs the
+    /// low-latency path, and reopened when passthrough changes the rate.
+    pub fn replace_media(
+        &mut self,
+        path: &str,
+        video_sender: Option<Sender<VideoFrame>>,
+    ) -> Result<PlayerStatus> {
+        let was_playing = self.state == PlaybackState::Playing;
+        let live = self.live_mode || is_live_source(path);
+        if self.audio_output.is_none() || live || self.live_active {
+            self.stop();
+        } else {
+            let _ = self.decoder.stop();
+            self.park_audio_output();
+            self.state = PlaybackState::Stopped;
+            self.current_time = 0.0;
+            self.last_frame.reset();
+        }
+
+        let status = self.open_media(path, video_sender, None)?;
+        if was_playing {
+            self.play()?;
+            return Ok(self.get_status());
+        }
+        Ok(status)
+    }
+
+    /// Load `path` into the decoder, reusing the sample channel and audio
+    /// output if `replace_media` kept them open
+    fn open_media(
+        &mut self,
+        path: &str,
+        video_sender: Option<Sender<VideoFrame>>,
+        start_at: Option<f64>,
+    ) -> Result<PlayerStatus> {
+        self.thumbnailer = None;
+        self.waveform = None;
 
-        // Load file in decoder with video sender
-        let info = self.decoder.load(path, video_sender)?;
+        // Real-time sources always use the low-latency path
+        self.live_active = self.live_mode || is_live_source(path);
+        self.decoder.set_live_mode(self.live_active)?;
+        self.speed = self.speed_for_load(path);
+
+        // Load file in decoder with video and audio sample senders
+        let (sample_sender, sample_receiver) = match (&self.sample_sender, &self.sample_receiver) {
+            (Some(sender), Some(receiver)) => (sender.clone(), receiver.clone()),
+            _ => create_sample_channel(),
+        };
+        let audio_buffer = AudioBuffer::new(sample_sender.clone(), sample_receiver.clone());
+        // Leftovers of the previous file must not play at the start of this one
+        audio_buffer.clear();
+        let info = self.decoder.load(path, video_sender, Some(audio_buffer))?;
+        self.sync_decoder_settings()?;
 
         self.has_video = info.has_video;
         self.has_audio = info.has_audio;
-        self.video_width = info.video_width;
-        self.video_height = info.video_height;
+        self.has_cover_art = info.has_cover_art;
+        self.hdr = info.hdr;
+        self.video_bit_depth = info.video_bit_depth;
+        self.color = info.color;
+        self.slow_motion_factor = info.slow_motion_factor;
+        self.video_streams = info.video_streams;
+        self.video_stream_index = info.video_stream_index;
+        self.ready = info.ready;
+        self.source_sample_rate = info.audio_sample_rate;
+        self.passthrough_blocker = info.passthrough_blocker;
+        self.io = info.io;
         self.duration = info.duration;
+        self.loop_region = None;
         self.file_path = info.file_path.clone();
+        self.playlist.select(path);
+        self.subtitle_fonts = info.subtitle_fonts;
+        self.media_info = Some(MediaInfo {
+            active_decode_backend: self.decoder.decode_backend(),
+            deinterlacing: self.decoder.deinterlacing(),
+            ..info.media
+        });
         self.current_time = 0.0;
         self.state = PlaybackState::Stopped;
 
+        // Position the decoder before anything is played
+        if let Some(start) = start_at.filter(|_| !self.live_active) {
+            self.seek_decoder(start)?;
+        }
+        // Show the picture before play is pressed
+        if self.has_video {
+            self.decoder.show_frame(true)?;
+        }
+        if self.passthrough.requested {
+            self.apply_passthrough()?;
+        }
+
         // Setup audio if available
-        if self.has_audio {
-            let (sample_sender, sample_receiver) = create_sample_channel();
+        if !self.has_audio {
+            if let Some(output) = self.audio_output.take() {
+                output.stop();
+            }
+            self.sample_sender = None;
+            self.sample_receiver = None;
+        } else if self.audio_output.is_some() {
+            self.decoder.set_audio_output_connected(true)?;
+        } else {
             self.sample_sender = Some(sample_sender);
+            self.sample_receiver = Some(sample_receiver);
+            self.connect_audio_output()?;
+        }
+
+        Ok(self.get_status())
+    }
+
+    /// Re-send user settings so a newly loaded file plays with them from the first sample
+    fn sync_decoder_settings(&self) -> Result<()> {
+        self.decoder.set_volume(self.volume)?;
+        self.decoder.set_playback_speed(self.speed)?;
+        self.decoder.set_effects(self.effects.clone())?;
+        self.decoder.set_auto_balance(self.auto_balance)?;
+        self.decoder.set_mono_output(self.mono_output)?;
+        self.decoder.set_tone_mapping(self.tone_mapping)?;
+        self.decoder.set_video_adjustments(self.video_adjustments)?;
+        self.decoder.set_letterbox(self.letterbox)?;
+        self.decoder
+            .set_output_pixel_format(self.output_pixel_format)
+    }
+
+    /// Open the audio device at the current output rate
+    fn open_audio_output(&mut self) -> Result<()> {
+        let Some(receiver) = self.sample_receiver.clone() else {
+            return Ok(());
+        };
+        let device = self.connected_output_device();
+        let output = AudioOutput::new(
+            device.as_deref(),
+            self.output_sample_rate,
+            OUTPUT_CHANNELS,
+            receiver,
+            self.decoder.audio_clock(),
+            OutputOptions {
+                live_mode: self.live_active,
+                realtime: self.realtime_audio,
+                timer: self.decoder.clock(),
+            },
+        )
+        .map_err(|e| PlayerError::AudioOutput(e.to_string()))?;
+
+        // The device runs at the closest rate it supports; resample to that one,
+        // or audio plays at the wrong pitch and speed
+        let rate = output.sample_rate();
+        self.audio_output = Some(output);
+        if rate != self.output_sample_rate {
+            eprintln!(
+                "Output device runs at {} Hz instead of {} Hz, resampling to it",
+                rate, self.output_sample_rate
+            );
+            self.output_sample_rate = rate;
+            self.decoder.set_output_sample_rate(rate)?;
+        }
+        if self.state != PlaybackState::Playing {
+            self.park_audio_output();
+        }
+        Ok(())
+    }
 
-            self.audio_output = Some(AudioOutput::new(44100, 2, sample_receiver)?);
+    /// Open the audio device, keeping playback going without sound if none is
+    /// available; `retry_audio_output` tries again later
+    fn connect_audio_output(&mut self) -> Result<()> {
+        let opened = self.open_audio_output();
+        self.audio_output_opened(opened)
+    }
+
+    /// Route audio to the device if it opened, else drop it with a warning
+    fn audio_output_opened(&mut self, opened: Result<()>) -> Result<()> {
+        match opened {
+            Ok(()) => self.decoder.set_audio_output_connected(true),
+            Err(e) => {
+                self.ready.audio = StreamStatus::Failed(e.to_string());
+                let _ = self.event_sender().send(PlayerEvent::Warning {
+                    message: format!("No audio output, playing without sound: {}", e),
+                });
+                self.decoder.set_audio_output_connected(false)
+            }
         }
+    }
 
+    /// Try again to open the audio device after it failed at load time (e.g.
+    /// once a device is plugged in). Playback resumes with sound from the
+    /// current position.
+    pub fn retry_audio_output(&mut self) -> Result<PlayerStatus> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if self.audio_output.is_none() && self.sample_receiver.is_some() {
+            self.open_audio_output()?;
+            self.ready.audio = StreamStatus::Ready;
+            self.decoder.set_audio_output_connected(true)?;
+            if !self.live_active {
+                // Restart the audio clock in step with the video
+                self.seek(self.position())?;
+            }
+        }
         Ok(self.get_status())
     }
 
+    /// Silence the device while not playing: held open if kept warm, else paused
+    fn park_audio_output(&self) {
+        if let Some(ref output) = self.audio_output {
+            if self.keep_device_warm {
+                output.hold();
+            } else {
+                output.pause();
+            }
+        }
+    }
+
     /// Play media
     pub fn play(&mut self) -> Result<()> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+
         match self.state {
-            PlaybackState::Stopped | PlaybackState::Ended => {
+            PlaybackState::Stopped => {
                 // Start from beginning
                 self.decoder.play()?;
             }
+            PlaybackState::Ended => {
+                // The decoder is parked at the end of the input
+                if !self.live_active {
+                    self.seek(0.0)?;
+                }
+                self.decoder.play()?;
+            }
             PlaybackState::Paused => {
                 // Resume
                 self.decoder.play()?;
@@ -121,11 +652,7 @@ impl MediaPlayer {
     pub fn pause(&mut self) -> Result<()> {
         if self.state == PlaybackState::Playing {
             self.decoder.pause()?;
-
-            if let Some(ref output) = self.audio_output {
-                output.pause();
-            }
-
+            self.park_audio_output();
             self.state = PlaybackState::Paused;
         }
         Ok(())
@@ -143,14 +670,132 @@ impl MediaPlayer {
         self.current_time = 0.0;
         self.audio_output = None;
         self.sample_sender = None;
+        self.sample_receiver = None;
+        self.last_frame.reset();
+        self.loop_region = None;
+        // The decoder finalized the recording
+        self.output_sink = OutputSink::Device;
+    }
+
+    /// Seek to a specific time in seconds, once the decoder has applied it.
+    ///
+    /// Returns the position playback resumes from, which is earlier than `time`
+    /// in `SeekMode::Fast`. While not playing, the frame at that position is
+    /// decoded and sent right away, so the picture matches the position.
+    pub fn seek(&mut self, time: f64) -> Result<f64> {
+        let landed = self.seek_decoder(time)?;
+        if self.state != PlaybackState::Playing && self.has_video {
+            self.decoder.show_frame(false)?;
+        }
+        Ok(landed)
     }
 
-    /// Seek to a specific time in seconds
-    pub fn seek(&mut self, time: f64) -> Result<()> {
+    /// Move the decoder without showing a frame
+    fn seek_decoder(&mut self, time: f64) -> Result<f64> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if self.live_active {
+            return Err(PlayerError::NotSeekable.into());
+        }
+
+        // A preview still decoding would land after the committed frame
+        if let Some(ref thumbnailer) = self.thumbnailer {
+            thumbnailer.cancel_scrub();
+        }
         let time = time.clamp(0.0, self.duration);
-        self.decoder.seek(time)?;
-        self.current_time = time;
-        Ok(())
+        let landed = self.decoder.seek_confirmed(time, SEEK_ACK_TIMEOUT)?;
+        self.current_time = landed;
+        self.last_frame.clear_timestamp();
+        Ok(landed)
+    }
+
+    /// Current playback position in seconds.
+    ///
+    /// With audio this is sample-accurate: the timestamp of the first sample
+    /// played since the last seek plus the samples the device has consumed
+    /// since. Video-only media reports the last presented frame. Until new
+    /// media plays after a seek, the seek target is reported.
+    pub fn position(&self) -> f64 {
+        if self.state == PlaybackState::Stopped {
+            return self.current_time;
+        }
+        let position = if self.has_audio {
+            let samples_per_second = self.output_sample_rate as f64 * OUTPUT_CHANNELS as f64;
+            self.decoder.audio_clock().position(samples_per_second)
+        } else {
+            self.last_frame.latest_timestamp()
+        };
+        let position = position.unwrap_or(self.current_time).max(0.0);
+        // While looping the clock runs on across passes
+        let position = self
+            .loop_region
+            .map_or(position, |region| region.fold(position));
+        if self.duration > 0.0 {
+            position.min(self.duration)
+        } else {
+            position
+        }
+    }
+
+    /// Playback position as a fraction (0.0 to 1.0) of the duration; `None`
+    /// for live streams and files of unknown length
+    pub fn position_fraction(&self) -> Option<f64> {
+        position_fraction(self.position(), self.duration)
+    }
+
+    /// Seek to a fraction (0.0 to 1.0) of the duration; returns the position
+    /// landed on in seconds
+    pub fn seek_fraction(&mut self, fraction: f64) -> Result<f64> {
+        if !fraction.is_finite() {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid seek fraction {}",
+                fraction
+            ))
+            .into());
+        }
+        if self.file_path.is_some() && !(self.duration > 0.0 && self.duration.is_finite()) {
+            return Err(PlayerError::NotSeekable.into());
+        }
+        self.seek(fraction.clamp(0.0, 1.0) * self.duration)
+    }
+
+    /// Seek to `percent` (0 to 100) of the file, for huge files where exact
+    /// seeks are slow and files whose timestamps cannot be trusted.
+    ///
+    /// With a known duration and reliable timestamps this seeks by time,
+    /// snapping back to the keyframe before it with `snap_to_keyframe`
+    /// (instant, whatever the seek mode). Files of unknown length and formats
+    /// whose timestamps may jump (MPEG-TS) seek to that fraction of their bytes
+    /// and land on the first keyframe after it. Returns the position landed on
+    pub fn seek_percent(&mut self, percent: f64, snap_to_keyframe: bool) -> Result<f64> {
+        if !percent.is_finite() {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid seek percentage {}",
+                percent
+            ))
+            .into());
+        }
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if self.live_active {
+            return Err(PlayerError::NotSeekable.into());
+        }
+
+        if let Some(ref thumbnailer) = self.thumbnailer {
+            thumbnailer.cancel_scrub();
+        }
+        let fraction = percent.clamp(0.0, 100.0) / 100.0;
+        let landed = self
+            .decoder
+            .seek_percent(fraction, snap_to_keyframe, SEEK_ACK_TIMEOUT)?;
+        self.current_time = landed;
+        self.last_frame.clear_timestamp();
+        if self.state != PlaybackState::Playing && self.has_video {
+            self.decoder.show_frame(false)?;
+        }
+        Ok(landed)
     }
 
     /// Set volume (0.0 - 1.0)
@@ -160,18 +805,1039 @@ impl MediaPlayer {
         let _ = self.decoder.set_volume(volume);
     }
 
+    /// Keep the audio device running on silence while paused, so resume has no
+    /// device start-up latency. The device still sleeps after a long pause.
+    pub fn set_keep_device_warm(&mut self, enabled: bool) {
+        self.keep_device_warm = enabled;
+        if self.state != PlaybackState::Playing {
+            self.park_audio_output();
+        }
+    }
+
+    /// Raise the scheduling priority of the audio device thread (on by
+    /// default) where the OS allows it; an open output is rebuilt to apply it
+    pub fn set_realtime_audio(&mut self, enabled: bool) -> Result<()> {
+        if enabled == self.realtime_audio {
+            return Ok(());
+        }
+        self.realtime_audio = enabled;
+        if let Some(output) = self.audio_output.take() {
+            output.stop();
+            self.open_audio_output()?;
+        }
+        Ok(())
+    }
+
+    /// Play on the output device named `name`, or the system default for
+    /// `None`; an open output moves to it at once.
+    ///
+    /// A device that is not connected is still remembered (and saved with the
+    /// settings): audio plays on the default device with a `player-warning`
+    /// until a scan finds it again. Returns the name of the device audio plays
+    /// on.
+    pub fn set_output_device(&mut self, name: Option<String>) -> Result<Option<String>> {
+        let previous = self.connected_output_device();
+        self.output_device = name;
+        if let Some(ref name) = self.output_device {
+            if !self.output_devices.devices.contains(name) {
+                let _ = self.event_sender().send(PlayerEvent::Warning {
+                    message: format!(
+                        "Output device {} is not connected, using the default device",
+                        name
+                    ),
+                });
+            }
+        }
+        if self.connected_output_device() != previous {
+            if let Some(output) = self.audio_output.take() {
+                output.stop();
+                self.open_audio_output()?;
+            }
+        }
+        Ok(self.current_output_device())
+    }
+
+    /// The chosen output device while it is connected; `None` plays on the
+    /// system default
+    fn connected_output_device(&self) -> Option<String> {
+        self.output_device
+            .clone()
+            .filter(|name| self.output_devices.devices.contains(name))
+    }
+
+    /// End playback of the loaded file at `end` seconds instead of at its end,
+    /// e.g. for a cue sheet track; the next load clears it
+    pub fn set_out_point(&mut self, end: Option<f64>) -> Result<()> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if let Some(end) = end.filter(|end| !end.is_finite() || *end <= 0.0) {
+            return Err(PlayerError::InvalidArgument(format!("Invalid out point {}", end)).into());
+        }
+        self.decoder.set_out_point(end)
+    }
+
+    /// Play `region` over and over without a stall at the boundary, e.g. for
+    /// background video or A-B repeat; `None` plays on to the end again.
+    ///
+    /// The end is clamped to the duration, and the region must be at least
+    /// `MIN_LOOP_LENGTH` and (for video) one frame long. Playback moves into
+    /// it: from the current position if that is before the end, else from the
+    /// start. While looping, seeks past the end land on the start and the loop
+    /// overrides the out point. Returns the region in use; the next load
+    /// clears it.
+    pub fn set_loop(&mut self, region: Option<LoopRegion>) -> Result<Option<LoopRegion>> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if self.live_active || !(self.duration > 0.0 && self.duration.is_finite()) {
+            return Err(PlayerError::NotSeekable.into());
+        }
+        let region = region.map(|region| self.checked_loop(region)).transpose()?;
+
+        let position = self.position();
+        self.decoder.set_loop(region)?;
+        self.loop_region = region;
+        self.seek(region.map_or(position, |region| region.seek_target(position)))?;
+        Ok(region)
+    }
+
+    /// `region` with its end clamped to the duration, if it is long enough
+    fn checked_loop(&self, region: LoopRegion) -> Result<LoopRegion> {
+        if !region.start.is_finite() || !region.end.is_finite() || region.start < 0.0 {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid loop region {} to {}",
+                region.start, region.end
+            ))
+            .into());
+        }
+        let region = LoopRegion {
+            end: region.end.min(self.duration),
+            ..region
+        };
+        let frame_interval = self
+            .media_info
+            .as_ref()
+            .and_then(|info| info.streams.iter().find(|s| s.kind == "video"))
+            .and_then(|stream| stream.frame_rate)
+            .filter(|_| self.has_video)
+            .map_or(0.0, |fps| 1.0 / fps);
+        let min_length = MIN_LOOP_LENGTH.max(frame_interval);
+        if region.length() < min_length {
+            return Err(PlayerError::InvalidArgument(format!(
+                "The loop region must be at least {:.3} s long",
+                min_length
+            ))
+            .into());
+        }
+        Ok(region)
+    }
+
+    /// Choose what happens when playback reaches the end (`Stop` by default)
+    pub fn set_end_behavior(&mut self, behavior: EndBehavior) {
+        self.end_behavior = behavior;
+    }
+
+    /// Apply the end behavior once the decoder reports the end of the file.
+    ///
+    /// Returns the playlist entry to load next for `NextTrack`; loading it is
+    /// left to the caller, which also sets up the frame emitter.
+    pub fn handle_end(&mut self) -> Result<Option<(usize, PlaylistEntry)>> {
+        // The end of a file replaced or stopped since
+        if self.state != PlaybackState::Playing {
+            return Ok(None);
+        }
+        self.state = PlaybackState::Ended;
+        match self.end_behavior {
+            EndBehavior::Stop => {}
+            EndBehavior::Loop => self.play()?,
+            EndBehavior::NextTrack => {
+                if let Some(next) = self.playlist.adjacent(1) {
+                    return Ok(Some(next));
+                }
+            }
+            EndBehavior::Close => {
+                let _ = self.event_sender().send(PlayerEvent::CloseRequested);
+            }
+        }
+        if self.state == PlaybackState::Ended {
+            self.park_audio_output();
+        }
+        Ok(None)
+    }
+
+    /// Change the volume by `delta`, returning the new (clamped) volume.
+    ///
+    /// With a `step`, `delta` counts steps of that configured size instead
+    /// (`1.0` up, `-1.0` down).
+    pub fn adjust_volume(&mut self, delta: f32, step: Option<VolumeStep>) -> Result<f32> {
+        if !delta.is_finite() {
+            return Err(
+                PlayerError::InvalidArgument(format!("Invalid volume step {}", delta)).into(),
+            );
+        }
+        let change = match step {
+            Some(step) => delta * self.volume_steps.size(step),
+            None => delta,
+        };
+        self.set_volume(self.volume + change);
+        Ok(self.volume)
+    }
+
+    /// Set the fine and coarse step sizes of `adjust_volume`, each clamped to
+    /// `VOLUME_STEP_RANGE`; returns the sizes in use
+    pub fn set_volume_steps(&mut self, steps: VolumeSteps) -> Result<VolumeSteps> {
+        for size in [steps.fine, steps.coarse] {
+            if !size.is_finite() {
+                return Err(PlayerError::InvalidArgument(format!(
+                    "Invalid volume step size {}",
+                    size
+                ))
+                .into());
+            }
+        }
+        let clamp = |size: f32| size.clamp(*VOLUME_STEP_RANGE.start(), *VOLUME_STEP_RANGE.end());
+        self.volume_steps = VolumeSteps {
+            fine: clamp(steps.fine),
+            coarse: clamp(steps.coarse),
+        };
+        Ok(self.volume_steps)
+    }
+
+    pub fn volume_steps(&self) -> VolumeSteps {
+        self.volume_steps
+    }
+
+    /// Enable or disable the low-latency live path.
+    ///
+    /// Live mode minimizes buffering for real-time sources (capture, RTSP): the
+    /// demuxer does not buffer, audio pre-roll is kept to ~100ms and video frames
+    /// that arrive while the previous one is still queued are dropped. Latency
+    /// stays bounded at the cost of occasional audio glitches and skipped frames.
+    /// URLs with a streaming scheme (`rtsp://`, `udp://`, ...) always use it.
+    /// Takes effect on the next load.
+    pub fn set_live_mode(&mut self, enabled: bool) {
+        self.live_mode = enabled;
+    }
+
+    /// Path of the loaded file if it embeds cover art
+    pub fn cover_art_path(&self) -> Result<String> {
+        let Some(ref path) = self.file_path else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if !self.has_cover_art {
+            return Err(PlayerError::Unsupported("No cover art".to_string()).into());
+        }
+        Ok(path.clone())
+    }
+
+    /// Files queued for playback and the one currently loaded
+    pub fn playlist(&self) -> Playlist {
+        self.playlist.clone()
+    }
+
+    /// Append files to the playlist; returns the index of the first one
+    pub fn enqueue(&mut self, entries: Vec<PlaylistEntry>) -> usize {
+        self.playlist.append(entries)
+    }
+
+    /// Note that the playlist entry at `index` is the file now loaded, and
+    /// announce it with `track-changed`
+    pub fn set_playlist_current(&mut self, index: usize) {
+        self.playlist.set_current(index);
+        let Some(entry) = self.playlist.get(index) else {
+            return;
+        };
+        let _ = self.event_sender().send(PlayerEvent::TrackChanged {
+            index,
+            path: entry.path.clone(),
+            title: entry.title.clone(),
+            duration: (entry.end.unwrap_or(self.duration) - entry.start.unwrap_or(0.0)).max(0.0),
+            metadata: self
+                .media_info
+                .as_ref()
+                .map(|info| info.metadata.clone())
+                .unwrap_or_default(),
+        });
+    }
+
+    /// Path of the loaded file and, if it has video, the thumbnail decoder to
+    /// preview its chapters with
+    pub fn chapter_source(&mut self) -> Result<(String, Option<Thumbnailer>)> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        let thumbnailer = if self.has_video {
+            Some(self.thumbnailer()?)
+        } else {
+            None
+        };
+        Ok((path, thumbnailer))
+    }
+
+    /// Path of the loaded file, if it is on the local file system (not a URL)
+    pub fn local_path(&self) -> Result<String> {
+        let Some(ref path) = self.file_path else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if path.contains("://") {
+            return Err(PlayerError::Unsupported(format!("{} is not a local file", path)).into());
+        }
+        Ok(path.clone())
+    }
+
+    /// Path of the loaded file, if it has video
+    pub fn video_path(&self) -> Result<String> {
+        let Some(ref path) = self.file_path else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if !self.has_video {
+            return Err(PlayerError::Unsupported("No video stream".to_string()).into());
+        }
+        Ok(path.clone())
+    }
+
+    /// Slot the frame emitter stores delivered frames in, with the current generation
+    pub fn frame_slot(&self) -> (Arc<FrameSlot>, u64) {
+        (self.last_frame.clone(), self.last_frame.generation())
+    }
+
+    /// Last video frame delivered to the frontend, or `None` if none was decoded yet
+    pub fn current_video_frame(&self) -> Result<Option<VideoFrame>> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if !self.has_video {
+            return Err(PlayerError::Unsupported("No video stream".to_string()).into());
+        }
+        Ok(self.last_frame.latest())
+    }
+
+    /// Get the thumbnail decoder for the loaded file, opening it if needed.
+    ///
+    /// The returned handle can be used after releasing the player lock.
+    pub fn thumbnailer(&mut self) -> Result<Thumbnailer> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if self.live_active {
+            return Err(PlayerError::NotSeekable.into());
+        }
+
+        match self.thumbnailer {
+            Some(ref thumbnailer) if thumbnailer.path() == path => Ok(thumbnailer.clone()),
+            _ => {
+                let thumbnailer = Thumbnailer::open(&path)?;
+                self.thumbnailer = Some(thumbnailer.clone());
+                Ok(thumbnailer)
+            }
+        }
+    }
+
+    /// Handle to the waveform decoder for the loaded file, opened on first use
+    pub fn waveform(&mut self) -> Result<Waveform> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if self.live_active {
+            return Err(PlayerError::NotSeekable.into());
+        }
+        if !self.has_audio {
+            return Err(PlayerError::Unsupported("No audio stream".to_string()).into());
+        }
+
+        match self.waveform {
+            Some(ref waveform) if waveform.path() == path => Ok(waveform.clone()),
+            _ => {
+                let waveform = Waveform::open(&path)?;
+                self.waveform = Some(waveform.clone());
+                Ok(waveform)
+            }
+        }
+    }
+
+    /// Change the device sample rate, e.g. to match a DAC.
+    ///
+    /// Falls back to the closest rate the device supports. The resampler and the
+    /// output stream are rebuilt and playback resumes from the same position.
+    /// Returns the rate actually in use.
+    pub fn set_output_sample_rate(&mut self, rate: u32) -> Result<u32> {
+        let rate = nearest_supported_rate(rate, OUTPUT_CHANNELS)
+            .map_err(|e| PlayerError::AudioOutput(e.to_string()))?;
+        if rate == self.output_sample_rate {
+            return Ok(rate);
+        }
+
+        // Stop the old stream first so the device clock freezes at the current position
+        if let Some(output) = self.audio_output.take() {
+            output.stop();
+        }
+        self.output_sample_rate = rate;
+        self.decoder.set_output_sample_rate(rate)?;
+        self.open_audio_output()?;
+        Ok(self.output_sample_rate)
+    }
+
+    /// Send audio to the device unchanged when it can play the source's native
+    /// rate and format; otherwise keep resampling and report why
+    pub fn set_passthrough(&mut self, enabled: bool) -> Result<PassthroughStatus> {
+        self.passthrough.requested = enabled;
+        self.apply_passthrough()?;
+        Ok(self.passthrough.clone())
+    }
+
+    /// Decide whether the loaded audio can be passed through and configure the
+    /// decoder and output rate accordingly
+    fn apply_passthrough(&mut self) -> Result<()> {
+        let denied = if !self.passthrough.requested {
+            None
+        } else if self.file_path.is_none() {
+            Some("nothing is loaded".to_string())
+        } else if !self.has_audio {
+            Some("the file has no playable audio".to_string())
+        } else if let Some(ref reason) = self.passthrough_blocker {
+            Some(reason.clone())
+        } else {
+            let rate = self.source_sample_rate;
+            match nearest_supported_rate(rate, OUTPUT_CHANNELS) {
+                Ok(supported) if supported == rate => None,
+                _ => Some(format!("the output device cannot play {} Hz", rate)),
+            }
+        };
+
+        let active = self.passthrough.requested && denied.is_none();
+        if active {
+            self.set_output_sample_rate(self.source_sample_rate)?;
+        }
+        self.decoder.set_passthrough(active)?;
+        if let Some(reason) = denied.as_ref().filter(|_| self.has_audio) {
+            let _ = self.event_sender().send(PlayerEvent::Warning {
+                message: format!("Passthrough unavailable: {}", reason),
+            });
+        }
+        self.passthrough.active = active;
+        self.passthrough.denied = denied;
+        Ok(())
+    }
+
+    /// Choose between keyframe (fast) and frame-accurate seeks
+    pub fn set_seek_mode(&mut self, mode: SeekMode) -> Result<()> {
+        self.seek_mode = mode;
+        self.decoder.set_seek_mode(mode)
+    }
+
+    /// Limit how far decoding runs ahead of playback; returns the clamped value
+    pub fn set_buffer_ahead(&mut self, seconds: f64) -> Result<f64> {
+        if !seconds.is_finite() {
+            return Err(
+                PlayerError::InvalidArgument(format!("Invalid buffer length {}", seconds)).into(),
+            );
+        }
+        let seconds = seconds.clamp(MIN_BUFFER_AHEAD, MAX_BUFFER_AHEAD);
+        self.decoder.set_buffer_ahead(seconds)?;
+        self.buffer_ahead = seconds;
+        Ok(seconds)
+    }
+
+    /// Emit at most `fps` video frames per second, e.g. the display refresh
+    /// rate for high frame rate sources; `None` emits every frame
+    pub fn set_max_fps(&mut self, fps: Option<f64>) -> Result<()> {
+        if let Some(fps) = fps.filter(|fps| !MAX_FPS_RANGE.contains(fps)) {
+            return Err(
+                PlayerError::InvalidArgument(format!("Invalid frame rate cap {}", fps)).into(),
+            );
+        }
+        self.decoder.set_max_fps(fps)?;
+        self.max_fps = fps;
+        Ok(())
+    }
+
+    /// Shift video against audio to fine-tune lip sync (positive delays video,
+    /// negative shows it earlier); returns the clamped offset in milliseconds
+    pub fn set_av_offset(&mut self, ms: f64) -> Result<f64> {
+        if !ms.is_finite() {
+            return Err(PlayerError::InvalidArgument(format!("Invalid A/V offset {}", ms)).into());
+        }
+        let ms = ms.clamp(-MAX_AV_OFFSET_MS, MAX_AV_OFFSET_MS);
+        self.decoder.set_av_offset(ms / 1000.0)?;
+        self.av_offset_ms = ms;
+        Ok(ms)
+    }
+
+    /// Skip leading and trailing audio quieter than `threshold_db` (RMS, dBFS)
+    /// in audio-only files
+    pub fn set_skip_silence(&mut self, enabled: bool, threshold_db: f32) -> Result<()> {
+        if !threshold_db.is_finite() {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid silence threshold {}",
+                threshold_db
+            ))
+            .into());
+        }
+        let threshold_db = threshold_db.clamp(-120.0, 0.0);
+        self.decoder
+            .set_skip_silence(enabled.then_some(threshold_db))
+    }
+
+    /// Play slow-motion video at the speed it was recorded at instead of
+    /// slowed down as authored (off by default). Only applies to video without
+    /// audio that declares its capture rate; files keep the choice across loads
+    pub fn set_real_time_motion(&mut self, enabled: bool) -> Result<()> {
+        self.real_time_motion = enabled;
+        self.decoder.set_real_time_motion(enabled)
+    }
+
+    /// Play `speed` (0.5 to 2.0) media seconds per second without changing
+    /// the pitch; returns the speed in use (clamped). The speed is noted for
+    /// the next loads as `set_speed_memory` asks; live sources play at 1x
+    pub fn set_playback_speed(&mut self, speed: f64) -> Result<f64> {
+        if !speed.is_finite() {
+            return Err(
+                PlayerError::InvalidArgument(format!("Invalid playback speed {}", speed)).into(),
+            );
+        }
+        let speed = speed.clamp(*PLAYBACK_SPEED_RANGE.start(), *PLAYBACK_SPEED_RANGE.end());
+        if self.live_active && speed != 1.0 {
+            return Err(
+                PlayerError::Unsupported("Live sources play at normal speed".to_string()).into(),
+            );
+        }
+        self.remembered_speeds
+            .remember(self.file_path.as_deref(), speed);
+        if speed != self.speed {
+            let position = self.position();
+            self.speed = speed;
+            self.decoder.set_playback_speed(speed)?;
+            // Audio already queued at the old speed is dropped
+            if self.file_path.is_some() && !self.live_active {
+                self.seek(position)?;
+            }
+        }
+        Ok(speed)
+    }
+
+    /// Choose which speed newly loaded files start at
+    pub fn set_speed_memory(&mut self, mode: SpeedMemory) {
+        self.remembered_speeds.mode = mode;
+    }
+
+    /// Speed the file at `path` starts playing at
+    fn speed_for_load(&self, path: &str) -> f64 {
+        if self.live_active {
+            1.0
+        } else {
+            self.remembered_speeds.speed_for(path)
+        }
+    }
+
+    /// Choose which clock audio and video are synchronized to
+    pub fn set_sync_mode(&mut self, mode: SyncMode) -> Result<()> {
+        self.decoder.set_sync_mode(mode)
+    }
+
+    /// Choose whether audio (`SmoothnessFirst`, the default) or video frames
+    /// (`QualityFirst`) give way when decoding falls behind; the mode in use
+    /// is reported in `get_stats`
+    pub fn set_degradation_mode(&mut self, mode: DegradationMode) -> Result<()> {
+        self.decoder.set_degradation_mode(mode)
+    }
+
+    /// Decode files completely into memory so seeks are instant (`FullDecode`),
+    /// or decode just ahead of playback (`Streaming`, the default). `max_mb`
+    /// caps the memory a file may take; longer or larger files stream with a
+    /// warning. Applies to the loaded file and the following ones
+    pub fn set_cache_mode(&mut self, mode: CacheMode, max_mb: Option<u64>) -> Result<()> {
+        let max_mb = max_mb.unwrap_or(DEFAULT_CACHE_MAX_BYTES / (1024 * 1024));
+        if !CACHE_MAX_MB_RANGE.contains(&max_mb) {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Cache cap must be {}..={} MiB, got {}",
+                CACHE_MAX_MB_RANGE.start(),
+                CACHE_MAX_MB_RANGE.end(),
+                max_mb
+            ))
+            .into());
+        }
+        self.decoder.set_cache_mode(mode, max_mb * 1024 * 1024)
+    }
+
+    /// Read local files through an AVIO buffer of `bytes`, for fewer and
+    /// larger reads on slow disks. Sizes outside 4 KiB to 16 MiB fall back to
+    /// FFmpeg's 32 KiB. Applies from the next file opened; returns the
+    /// size that will be used
+    pub fn set_io_buffer_size(&mut self, bytes: u64) -> Result<usize> {
+        let size = checked_buffer_size(bytes);
+        if size as u64 != bytes {
+            eprintln!(
+                "Unsupported I/O buffer size of {} bytes, using {}",
+                bytes, size
+            );
+        }
+        self.io_settings.buffer_size = size;
+        self.decoder.set_io_settings(self.io_settings)?;
+        Ok(size)
+    }
+
+    /// Read local files in large sequential chunks ahead of the demuxer (off by
+    /// default), so short seeks and interleaved streams rarely wait on the disk.
+    /// Applies from the next file opened
+    pub fn set_read_ahead(&mut self, enabled: bool) -> Result<()> {
+        self.io_settings.read_ahead = enabled;
+        self.decoder.set_io_settings(self.io_settings)
+    }
+
+    /// Tone map HDR video to SDR (on by default); off shows the raw signal
+    pub fn set_tone_mapping(&mut self, enabled: bool) -> Result<()> {
+        self.tone_mapping = enabled;
+        self.decoder.set_tone_mapping(enabled)
+    }
+
+    pub fn tone_mapping(&self) -> bool {
+        self.tone_mapping
+    }
+
+    /// Choose when interlaced video is deinterlaced (`Auto` by default)
+    pub fn set_deinterlace(&mut self, mode: DeinterlaceMode) -> Result<()> {
+        self.decoder.set_deinterlace(mode)
+    }
+
+    /// Set brightness, contrast, saturation and gamma; returns them as clamped
+    pub fn set_video_adjustments(
+        &mut self,
+        adjustments: VideoAdjustments,
+    ) -> Result<VideoAdjustments> {
+        let adjustments = adjustments.clamped();
+        self.decoder.set_video_adjustments(adjustments)?;
+        self.video_adjustments = adjustments;
+        Ok(adjustments)
+    }
+
+    /// Pad frames with bars to `aspect_ratio` (display width over height), so
+    /// they have a fixed size for the whole file; `None` sends them at the
+    /// source size. Applies from the next decoded frame.
+    pub fn set_letterbox(&mut self, letterbox: Option<Letterbox>) -> Result<Option<Letterbox>> {
+        if let Some(aspect) = letterbox
+            .map(|l| l.aspect_ratio)
+            .filter(|aspect| !LETTERBOX_ASPECT_RANGE.contains(aspect))
+        {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid letterbox aspect ratio {}",
+                aspect
+            ))
+            .into());
+        }
+        self.decoder.set_letterbox(letterbox)?;
+        self.letterbox = letterbox;
+        Ok(letterbox)
+    }
+
+    /// Layout of the frames sent to the frontend (`Rgba` by default); applies
+    /// from the next decoded frame
+    pub fn set_output_pixel_format(&mut self, format: PixelFormat) -> Result<()> {
+        self.decoder.set_output_pixel_format(format)?;
+        self.output_pixel_format = format;
+        Ok(())
+    }
+
+    /// Audio language (e.g. "eng") to open multi-language files with; `None` uses
+    /// the default track. Applies from the next load.
+    pub fn set_preferred_audio_language(&mut self, language: Option<String>) -> Result<()> {
+        let language = language
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty());
+        self.preferred_audio_language = language.clone();
+        self.decoder.set_preferred_audio_language(language)
+    }
+
+    /// The settings that persist across sessions, as currently in use
+    pub fn settings(&self) -> PlayerSettings {
+        PlayerSettings {
+            volume: self.volume,
+            volume_steps: self.volume_steps,
+            effects: self.effects.clone(),
+            auto_balance: self.auto_balance,
+            mono_output: self.mono_output,
+            preferred_audio_language: self.preferred_audio_language.clone(),
+            seek_mode: self.seek_mode,
+            buffer_ahead: self.buffer_ahead,
+            av_offset_ms: self.av_offset_ms,
+            tone_mapping: self.tone_mapping,
+            video_adjustments: self.video_adjustments,
+            keep_device_warm: self.keep_device_warm,
+            realtime_audio: self.realtime_audio,
+            end_behavior: self.end_behavior,
+            output_device: self.output_device.clone(),
+            remembered_speeds: self.remembered_speeds.clone(),
+        }
+    }
+
+    /// Apply saved settings; returns them as actually in use (clamped)
+    pub fn apply_settings(&mut self, settings: &PlayerSettings) -> Result<PlayerSettings> {
+        self.set_volume(settings.volume);
+        self.set_volume_steps(settings.volume_steps)?;
+        self.set_effects(settings.effects.clone())?;
+        self.set_auto_balance(settings.auto_balance)?;
+        self.set_mono_output(settings.mono_output)?;
+        self.set_preferred_audio_language(settings.preferred_audio_language.clone())?;
+        self.set_seek_mode(settings.seek_mode)?;
+        self.set_buffer_ahead(settings.buffer_ahead)?;
+        self.set_av_offset(settings.av_offset_ms)?;
+        self.set_tone_mapping(settings.tone_mapping)?;
+        self.set_video_adjustments(settings.video_adjustments)?;
+        self.set_keep_device_warm(settings.keep_device_warm);
+        self.set_realtime_audio(settings.realtime_audio)?;
+        self.set_end_behavior(settings.end_behavior);
+        self.set_output_device(settings.output_device.clone())?;
+        self.remembered_speeds = settings.remembered_speeds.clone();
+        Ok(self.settings())
+    }
+
+    /// Switch to the next audio track of the loaded file, wrapping around
+    pub fn cycle_audio_track(&mut self) -> Result<AudioTrack> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        let position = self.position();
+        let track = self.decoder.cycle_audio_track()?;
+        self.current_time = position;
+        self.has_audio = true;
+        self.ready.audio = StreamStatus::Ready;
+        Ok(track)
+    }
+
+    /// Video streams of the loaded file, cover art excluded
+    pub fn get_video_tracks(&self) -> Vec<VideoTrack> {
+        let Some(ref info) = self.media_info else {
+            return Vec::new();
+        };
+        info.streams
+            .iter()
+            .filter(|stream| self.video_streams.contains(&stream.index))
+            .map(|stream| VideoTrack {
+                stream_index: stream.index,
+                language: stream.language.clone(),
+                title: stream.title.clone(),
+                codec: stream.codec.clone(),
+                width: stream.width.unwrap_or(0),
+                height: stream.height.unwrap_or(0),
+                frame_rate: stream.frame_rate,
+                active: self.video_stream_index == Some(stream.index),
+            })
+            .collect()
+    }
+
+    /// Play video stream `index` of the loaded file, keeping the position
+    pub fn set_video_track(&mut self, index: usize) -> Result<VideoTrack> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        let tracks = self.get_video_tracks();
+        let Some(track) = tracks.into_iter().find(|t| t.stream_index == index) else {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Stream {} is not a video track",
+                index
+            ))
+            .into());
+        };
+        if track.active {
+            return Ok(track);
+        }
+
+        let position = self.position();
+        let video = self.decoder.set_video_track(index)?;
+        self.current_time = position;
+        self.hdr = video.hdr;
+        self.video_bit_depth = video.bit_depth;
+        self.color = video.color;
+        self.slow_motion_factor = video.slow_motion_factor;
+        self.video_stream_index = Some(index);
+        self.has_video = true;
+        self.ready.video = StreamStatus::Ready;
+        if self.state != PlaybackState::Playing {
+            self.decoder.show_frame(false)?;
+        }
+        Ok(VideoTrack {
+            active: true,
+            ..track
+        })
+    }
+
+    /// Register a custom DSP stage (see `DspHook` for its constraints)
+    pub fn add_dsp_hook(&mut self, hook: Box<dyn DspHook>) -> Result<()> {
+        self.decoder.add_dsp_hook(hook)
+    }
+
+    /// Set preamp, balance and equalizer gains; returns the (clamped) settings in use
+    pub fn set_effects(&mut self, effects: AudioEffects) -> Result<AudioEffects> {
+        let effects = effects.clamped();
+        self.decoder.set_effects(effects.clone())?;
+        self.effects = effects.clone();
+        Ok(effects)
+    }
+
+    pub fn effects(&self) -> AudioEffects {
+        self.effects.clone()
+    }
+
+    /// Play the sum of both channels (at -3 dB each) on both speakers, after
+    /// the effects and balance; the device keeps its channel count
+    pub fn set_mono_output(&mut self, enabled: bool) -> Result<()> {
+        self.mono_output = enabled;
+        self.decoder.set_mono_output(enabled)
+    }
+
+    /// Watch the output, after volume, effects and DSP hooks, for samples at
+    /// full scale and for peaks between samples over it (off by default);
+    /// overs raise `clipping-detected` and are counted in `get_stats`
+    pub fn set_clip_detection(&mut self, enabled: bool) -> Result<()> {
+        self.decoder.set_clip_detection(enabled)
+    }
+
+    /// Route the audio to the device, a WAV file, or both ("record what's
+    /// playing"). The file gets the samples the device would play, after
+    /// volume and effects, as 32-bit float at the output rate; with `File`
+    /// alone the device plays silence so playback keeps its pace.
+    ///
+    /// Recording ends (and the file is finalized) on `Device`, on the next
+    /// sink, on stop or load, and on its own if writing fails or the output
+    /// rate changes, each raising `RecordingStopped`
+    pub fn set_output_sink(&mut self, sink: OutputSink) -> Result<()> {
+        let file = match sink.path() {
+            Some(_) if self.file_path.is_none() => return Err(PlayerError::NotLoaded.into()),
+            Some(path) => Some(
+                FileSink::create(
+                    path,
+                    self.output_sample_rate,
+                    OUTPUT_CHANNELS,
+                    self.event_sender(),
+                )
+                .map_err(PlayerError::from)?,
+            ),
+            None => None,
+        };
+        self.decoder.set_output_sink(file, sink.plays_on_device())?;
+        self.output_sink = sink;
+        Ok(())
+    }
+
+    /// The recording to `path` ended by itself; audio is back on the device
+    pub fn recording_stopped(&mut self, path: &str) {
+        if self.output_sink.path() == Some(path) {
+            self.output_sink = OutputSink::Device;
+        }
+    }
+
+    /// Karaoke mode: remove the center of the mix, where the lead vocal
+    /// usually sits. Sources with a center channel have it muted; stereo
+    /// sources play left minus right. Mono sources are left alone
+    pub fn set_vocal_removal(&mut self, enabled: bool) -> Result<()> {
+        self.decoder.set_vocal_removal(enabled)
+    }
+
+    /// Silence source channels before they are downmixed to stereo; bit 0 of
+    /// `mask` is the first channel of the source layout (left for stereo)
+    pub fn set_channel_mute(&mut self, mask: u64) -> Result<()> {
+        self.decoder.set_channel_mute(mask)?;
+        self.channel_mute = mask;
+        Ok(())
+    }
+
+    /// Match the levels of the stereo channels when one is recorded louder.
+    /// Measured over the first seconds of each file and applied before the
+    /// manual balance; the correction shows up in `get_stats`
+    pub fn set_auto_balance(&mut self, enabled: bool) -> Result<()> {
+        self.auto_balance = enabled;
+        self.decoder.set_auto_balance(enabled)
+    }
+
+    /// Return preamp, balance, equalizer, auto-balance, mono output, vocal
+    /// removal and channel mutes to neutral in one step; raises
+    /// `AudioEffectsChanged`. Volume and DSP hooks are left as they are
+    pub fn reset_audio_effects(&mut self) -> Result<AudioEffects> {
+        self.decoder.reset_audio_effects()?;
+        self.effects = AudioEffects::default();
+        self.auto_balance = false;
+        self.mono_output = false;
+        self.channel_mute = 0;
+        let _ = self.event_sender().send(PlayerEvent::AudioEffectsChanged {
+            effects: self.effects.clone(),
+            auto_balance: false,
+            mono_output: false,
+            vocal_removal: false,
+            channel_mute: 0,
+        });
+        Ok(self.effects.clone())
+    }
+
+    /// Name of the device audio plays on (the device it would open on when
+    /// nothing is open)
+    pub fn current_output_device(&self) -> Option<String> {
+        match self.audio_output {
+            Some(ref output) => Some(output.device_name().to_string()),
+            None => self
+                .connected_output_device()
+                .or_else(|| self.output_devices.default_device.clone()),
+        }
+    }
+
+    /// Record a device scan, returning whether anything changed.
+    ///
+    /// Changes raise `DevicesChanged`. The open output moves to the device it
+    /// should play on when that changes: the chosen device when it is plugged
+    /// in again, else the default, so playback follows the system setting.
+    pub fn update_output_devices(&mut self, devices: OutputDevices) -> Result<bool> {
+        if devices == self.output_devices {
+            return Ok(false);
+        }
+        let _ = self.event_sender().send(PlayerEvent::DevicesChanged {
+            devices: devices.devices.clone(),
+            default_device: devices.default_device.clone(),
+        });
+
+        let was_connected = self.connected_output_device();
+        self.output_devices = devices;
+        if let Some(name) = was_connected.filter(|_| self.connected_output_device().is_none()) {
+            let _ = self.event_sender().send(PlayerEvent::Warning {
+                message: format!(
+                    "Output device {} was removed, using the default device",
+                    name
+                ),
+            });
+        }
+        let target = self
+            .connected_output_device()
+            .or_else(|| self.output_devices.default_device.clone());
+        let follow = self
+            .audio_output
+            .as_ref()
+            .is_some_and(|output| target.as_deref().is_some_and(|d| d != output.device_name()));
+        // A device appeared while the loaded file is playing without sound
+        let reconnect =
+            self.audio_output.is_none() && self.sample_receiver.is_some() && target.is_some();
+        if follow {
+            if let Some(output) = self.audio_output.take() {
+                output.stop();
+            }
+            self.open_audio_output()?;
+        } else if reconnect {
+            let _ = self.retry_audio_output();
+        }
+        Ok(true)
+    }
+
+    /// Configure how network sources recover from read errors
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) -> Result<()> {
+        self.decoder.set_reconnect_policy(policy)
+    }
+
+    /// Receiver for events to forward to the frontend
+    pub fn events(&self) -> Receiver<PlayerEvent> {
+        self.decoder.events()
+    }
+
+    /// Sender for events raised by background jobs
+    pub fn event_sender(&self) -> Sender<PlayerEvent> {
+        self.decoder.event_sender()
+    }
+
+    /// Intended display size of the video (zero for audio-only files)
+    pub fn display_dimensions(&self) -> Result<DisplayDimensions> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        if !self.has_video {
+            return Ok(DisplayDimensions::default());
+        }
+        Ok(self.decoder.display_dimensions())
+    }
+
+    /// Get playback diagnostics
+    pub fn get_stats(&self) -> PlaybackStats {
+        PlaybackStats {
+            sync: self.decoder.sync_stats(),
+            output_sample_rate: self.output_sample_rate,
+            buffer_ahead: self.buffer_ahead,
+            max_fps: self.max_fps,
+            passthrough: self.passthrough.clone(),
+            active_decode_backend: self.decoder.decode_backend(),
+            auto_balance: self.decoder.auto_balance(),
+            load_timings: self.decoder.load_timings(),
+            io: self.io,
+            clipping: self.decoder.clip_stats(),
+            queues: self.decoder.queue_depth(),
+        }
+    }
+
+    /// Bytes downloaded so far for a network source
+    pub fn network_stats(&self) -> Result<NetworkStats> {
+        let Some(ref path) = self.file_path else {
+            return Err(PlayerError::NotLoaded.into());
+        };
+        if !is_network_source(path) {
+            return Err(
+                PlayerError::Unsupported(format!("{} is not a network source", path)).into(),
+            );
+        }
+        Ok(self.decoder.network_stats().unwrap_or_default())
+    }
+
+    /// Container and stream properties of the loaded file
+    pub fn media_info(&self) -> Result<MediaInfo> {
+        let mut info = self.media_info.clone().ok_or(PlayerError::NotLoaded)?;
+        info.active_decode_backend = self.decoder.decode_backend();
+        info.deinterlacing = self.decoder.deinterlacing();
+        info.load_timings = Some(self.decoder.load_timings());
+        Ok(info)
+    }
+
+    /// Fonts the loaded file embeds for styled subtitles (empty if none)
+    pub fn subtitle_fonts(&self) -> Result<Vec<SubtitleFont>> {
+        if self.file_path.is_none() {
+            return Err(PlayerError::NotLoaded.into());
+        }
+        Ok(self.subtitle_fonts.clone())
+    }
+
     /// Get current status
     pub fn get_status(&self) -> PlayerStatus {
+        let current_time = self.position();
+        // Follows resolution changes in the middle of the stream
+        let (video_width, video_height) = self.decoder.video_size();
         PlayerStatus {
             is_playing: self.state == PlaybackState::Playing,
-            current_time: self.current_time,
+            current_time,
             duration: self.duration,
+            current_time_text: format_time(current_time),
+            duration_text: format_duration(self.duration),
             volume: self.volume,
             file_path: self.file_path.clone(),
             has_video: self.has_video,
             has_audio: self.has_audio,
-            video_width: self.video_width,
-            video_height: self.video_height,
+            video_width,
+            video_height,
+            has_cover_art: self.has_cover_art,
+            hdr: self.hdr,
+            video_bit_depth: self.video_bit_depth,
+            color: self.color,
+            slow_motion_factor: self.slow_motion_factor,
+            real_time_motion: self.real_time_motion,
+            playback_speed: self.speed,
+            ready: self.ready.clone(),
+            video_error: match self.ready.video {
+                StreamStatus::Failed(ref reason) => Some(reason.clone()),
+                _ => None,
+            },
+            live_mode: self.live_active,
+            seek_mode: self.seek_mode,
+            audio_output: self.audio_output.is_some(),
+            output_sink: self.output_sink.clone(),
+            mono_output: self.mono_output,
+            vocal_removal: self.decoder.vocal_removal(),
+            channel_mute: self.channel_mute,
+            video_adjustments: self.video_adjustments,
+            letterbox: self.letterbox,
+            output_pixel_format: self.output_pixel_format,
+            loop_region: self.loop_region,
+            cache: self.decoder.cache_status(),
         }
     }
 
@@ -194,3 +1860,178 @@ impl Default for MediaPlayer {
 
 // Type alias for backward compatibility
 pub type AudioPlayer = MediaPlayer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error<T: std::fmt::Debug>(result: Result<T>) -> PlayerError {
+        PlayerError::from(result.unwrap_err())
+    }
+
+    #[test]
+    fn cover_art_needs_a_file_with_an_attached_picture() {
+        let mut player = MediaPlayer::new();
+        assert_eq!(error(player.cover_art_path()), PlayerError::NotLoaded);
+
+        player.file_path = Some("song.mp3".to_string());
+        assert!(matches!(
+            error(player.cover_art_path()),
+            PlayerError::Unsupported(_)
+        ));
+        player.has_cover_art = true;
+        assert_eq!(player.cover_art_path().unwrap(), "song.mp3");
+    }
+
+    #[test]
+    fn a_missing_audio_device_keeps_the_file_playing_without_sound() {
+        let mut player = MediaPlayer::new();
+        let events = player.events();
+        let retried = player.retry_audio_output().map(|_| ());
+        assert_eq!(error(retried), PlayerError::NotLoaded);
+
+        player.file_path = Some("clip.mp4".to_string());
+        player.has_audio = true;
+        let no_device = PlayerError::AudioOutput("No output device available".to_string());
+        player.audio_output_opened(Err(no_device.into())).unwrap();
+
+        let status = player.get_status();
+        assert!(!status.audio_output);
+        assert!(matches!(status.ready.audio, StreamStatus::Failed(_)));
+        assert!(events
+            .try_iter()
+            .any(|event| matches!(event, PlayerEvent::Warning { .. })));
+        // Nothing was set up to retry with: the call changes nothing
+        assert!(!player.retry_audio_output().unwrap().audio_output);
+    }
+
+    #[test]
+    fn a_video_stream_that_failed_reports_why() {
+        let mut player = MediaPlayer::new();
+        assert_eq!(player.get_status().video_error, None);
+        let reason = "Unsupported codec AV1, not built into this FFmpeg".to_string();
+        player.ready.video = StreamStatus::Failed(reason.clone());
+        assert_eq!(player.get_status().video_error, Some(reason));
+    }
+
+    fn devices(devices: &[&str]) -> OutputDevices {
+        OutputDevices {
+            devices: devices.iter().map(|name| name.to_string()).collect(),
+            default_device: Some("Speakers".to_string()),
+        }
+    }
+
+    fn warned(events: &Receiver<PlayerEvent>) -> bool {
+        events
+            .try_iter()
+            .any(|event| matches!(event, PlayerEvent::Warning { .. }))
+    }
+
+    #[test]
+    fn the_saved_output_device_is_selected_again() {
+        let mut player = MediaPlayer::new();
+        let events = player.events();
+        player.output_devices = devices(&["Speakers", "USB DAC"]);
+        let settings = PlayerSettings {
+            output_device: Some("USB DAC".to_string()),
+            ..PlayerSettings::default()
+        };
+        let applied = player.apply_settings(&settings).unwrap();
+        assert_eq!(applied.output_device.as_deref(), Some("USB DAC"));
+        assert_eq!(player.current_output_device().as_deref(), Some("USB DAC"));
+        assert!(!warned(&events));
+
+        player.set_output_device(None).unwrap();
+        assert_eq!(player.current_output_device().as_deref(), Some("Speakers"));
+        assert_eq!(player.settings().output_device, None);
+    }
+
+    #[test]
+    fn a_missing_output_device_falls_back_to_the_default() {
+        let mut player = MediaPlayer::new();
+        let events = player.events();
+        player.output_devices = devices(&["Speakers"]);
+        let settings = PlayerSettings {
+            output_device: Some("USB DAC".to_string()),
+            ..PlayerSettings::default()
+        };
+        let applied = player.apply_settings(&settings).unwrap();
+        assert_eq!(player.current_output_device().as_deref(), Some("Speakers"));
+        assert!(warned(&events));
+        // The choice is kept for when the device is back
+        assert_eq!(applied.output_device.as_deref(), Some("USB DAC"));
+        player
+            .update_output_devices(devices(&["Speakers", "USB DAC"]))
+            .unwrap();
+        assert_eq!(player.current_output_device().as_deref(), Some("USB DAC"));
+
+        player
+            .update_output_devices(devices(&["Speakers"]))
+            .unwrap();
+        assert_eq!(player.current_output_device().as_deref(), Some("Speakers"));
+        assert!(warned(&events));
+    }
+
+    #[test]
+    fn a_remembered_file_loads_at_its_speed() {
+        let mut player = MediaPlayer::new();
+        let settings = PlayerSettings {
+            remembered_speeds: RememberedSpeeds {
+                mode: SpeedMemory::PerFile,
+                ..RememberedSpeeds::default()
+            },
+            ..PlayerSettings::default()
+        };
+        player.apply_settings(&settings).unwrap();
+        player.file_path = Some("lecture.mp4".to_string());
+        player.duration = 3600.0;
+        assert_eq!(player.set_playback_speed(1.5).unwrap(), 1.5);
+        assert_eq!(player.get_status().playback_speed, 1.5);
+
+        // The next session starts from the saved settings
+        let mut next = MediaPlayer::new();
+        next.apply_settings(&player.settings()).unwrap();
+        assert_eq!(next.speed_for_load("lecture.mp4"), 1.5);
+        assert_eq!(next.speed_for_load("other.mp4"), 1.0);
+        next.live_active = true;
+        assert_eq!(next.speed_for_load("lecture.mp4"), 1.0);
+    }
+
+    #[test]
+    fn playback_speeds_are_checked() {
+        let mut player = MediaPlayer::new();
+        assert_eq!(player.set_playback_speed(3.0).unwrap(), 2.0);
+        assert!(matches!(
+            error(player.set_playback_speed(f64::NAN)),
+            PlayerError::InvalidArgument(_)
+        ));
+
+        player.live_active = true;
+        assert!(matches!(
+            error(player.set_playback_speed(1.5)),
+            PlayerError::Unsupported(_)
+        ));
+        assert_eq!(player.set_playback_speed(1.0).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn seek_returns_where_the_decoder_landed() {
+        let mut player = MediaPlayer::new();
+        assert_eq!(error(player.seek(1.0)), PlayerError::NotLoaded);
+
+        player.file_path = Some("clip.mp4".to_string());
+        player.duration = 10.0;
+        assert_eq!(player.seek(4.0).unwrap(), 4.0);
+        // Past the end of a loop the decoder restarts it
+        let region = LoopRegion {
+            start: 2.0,
+            end: 6.0,
+        };
+        player.decoder.set_loop(Some(region)).unwrap();
+        assert_eq!(player.seek(7.0).unwrap(), 2.0);
+        assert_eq!(player.current_time, 2.0);
+        // Targets beyond the file are clamped before they are sent
+        player.decoder.set_loop(None).unwrap();
+        assert_eq!(player.seek(25.0).unwrap(), 10.0);
+    }
+}
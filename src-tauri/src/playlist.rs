@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+/// A playlist item: a whole file, or a section of one (a cue sheet track)
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlaylistEntry {
+    pub path: String,
+    pub title: Option<String>,
+    /// In and out points in seconds; `None` plays from the start / to the end
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl PlaylistEntry {
+    /// An entry playing all of `path`
+    pub fn file(path: String) -> Self {
+        Self {
+            path,
+            title: None,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// Files queued for playback, in order, and the entry currently loaded
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Playlist {
+    items: Vec<PlaylistEntry>,
+    /// Index into `items`; `None` when the loaded file is not in the playlist
+    current: Option<usize>,
+}
+
+impl Playlist {
+    /// Append `entries`; returns the index of the first one
+    pub fn append(&mut self, entries: Vec<PlaylistEntry>) -> usize {
+        let first = self.items.len();
+        self.items.extend(entries);
+        first
+    }
+
+    /// Mark the entry for `path` as current, or none if it is not queued
+    pub fn select(&mut self, path: &str) {
+        if self.current.is_some_and(|i| self.items[i].path == path) {
+            return;
+        }
+        self.current = self.items.iter().position(|item| item.path == path);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PlaylistEntry> {
+        self.items.get(index)
+    }
+
+    /// Mark entry `index` as current (it was loaded by position, so duplicates
+    /// of the same path are told apart)
+    pub fn set_current(&mut self, index: usize) {
+        self.current = (index < self.items.len()).then_some(index);
+    }
+
+    /// Index and entry `offset` places from the current one (the first entry
+    /// when nothing queued is loaded yet)
+    pub fn adjacent(&self, offset: isize) -> Option<(usize, PlaylistEntry)> {
+        let index = match self.current {
+            Some(current) => current.checked_add_signed(offset)?,
+            None => 0,
+        };
+        self.items.get(index).map(|entry| (index, entry.clone()))
+    }
+}
@@ -0,0 +1,92 @@
+use crate::effects::AudioEffects;
+use crate::error::PlayerError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Named effect presets and per-file/per-folder defaults, persisted as JSON
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetStore {
+    presets: HashMap<String, AudioEffects>,
+    /// File or folder path -> preset name
+    defaults: HashMap<String, String>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
+}
+
+impl PresetStore {
+    /// Load the store from `file`, starting empty if it is missing or unreadable
+    pub fn load(file: PathBuf) -> Self {
+        let mut store = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|json| serde_json::from_str::<PresetStore>(&json).ok())
+            .unwrap_or_default();
+        store.file = Some(file);
+        store
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(ref file) = self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Names of all saved presets, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.presets.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn get(&self, name: &str) -> Result<AudioEffects> {
+        self.presets
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PlayerError::InvalidArgument(format!("Unknown preset: {}", name)).into())
+    }
+
+    /// Save (or overwrite) a named preset
+    pub fn save_preset(&mut self, name: &str, settings: AudioEffects) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(PlayerError::InvalidArgument("Preset name is empty".to_string()).into());
+        }
+        self.presets.insert(name.to_string(), settings.clamped());
+        self.save()
+    }
+
+    /// Associate a preset with a file or folder; `None` removes the association
+    pub fn set_default_for(&mut self, path: &str, name: Option<&str>) -> Result<()> {
+        // Folders match by their path without a trailing separator
+        let key = match path.trim_end_matches(['/', '\\']) {
+            "" => path,
+            trimmed => trimmed,
+        };
+        match name {
+            Some(name) => {
+                self.get(name)?;
+                self.defaults.insert(key.to_string(), name.to_string());
+            }
+            None => {
+                self.defaults.remove(key);
+            }
+        }
+        self.save()
+    }
+
+    /// Preset for `path`: its own association, else the nearest enclosing folder's
+    pub fn default_for(&self, path: &str) -> Option<AudioEffects> {
+        Path::new(path)
+            .ancestors()
+            .filter_map(|p| p.to_str())
+            .find_map(|p| self.defaults.get(p))
+            .and_then(|name| self.presets.get(name))
+            .cloned()
+    }
+}
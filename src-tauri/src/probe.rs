@@ -0,0 +1,196 @@
+use crate::decoder::{is_cover_art, media_start_time, open_probed, DecodeBackend, LoadTimings};
+use crate::error::PlayerError;
+use crate::thumbnail::{read_chapters, ChapterPreview};
+use crate::timebase::micros_to_seconds;
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Tag slow-motion recordings carry with their capture rate (Android cameras)
+const CAPTURE_FPS_TAG: &str = "com.android.capture.fps";
+
+/// Properties of a media file, as opposed to the playback state
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MediaInfo {
+    pub file_path: String,
+    /// Container format detected from the content (FFmpeg short name, e.g. "matroska,webm")
+    pub format_name: String,
+    /// Human-readable container name
+    pub format_description: String,
+    pub duration: f64,
+    /// Container tags such as title, artist and album
+    pub metadata: BTreeMap<String, String>,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<ChapterPreview>,
+    /// What decodes the video right now (`None` without video or when probed)
+    pub active_decode_backend: Option<DecodeBackend>,
+    /// Frames are being deinterlaced (see `set_deinterlace`)
+    pub deinterlacing: bool,
+    /// How long the loaded file took to open and to decode its first outputs
+    /// (`None` when probed)
+    pub load_timings: Option<LoadTimings>,
+}
+
+/// One stream of a media file, from its codec parameters
+#[derive(Clone, Debug, Serialize)]
+pub struct StreamInfo {
+    pub index: usize,
+    /// "audio", "video", "subtitle", "data", "attachment" or "unknown"
+    pub kind: &'static str,
+    pub codec: String,
+    /// ISO 639 code from the stream metadata
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Audio only
+    pub channels: Option<u16>,
+    pub sample_rate: Option<u32>,
+    /// Video only
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Frames per second the timestamps play at on average (nominal rate)
+    pub frame_rate: Option<f64>,
+    /// Lowest rate all timestamps fit on (FFmpeg's `r_frame_rate`); differs
+    /// from `frame_rate` for variable frame rate video
+    pub real_frame_rate: Option<f64>,
+    /// Rate the camera recorded at, for slow-motion files that declare it
+    pub capture_frame_rate: Option<f64>,
+    /// A video stream that is embedded cover art rather than playable video
+    pub cover_art: bool,
+}
+
+/// Describe an opened input without decoding anything
+pub fn describe(input: &ffmpeg::format::context::Input, path: &str) -> MediaInfo {
+    let metadata = input
+        .metadata()
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    let streams = input
+        .streams()
+        .map(|stream| {
+            let parameters = stream.parameters();
+            let medium = parameters.medium();
+            let (channels, sample_rate, width, height) = unsafe {
+                let p = parameters.as_ptr();
+                (
+                    (*p).ch_layout.nb_channels,
+                    (*p).sample_rate,
+                    (*p).width,
+                    (*p).height,
+                )
+            };
+            let audio = medium == ffmpeg::media::Type::Audio;
+            let video = medium == ffmpeg::media::Type::Video;
+            let tags = stream.metadata();
+            StreamInfo {
+                index: stream.index(),
+                kind: match medium {
+                    ffmpeg::media::Type::Audio => "audio",
+                    ffmpeg::media::Type::Video => "video",
+                    ffmpeg::media::Type::Subtitle => "subtitle",
+                    ffmpeg::media::Type::Data => "data",
+                    ffmpeg::media::Type::Attachment => "attachment",
+                    ffmpeg::media::Type::Unknown => "unknown",
+                },
+                codec: parameters.id().name().to_string(),
+                language: tags.get("language").map(str::to_string),
+                title: tags.get("title").map(str::to_string),
+                channels: audio.then_some(channels.max(0) as u16),
+                sample_rate: audio.then_some(sample_rate.max(0) as u32),
+                width: video.then_some(width.max(0) as u32),
+                height: video.then_some(height.max(0) as u32),
+                frame_rate: rational_rate(stream.avg_frame_rate()).filter(|_| video),
+                real_frame_rate: rational_rate(stream.rate()).filter(|_| video),
+                capture_frame_rate: capture_frame_rate(input, &stream).filter(|_| video),
+                cover_art: video && is_cover_art(&stream),
+            }
+        })
+        .collect();
+
+    MediaInfo {
+        file_path: path.to_string(),
+        format_name: input.format().name().to_string(),
+        format_description: input.format().description().to_string(),
+        duration: micros_to_seconds(input.duration()).max(0.0),
+        metadata,
+        streams,
+        chapters: read_chapters(input, media_start_time(input)),
+        active_decode_backend: None,
+        deinterlacing: false,
+        load_timings: None,
+    }
+}
+
+/// A frame rate, `None` if unknown (FFmpeg reports 0/0 or 0/1)
+pub fn rational_rate(rate: ffmpeg::Rational) -> Option<f64> {
+    (rate.numerator() > 0 && rate.denominator() > 0).then(|| f64::from(rate))
+}
+
+/// Capture rate a slow-motion recording declares on the stream or container
+pub fn capture_frame_rate(
+    input: &ffmpeg::format::context::Input,
+    stream: &ffmpeg::format::stream::Stream,
+) -> Option<f64> {
+    let tag = stream.metadata().get(CAPTURE_FPS_TAG).map(str::to_owned);
+    tag.or_else(|| input.metadata().get(CAPTURE_FPS_TAG).map(str::to_owned))
+        .and_then(|fps| fps.trim().parse::<f64>().ok())
+        .filter(|fps| fps.is_finite() && *fps > 0.0)
+}
+
+/// Open `path`, describe it and close it again, without touching any player
+pub fn probe(path: &str) -> Result<MediaInfo, PlayerError> {
+    let _ = ffmpeg::init();
+    let input = open_probed(path)?;
+    Ok(describe(&input, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_rates_are_none() {
+        assert_eq!(rational_rate(ffmpeg::Rational(25, 1)), Some(25.0));
+        let ntsc = rational_rate(ffmpeg::Rational(30000, 1001)).unwrap();
+        assert!((ntsc - 29.97).abs() < 0.001);
+        assert_eq!(rational_rate(ffmpeg::Rational(0, 0)), None);
+        assert_eq!(rational_rate(ffmpeg::Rational(0, 1)), None);
+        assert_eq!(rational_rate(ffmpeg::Rational(30, 0)), None);
+    }
+
+    /// 0.1 s of 16-bit mono silence at 8 kHz as a WAV file
+    fn wav_bytes() -> Vec<u8> {
+        let data_len: u32 = 800 * 2;
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes()); // PCM
+        wav.extend(1u16.to_le_bytes()); // mono
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend((8000u32 * 2).to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        wav
+    }
+
+    #[test]
+    fn a_misnamed_file_opens_with_its_detected_format() {
+        let path =
+            std::env::temp_dir().join(format!("wenplayer-misnamed-{}.mp4", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, wav_bytes()).unwrap();
+
+        let info = probe(path);
+        std::fs::remove_file(path).unwrap();
+        let info = info.unwrap();
+        assert_eq!(info.format_name, "wav");
+        assert_eq!(info.streams.len(), 1);
+        assert_eq!(info.streams[0].sample_rate, Some(8000));
+        assert_eq!(info.streams[0].channels, Some(1));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::events::PlayerEvent;
+use crate::export::{ExportFormat, WavWriter};
+use anyhow::Result;
+use crossbeam_channel::{unbounded, Sender};
+use serde::{Deserialize, Serialize};
+use std::thread::JoinHandle;
+
+/// Audio data a WAV file can hold (its sizes are 32-bit), less the header
+const MAX_RECORDING_BYTES: u64 = u32::MAX as u64 - 64;
+
+/// Where the played audio goes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum OutputSink {
+    /// The output device only
+    #[default]
+    Device,
+    /// A WAV file only; the device plays silence to keep the playback pace
+    File(String),
+    /// The device and a WAV file
+    Both(String),
+}
+
+impl OutputSink {
+    /// Path of the recording, if the audio is recorded
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            OutputSink::Device => None,
+            OutputSink::File(path) | OutputSink::Both(path) => Some(path),
+        }
+    }
+
+    /// Whether the device plays the audio (rather than silence)
+    pub fn plays_on_device(&self) -> bool {
+        !matches!(self, OutputSink::File(_))
+    }
+}
+
+enum SinkMessage {
+    Samples(Vec<f32>),
+    /// Stop recording for this reason, keeping what was written
+    Abort(String),
+}
+
+/// Records interleaved output samples to a 32-bit float WAV file.
+///
+/// Samples are written on a dedicated thread, so a slow disk never stalls
+/// decoding. The file is finalized when the sink is dropped, aborted, or a
+/// write fails (disk full); `RecordingStopped` reports which.
+pub struct FileSink {
+    sender: Option<Sender<SinkMessage>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileSink {
+    /// Create the file (errors such as a missing directory or a denied
+    /// permission are returned here) and start the writer thread
+    pub fn create(
+        path: &str,
+        rate: u32,
+        channels: u16,
+        events: Sender<PlayerEvent>,
+    ) -> Result<Self> {
+        let mut writer = WavWriter::create(path, rate, channels, ExportFormat::Float32)?;
+        let (sender, receiver) = unbounded::<SinkMessage>();
+        let path = path.to_string();
+
+        let thread = std::thread::spawn(move || {
+            let mut written = 0u64;
+            let mut error = None;
+            while let Ok(message) = receiver.recv() {
+                let samples = match message {
+                    SinkMessage::Samples(samples) => samples,
+                    SinkMessage::Abort(reason) => {
+                        error = Some(reason);
+                        break;
+                    }
+                };
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                if written + bytes.len() as u64 > MAX_RECORDING_BYTES {
+                    error = Some("The recording reached the 4 GB WAV limit".to_string());
+                    break;
+                }
+                if let Err(e) = writer.write(&bytes) {
+                    error = Some(format!("Cannot write the recording: {}", e));
+                    break;
+                }
+                written += bytes.len() as u64;
+            }
+
+            // Patch the header even after an error, so the file plays up to there
+            if let Err(e) = writer.finish() {
+                error.get_or_insert_with(|| format!("Cannot finish the recording: {}", e));
+            }
+            if let Some(ref error) = error {
+                eprintln!("Recording to {} stopped: {}", path, error);
+            }
+            let _ = events.send(PlayerEvent::RecordingStopped { path, error });
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queue samples for the file; false once the writer has stopped
+    pub fn write(&self, samples: &[f32]) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(SinkMessage::Samples(samples.to_vec())).is_ok())
+    }
+
+    /// Stop recording, reporting `reason` as the error
+    pub fn abort(self, reason: &str) {
+        if let Some(ref sender) = self.sender {
+            let _ = sender.send(SinkMessage::Abort(reason.to_string()));
+        }
+    }
+}
+
+impl Drop for FileSink {
+    /// Write out the queued samples and finalize the file
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
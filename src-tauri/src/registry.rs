@@ -0,0 +1,132 @@
+use crate::error::PlayerError;
+use crate::player::MediaPlayer;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Identifies a player instance in commands and events
+pub type PlayerId = u32;
+
+/// The player that exists from startup; commands without an id address it
+pub const MAIN_PLAYER: PlayerId = 0;
+
+/// Handle to one player instance
+pub type PlayerHandle = Arc<Mutex<MediaPlayer>>;
+
+/// Independent player instances keyed by id.
+///
+/// Each `MediaPlayer` owns its decoder and audio threads; removing an instance
+/// stops it and drops its handle, which ends those threads without touching the
+/// other players.
+pub struct PlayerRegistry {
+    players: Mutex<HashMap<PlayerId, PlayerHandle>>,
+    next_id: Mutex<PlayerId>,
+}
+
+impl PlayerRegistry {
+    pub fn new(main: MediaPlayer) -> Self {
+        let mut players = HashMap::new();
+        players.insert(MAIN_PLAYER, Arc::new(Mutex::new(main)));
+        Self {
+            players: Mutex::new(players),
+            next_id: Mutex::new(MAIN_PLAYER + 1),
+        }
+    }
+
+    /// Add a player and return its id
+    pub fn insert(&self, player: MediaPlayer) -> PlayerId {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            let id = *next_id;
+            *next_id += 1;
+            id
+        };
+        self.players
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(Mutex::new(player)));
+        id
+    }
+
+    /// The player with `id`, or the main player when `id` is `None`
+    pub fn get(&self, id: Option<PlayerId>) -> Result<PlayerHandle, PlayerError> {
+        let id = id.unwrap_or(MAIN_PLAYER);
+        self.players
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| PlayerError::InvalidArgument(format!("Unknown player {}", id)))
+    }
+
+    /// Stop and remove a player; the main player cannot be removed
+    pub fn remove(&self, id: PlayerId) -> Result<(), PlayerError> {
+        if id == MAIN_PLAYER {
+            return Err(PlayerError::InvalidArgument(
+                "The main player cannot be destroyed".to_string(),
+            ));
+        }
+        let player = self
+            .players
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| PlayerError::InvalidArgument(format!("Unknown player {}", id)))?;
+        player.lock().unwrap().stop();
+        Ok(())
+    }
+
+    /// Every player, for work that applies to all of them (device hotplug)
+    pub fn all(&self) -> Vec<PlayerHandle> {
+        self.players.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> PlayerRegistry {
+        PlayerRegistry::new(MediaPlayer::new())
+    }
+
+    #[test]
+    fn ids_count_up_from_after_the_main_player() {
+        let players = registry();
+        assert_eq!(players.insert(MediaPlayer::new()), MAIN_PLAYER + 1);
+        assert_eq!(players.insert(MediaPlayer::new()), MAIN_PLAYER + 2);
+        assert_eq!(players.all().len(), 3);
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_removal() {
+        let players = registry();
+        let first = players.insert(MediaPlayer::new());
+        players.remove(first).unwrap();
+        assert_ne!(players.insert(MediaPlayer::new()), first);
+    }
+
+    #[test]
+    fn lookup_by_id_defaults_to_the_main_player() {
+        let players = registry();
+        let id = players.insert(MediaPlayer::new());
+        let main = players.get(None).unwrap();
+        assert!(Arc::ptr_eq(&main, &players.get(Some(MAIN_PLAYER)).unwrap()));
+        assert!(!Arc::ptr_eq(&main, &players.get(Some(id)).unwrap()));
+        assert!(matches!(
+            players.get(Some(id + 1)),
+            Err(PlayerError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn removed_and_main_players() {
+        let players = registry();
+        let id = players.insert(MediaPlayer::new());
+        players.remove(id).unwrap();
+        assert!(players.get(Some(id)).is_err());
+        assert!(players.remove(id).is_err());
+        // The main player stays
+        assert!(players.remove(MAIN_PLAYER).is_err());
+        assert!(players.get(None).is_ok());
+    }
+}
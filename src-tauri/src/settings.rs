@@ -0,0 +1,106 @@
+use crate::adjustments::VideoAdjustments;
+use crate::decoder::{SeekMode, DEFAULT_BUFFER_AHEAD};
+use crate::effects::AudioEffects;
+use crate::player::{EndBehavior, VolumeSteps, DEFAULT_VOLUME};
+use crate::speed::RememberedSpeeds;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// User settings restored at startup and applied to every new player
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerSettings {
+    pub volume: f32,
+    /// Fine and coarse `adjust_volume` step sizes
+    pub volume_steps: VolumeSteps,
+    /// Preamp, balance and equalizer
+    pub effects: AudioEffects,
+    /// Match the stereo channel levels of each file
+    pub auto_balance: bool,
+    /// Downmix to mono on every channel
+    pub mono_output: bool,
+    pub preferred_audio_language: Option<String>,
+    pub seek_mode: SeekMode,
+    pub buffer_ahead: f64,
+    /// Lip-sync offset in milliseconds (positive delays video)
+    pub av_offset_ms: f64,
+    pub tone_mapping: bool,
+    /// Brightness, contrast, saturation and gamma
+    pub video_adjustments: VideoAdjustments,
+    pub keep_device_warm: bool,
+    pub realtime_audio: bool,
+    pub end_behavior: EndBehavior,
+    /// Output device to play on; `None` follows the system default
+    pub output_device: Option<String>,
+    /// Speed memory mode and the speeds it keeps for the next loads
+    pub remembered_speeds: RememberedSpeeds,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            volume: DEFAULT_VOLUME,
+            volume_steps: VolumeSteps::default(),
+            effects: AudioEffects::default(),
+            auto_balance: false,
+            mono_output: false,
+            preferred_audio_language: None,
+            seek_mode: SeekMode::default(),
+            buffer_ahead: DEFAULT_BUFFER_AHEAD,
+            av_offset_ms: 0.0,
+            tone_mapping: true,
+            video_adjustments: VideoAdjustments::default(),
+            keep_device_warm: false,
+            realtime_audio: true,
+            end_behavior: EndBehavior::default(),
+            output_device: None,
+            remembered_speeds: RememberedSpeeds::default(),
+        }
+    }
+}
+
+/// Player settings persisted as JSON in the app data folder
+#[derive(Debug, Default)]
+pub struct SettingsStore {
+    settings: PlayerSettings,
+    file: Option<PathBuf>,
+}
+
+impl SettingsStore {
+    /// Load settings from `file`, using the defaults if it is missing or corrupt
+    pub fn load(file: PathBuf) -> Self {
+        let settings = match std::fs::read_to_string(&file) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("Ignoring unreadable settings {}: {}", file.display(), e);
+                PlayerSettings::default()
+            }),
+            Err(_) => PlayerSettings::default(),
+        };
+        Self {
+            settings,
+            file: Some(file),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(ref file) = self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file, serde_json::to_string_pretty(&self.settings)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self) -> PlayerSettings {
+        self.settings.clone()
+    }
+
+    /// Replace the stored settings and write them to disk
+    pub fn update(&mut self, settings: PlayerSettings) -> Result<()> {
+        self.settings = settings;
+        self.save()
+    }
+}
@@ -0,0 +1,253 @@
+use crate::audio_output::OUTPUT_CHANNELS;
+use ffmpeg_next as ffmpeg;
+use serde::{Deserialize, Serialize};
+
+/// Slowest and fastest playback speed (the range one `atempo` filter covers)
+pub const PLAYBACK_SPEED_RANGE: std::ops::RangeInclusive<f64> = 0.5..=2.0;
+
+/// Files whose speed is remembered; the least recently set is forgotten first
+const MAX_REMEMBERED_FILES: usize = 500;
+
+/// Which speed a newly loaded file starts playing at
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedMemory {
+    /// The speed last set while the same file was loaded, else 1x
+    PerFile,
+    /// The speed last set for any file
+    Global,
+    /// Always 1x
+    #[default]
+    Reset,
+}
+
+/// Speed last set while one file was loaded
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileSpeed {
+    pub path: String,
+    pub speed: f64,
+}
+
+/// Playback speeds remembered for the next loads, saved with the settings
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RememberedSpeeds {
+    pub mode: SpeedMemory,
+    /// Speed last set for any file
+    pub last: f64,
+    /// Speeds other than 1x set in `PerFile` mode, least recently set first
+    pub files: Vec<FileSpeed>,
+}
+
+impl Default for RememberedSpeeds {
+    fn default() -> Self {
+        Self {
+            mode: SpeedMemory::default(),
+            last: 1.0,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl RememberedSpeeds {
+    /// Speed the file at `path` starts playing at
+    pub fn speed_for(&self, path: &str) -> f64 {
+        let speed = match self.mode {
+            SpeedMemory::Reset => 1.0,
+            SpeedMemory::Global => self.last,
+            SpeedMemory::PerFile => self
+                .files
+                .iter()
+                .find(|file| file.path == path)
+                .map_or(1.0, |file| file.speed),
+        };
+        // Hand-edited settings may hold anything
+        if speed.is_finite() {
+            speed.clamp(*PLAYBACK_SPEED_RANGE.start(), *PLAYBACK_SPEED_RANGE.end())
+        } else {
+            1.0
+        }
+    }
+
+    /// Note that `speed` was set while the file at `path` (if any) is loaded
+    pub fn remember(&mut self, path: Option<&str>, speed: f64) {
+        self.last = speed;
+        let Some(path) = path.filter(|_| self.mode == SpeedMemory::PerFile) else {
+            return;
+        };
+        self.files.retain(|file| file.path != path);
+        if speed != 1.0 {
+            self.files.push(FileSpeed {
+                path: path.to_string(),
+                speed,
+            });
+        }
+        let excess = self.files.len().saturating_sub(MAX_REMEMBERED_FILES);
+        self.files.drain(..excess);
+    }
+}
+
+/// `atempo` filter graph that changes the speed of interleaved stereo output
+/// samples without changing their pitch.
+///
+/// The filter works on overlapping windows, so it holds back some audio and
+/// is flushed at the end of the stream; seeks build a new one.
+pub struct Tempo {
+    sample_rate: u32,
+    speed: f64,
+    /// Timestamp of the next input frame, in samples
+    next_pts: i64,
+    /// Media time of the first sample fed in
+    start: Option<f64>,
+    /// Sample frames taken out so far
+    produced: u64,
+    graph: ffmpeg::filter::Graph,
+}
+
+impl Tempo {
+    pub fn new(sample_rate: u32, speed: f64) -> Result<Self, ffmpeg::Error> {
+        let args = format!(
+            "sample_rate={}:sample_fmt=flt:channel_layout=stereo:time_base=1/{}",
+            sample_rate, sample_rate
+        );
+        let mut graph = ffmpeg::filter::Graph::new();
+        let buffer = ffmpeg::filter::find("abuffer").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let sink = ffmpeg::filter::find("abuffersink").ok_or(ffmpeg::Error::FilterNotFound)?;
+        graph.add(&buffer, "in", &args)?;
+        graph.add(&sink, "out", "")?;
+        let spec = format!(
+            "atempo={},aformat=sample_fmts=flt:channel_layouts=stereo",
+            speed
+        );
+        graph.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        graph.validate()?;
+
+        Ok(Self {
+            sample_rate,
+            speed,
+            next_pts: 0,
+            start: None,
+            produced: 0,
+            graph,
+        })
+    }
+
+    /// Whether this graph was built for `speed` at `sample_rate`
+    pub fn matches(&self, sample_rate: u32, speed: f64) -> bool {
+        self.sample_rate == sample_rate && self.speed == speed
+    }
+
+    /// Feed interleaved samples starting at media time `timestamp` (`None` at
+    /// the end of the stream) and return the interleaved samples that are
+    /// ready, with the media time of the first one
+    pub fn process(
+        &mut self,
+        samples: Option<&[f32]>,
+        timestamp: f64,
+    ) -> Result<(Vec<f32>, f64), ffmpeg::Error> {
+        let channels = OUTPUT_CHANNELS as usize;
+        let start = *self.start.get_or_insert(timestamp);
+        {
+            let mut source = self.graph.get("in").ok_or(ffmpeg::Error::FilterNotFound)?;
+            match samples {
+                Some(samples) => {
+                    let count = samples.len() / channels;
+                    let mut frame = ffmpeg::frame::Audio::new(
+                        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+                        count,
+                        ffmpeg::channel_layout::ChannelLayout::STEREO,
+                    );
+                    frame.set_rate(self.sample_rate);
+                    frame.set_pts(Some(self.next_pts));
+                    self.next_pts += count as i64;
+                    let data = frame.data_mut(0);
+                    for (bytes, sample) in data.chunks_exact_mut(4).zip(samples) {
+                        bytes.copy_from_slice(&sample.to_ne_bytes());
+                    }
+                    source.source().add(&frame)?;
+                }
+                None => source.source().flush()?,
+            }
+        }
+
+        let mut sink = self.graph.get("out").ok_or(ffmpeg::Error::FilterNotFound)?;
+        let mut output = Vec::new();
+        loop {
+            let mut filtered = ffmpeg::frame::Audio::empty();
+            if sink.sink().frame(&mut filtered).is_err() {
+                break;
+            }
+            let len = filtered.samples() * channels * std::mem::size_of::<f32>();
+            output.extend(
+                filtered.data(0)[..len]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])),
+            );
+        }
+        let output_start = start + self.produced as f64 * self.speed / f64::from(self.sample_rate);
+        self.produced += (output.len() / channels) as u64;
+        Ok((output, output_start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remembering(mode: SpeedMemory) -> RememberedSpeeds {
+        RememberedSpeeds {
+            mode,
+            ..RememberedSpeeds::default()
+        }
+    }
+
+    #[test]
+    fn per_file_speeds_apply_to_their_file_only() {
+        let mut speeds = remembering(SpeedMemory::PerFile);
+        speeds.remember(Some("lecture.mp4"), 1.5);
+        speeds.remember(Some("film.mkv"), 0.75);
+        assert_eq!(speeds.speed_for("lecture.mp4"), 1.5);
+        assert_eq!(speeds.speed_for("film.mkv"), 0.75);
+        assert_eq!(speeds.speed_for("other.mp3"), 1.0);
+
+        // Back at 1x there is nothing left to remember
+        speeds.remember(Some("film.mkv"), 1.0);
+        assert_eq!(speeds.files.len(), 1);
+        assert_eq!(speeds.speed_for("film.mkv"), 1.0);
+    }
+
+    #[test]
+    fn global_and_reset_modes() {
+        let mut speeds = remembering(SpeedMemory::Global);
+        speeds.remember(Some("lecture.mp4"), 1.25);
+        assert_eq!(speeds.speed_for("other.mp3"), 1.25);
+        // Only per-file mode keeps paths
+        assert!(speeds.files.is_empty());
+
+        speeds.mode = SpeedMemory::Reset;
+        assert_eq!(speeds.speed_for("lecture.mp4"), 1.0);
+    }
+
+    #[test]
+    fn the_least_recently_set_file_is_forgotten() {
+        let mut speeds = remembering(SpeedMemory::PerFile);
+        for i in 0..MAX_REMEMBERED_FILES {
+            speeds.remember(Some(&format!("{}.mp3", i)), 1.5);
+        }
+        // Setting a speed again makes the file the most recent one
+        speeds.remember(Some("0.mp3"), 2.0);
+        speeds.remember(Some("new.mp3"), 1.5);
+        assert_eq!(speeds.files.len(), MAX_REMEMBERED_FILES);
+        assert_eq!(speeds.speed_for("0.mp3"), 2.0);
+        assert_eq!(speeds.speed_for("1.mp3"), 1.0);
+        assert_eq!(speeds.speed_for("new.mp3"), 1.5);
+    }
+
+    #[test]
+    fn saved_speeds_are_kept_in_range() {
+        let mut speeds = remembering(SpeedMemory::Global);
+        speeds.last = 16.0;
+        assert_eq!(speeds.speed_for("a.mp3"), 2.0);
+        speeds.last = f64::NAN;
+        assert_eq!(speeds.speed_for("a.mp3"), 1.0);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::clock::{system_clock, SharedClock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Drift (seconds) beyond which a correction is applied
+pub const SYNC_THRESHOLD: f64 = 0.04;
+
+/// How often (in sample frames) a frame is dropped or repeated while nudging audio
+const AUDIO_NUDGE_INTERVAL: usize = 100;
+
+/// Lateness (seconds) at which `DegradationMode::QualityFirst` starts dropping frames
+const QUALITY_FIRST_DROP_THRESHOLD: f64 = 0.5;
+
+/// Window the decode and presentation frame rates are averaged over
+const FRAME_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Which clock the other streams are synchronized to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SyncMode {
+    /// Video follows the audio device clock (drops late frames, holds early ones)
+    #[default]
+    AudioMaster,
+    /// Video runs on the wall clock and is never dropped; audio is nudged to follow
+    VideoMaster,
+    /// Both streams follow the wall clock
+    External,
+}
+
+/// What gives way when decoding cannot keep up with playback
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DegradationMode {
+    /// Keep video smooth: late frames are still shown until they are
+    /// `QUALITY_FIRST_DROP_THRESHOLD` behind, and audio is buffered further
+    /// ahead to ride out slow stretches of video decoding
+    QualityFirst,
+    /// Keep audio glitch-free: late frames are dropped, and while frames are
+    /// being dropped the video decoder skips frames nothing else refers to
+    #[default]
+    SmoothnessFirst,
+}
+
+/// Synchronization statistics reported by the decoder
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SyncStats {
+    pub sync_mode: SyncMode,
+    pub degradation_mode: DegradationMode,
+    /// Audio clock minus the last presented video timestamp (positive = audio ahead)
+    pub av_drift: f64,
+    /// Video frames dropped because they were late
+    pub frames_dropped: u64,
+    /// Video frames presented late with nothing newer to drop to (previous frame repeated)
+    pub frames_held: u64,
+    /// Video frames decoded after a later frame had been presented, dropped
+    /// instead of being shown out of order
+    pub frames_out_of_order: u64,
+    /// Video frames on time but not emitted, as one was already emitted in
+    /// their refresh interval under `set_max_fps`
+    pub frames_throttled: u64,
+    /// Audio chunks stretched or shortened to follow the master clock
+    pub audio_corrections: u64,
+    /// Decoded audio queued ahead of the device, in seconds
+    pub audio_buffered: f64,
+    /// Decoded video frames waiting for presentation
+    pub video_queued: usize,
+    /// Device output latency reported by the audio driver, in seconds
+    pub output_latency: f64,
+    /// Time from decoding a sample to hearing it (queued audio plus device
+    /// latency), in seconds
+    pub audio_latency: f64,
+    /// Manual lip-sync offset set with `set_av_offset` (positive delays video)
+    pub av_offset: f64,
+    /// Video frames decoded and presented per second, over the last second
+    pub decode_fps: f64,
+    pub present_fps: f64,
+    /// Frame rate the video is meant to play at (`None` without video or if unknown)
+    pub target_fps: Option<f64>,
+}
+
+/// Counts events (decoded or presented frames) within the last
+/// `FRAME_RATE_WINDOW`
+#[derive(Default)]
+pub struct RateMeter {
+    times: VecDeque<Instant>,
+}
+
+impl RateMeter {
+    pub fn tick(&mut self, now: Instant) {
+        self.times.push_back(now);
+        self.expire(now);
+    }
+
+    /// Events per second over the window ending at `now`
+    pub fn rate(&mut self, now: Instant) -> f64 {
+        self.expire(now);
+        self.times.len() as f64 / FRAME_RATE_WINDOW.as_secs_f64()
+    }
+
+    /// Forget earlier events (seek/load)
+    pub fn reset(&mut self) {
+        self.times.clear();
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .times
+            .front()
+            .is_some_and(|time| now.saturating_duration_since(*time) > FRAME_RATE_WINDOW)
+        {
+            self.times.pop_front();
+        }
+    }
+}
+
+/// Bit pattern (a NaN) marking an unknown audio base time
+const NO_BASE: u64 = u64::MAX;
+
+/// Playback position of the audio device, shared with the output callback
+#[derive(Debug)]
+pub struct AudioClock {
+    /// Interleaved samples handed to the device since the last flush
+    played: AtomicU64,
+    /// Set by the decoder after a seek; the callback discards what it still holds
+    flush: AtomicBool,
+    /// Media time (f64 bits) of the first sample sent after the last flush
+    base: AtomicU64,
+    /// Interleaved samples written to the device but not yet audible
+    latency: AtomicU64,
+    /// Media seconds per second of device audio (f64 bits)
+    speed: AtomicU64,
+    /// Interleaved samples already counted into `base` at an earlier speed
+    counted: AtomicU64,
+}
+
+impl Default for AudioClock {
+    fn default() -> Self {
+        Self {
+            played: AtomicU64::new(0),
+            flush: AtomicBool::new(false),
+            base: AtomicU64::new(NO_BASE),
+            latency: AtomicU64::new(0),
+            speed: AtomicU64::new(1.0f64.to_bits()),
+            counted: AtomicU64::new(0),
+        }
+    }
+}
+
+impl AudioClock {
+    /// Ask the output callback to discard buffered samples and restart counting
+    pub fn request_flush(&self) {
+        self.base.store(NO_BASE, Ordering::Release);
+        self.flush.store(true, Ordering::Release);
+    }
+
+    /// Record the media time of the first audio sent after a flush
+    pub fn note_start(&self, timestamp: f64) {
+        let _ = self.base.compare_exchange(
+            NO_BASE,
+            timestamp.to_bits(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+    }
+
+    /// Called by the output callback; returns true if a flush was requested
+    pub fn take_flush(&self) -> bool {
+        if self.flush.swap(false, Ordering::AcqRel) {
+            self.played.store(0, Ordering::Release);
+            self.counted.store(0, Ordering::Release);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether a flush has been requested but not yet applied
+    pub fn flush_pending(&self) -> bool {
+        self.flush.load(Ordering::Acquire)
+    }
+
+    /// Record samples written to the device
+    pub fn add_played(&self, samples: u64) {
+        self.played.fetch_add(samples, Ordering::AcqRel);
+    }
+
+    /// Record the device's output latency, reported by the output callback
+    pub fn set_latency(&self, samples: u64) {
+        self.latency.store(samples, Ordering::Release);
+    }
+
+    /// Interleaved samples written to the device but not yet audible
+    pub fn latency(&self) -> u64 {
+        self.latency.load(Ordering::Acquire)
+    }
+
+    /// Set the speed the samples played from now on count at; what has been
+    /// heard so far is folded into the base time at the old speed, so the
+    /// position does not jump
+    pub fn set_speed(&self, speed: f64, samples_per_second: f64) {
+        let base = self.base.load(Ordering::Acquire);
+        if let Some(position) = self.position(samples_per_second) {
+            let _ = self.base.compare_exchange(
+                base,
+                position.to_bits(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+            let audible = self.played().saturating_sub(self.latency());
+            self.counted.store(audible, Ordering::Release);
+        }
+        self.speed.store(speed.to_bits(), Ordering::Release);
+    }
+
+    /// Interleaved samples played since the last flush
+    pub fn played(&self) -> u64 {
+        self.played.load(Ordering::Acquire)
+    }
+
+    /// Media time of the sample the device is playing, counted from the samples
+    /// the output callback has consumed minus the device latency; `None` until
+    /// audio plays after a flush.
+    pub fn position(&self, samples_per_second: f64) -> Option<f64> {
+        let base = self.base.load(Ordering::Acquire);
+        if base == NO_BASE || self.flush_pending() || samples_per_second <= 0.0 {
+            return None;
+        }
+        let audible = self
+            .played()
+            .saturating_sub(self.latency())
+            .saturating_sub(self.counted.load(Ordering::Acquire));
+        let speed = f64::from_bits(self.speed.load(Ordering::Acquire));
+        Some(f64::from_bits(base) + audible as f64 * speed / samples_per_second)
+    }
+}
+
+/// Master clock selection and drift bookkeeping for the decoder thread
+pub struct SyncClock {
+    clock: SharedClock,
+    mode: SyncMode,
+    degradation: DegradationMode,
+    /// Wall clock anchor: (instant, media time at that instant)
+    wall_anchor: Option<(Instant, f64)>,
+    /// Media seconds per wall clock second (1.0 plays as timestamped)
+    wall_rate: f64,
+    /// Seconds video is presented later than its timestamp (negative: earlier)
+    av_offset: f64,
+    /// Whether a video drop / audio nudge burst is in progress (to log it once)
+    dropping: bool,
+    nudging: bool,
+    pub stats: SyncStats,
+}
+
+impl SyncClock {
+    pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// Pace against `clock` instead of the system clock
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            mode: SyncMode::default(),
+            degradation: DegradationMode::default(),
+            wall_anchor: None,
+            wall_rate: 1.0,
+            av_offset: 0.0,
+            dropping: false,
+            nudging: false,
+            stats: SyncStats::default(),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: SyncMode) {
+        self.mode = mode;
+        self.stats.sync_mode = mode;
+        self.wall_anchor = None;
+    }
+
+    pub fn set_degradation_mode(&mut self, mode: DegradationMode) {
+        self.degradation = mode;
+        self.stats.degradation_mode = mode;
+    }
+
+    pub fn degradation_mode(&self) -> DegradationMode {
+        self.degradation
+    }
+
+    /// Whether late video frames are currently being dropped
+    pub fn is_dropping(&self) -> bool {
+        self.dropping
+    }
+
+    /// Shift video presentation by `seconds` against the master clock, to
+    /// compensate lip-sync errors the latency measurement does not catch
+    pub fn set_av_offset(&mut self, seconds: f64) {
+        self.av_offset = seconds;
+        self.stats.av_offset = seconds;
+    }
+
+    /// Run the wall clock `rate` times faster than real time; only affects
+    /// media paced by the wall clock (no audio, or `VideoMaster`/`External`)
+    pub fn set_wall_rate(&mut self, rate: f64) {
+        if rate != self.wall_rate {
+            self.wall_rate = rate;
+            self.wall_anchor = None;
+        }
+    }
+
+    /// Forget the wall clock anchor (pause/resume); the next frame re-anchors it
+    pub fn reanchor(&mut self) {
+        self.wall_anchor = None;
+    }
+
+    /// Forget all anchors (seek/load)
+    pub fn reset(&mut self) {
+        self.wall_anchor = None;
+        self.dropping = false;
+        self.nudging = false;
+    }
+
+    /// Media time currently audible on the device, if known
+    pub fn audio_time(&self, audio: &AudioClock, samples_per_second: f64) -> Option<f64> {
+        audio.position(samples_per_second)
+    }
+
+    /// Media time on the wall clock, anchored at `timestamp` if not yet anchored
+    pub fn wall_time(&mut self, timestamp: f64) -> f64 {
+        let (instant, base) = *self
+            .wall_anchor
+            .get_or_insert_with(|| (self.clock.now(), timestamp));
+        base + self.clock.elapsed(instant).as_secs_f64() * self.wall_rate
+    }
+
+    /// Media time of the master clock
+    pub fn master_time(
+        &mut self,
+        audio: &AudioClock,
+        samples_per_second: f64,
+        timestamp: f64,
+    ) -> f64 {
+        match self.mode {
+            SyncMode::AudioMaster => match self.audio_time(audio, samples_per_second) {
+                Some(time) => time,
+                None => self.wall_time(timestamp),
+            },
+            SyncMode::VideoMaster | SyncMode::External => self.wall_time(timestamp),
+        }
+    }
+
+    /// Decide what to do with the next queued video frame.
+    ///
+    /// `has_next` tells whether another frame is queued behind this one; a late
+    /// frame is only dropped when something newer can replace it.
+    pub fn video_decision(
+        &mut self,
+        audio: &AudioClock,
+        samples_per_second: f64,
+        timestamp: f64,
+        has_next: bool,
+    ) -> VideoDecision {
+        // The frame is due `av_offset` after its timestamp
+        let timestamp = timestamp + self.av_offset;
+        let master = self.master_time(audio, samples_per_second, timestamp);
+        if let Some(audio_time) = self.audio_time(audio, samples_per_second) {
+            self.stats.av_drift = audio_time - timestamp;
+        }
+
+        let late = master - timestamp;
+        if late < 0.0 {
+            return VideoDecision::Wait;
+        }
+
+        let drop_threshold = match self.degradation {
+            DegradationMode::QualityFirst => QUALITY_FIRST_DROP_THRESHOLD,
+            DegradationMode::SmoothnessFirst => SYNC_THRESHOLD,
+        };
+        if late > SYNC_THRESHOLD {
+            if has_next && self.mode != SyncMode::VideoMaster && late > drop_threshold {
+                self.stats.frames_dropped += 1;
+                if !self.dropping {
+                    eprintln!(
+                        "A/V sync: video {:.3}s behind master clock, dropping frames",
+                        late
+                    );
+                    self.dropping = true;
+                }
+                return VideoDecision::Drop;
+            }
+            // Nothing newer to show: the previous frame has effectively been repeated
+            self.stats.frames_held += 1;
+        }
+
+        self.dropping = false;
+        VideoDecision::Present
+    }
+
+    /// Nudge an interleaved audio chunk toward the wall clock when video is master.
+    ///
+    /// Drops or repeats one sample frame every `AUDIO_NUDGE_INTERVAL` frames, a
+    /// ~1% speed change that is inaudible for short corrections.
+    pub fn correct_audio(
+        &mut self,
+        samples: Vec<f32>,
+        channels: usize,
+        audio: &AudioClock,
+        samples_per_second: f64,
+    ) -> Vec<f32> {
+        if self.mode == SyncMode::AudioMaster || channels == 0 {
+            return samples;
+        }
+        let Some(audio_time) = self.audio_time(audio, samples_per_second) else {
+            return samples;
+        };
+        let Some((instant, base)) = self.wall_anchor else {
+            return samples;
+        };
+        let drift =
+            audio_time - (base + self.clock.elapsed(instant).as_secs_f64() * self.wall_rate);
+        if drift.abs() <= SYNC_THRESHOLD {
+            self.nudging = false;
+            return samples;
+        }
+
+        self.stats.audio_corrections += 1;
+        if !self.nudging {
+            let action = if drift > 0.0 {
+                "stretching"
+            } else {
+                "shortening"
+            };
+            eprintln!(
+                "A/V sync: audio {:+.3}s off the master clock, {} audio",
+                drift, action
+            );
+            self.nudging = true;
+        }
+
+        let mut corrected = Vec::with_capacity(samples.len() + samples.len() / 50);
+        for (i, frame) in samples.chunks(channels).enumerate() {
+            let nudge = i % AUDIO_NUDGE_INTERVAL == AUDIO_NUDGE_INTERVAL - 1;
+            if nudge && drift < 0.0 {
+                // Audio is behind: skip this frame
+                continue;
+            }
+            corrected.extend_from_slice(frame);
+            if nudge && drift > 0.0 {
+                // Audio is ahead: repeat this frame
+                corrected.extend_from_slice(frame);
+            }
+        }
+        corrected
+    }
+}
+
+impl Default for SyncClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Presentation decision for a queued video frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VideoDecision {
+    /// Not due yet
+    Wait,
+    /// Due now
+    Present,
+    /// Too late, skip it
+    Drop,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn wall_rate_scales_media_time() {
+        let clock = Arc::new(MockClock::new());
+        let mut sync = SyncClock::with_clock(clock.clone());
+        assert_eq!(sync.wall_time(10.0), 10.0);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(sync.wall_time(0.0), 11.0);
+
+        // A new rate starts from the next timestamp instead of jumping
+        sync.set_wall_rate(8.0);
+        assert_eq!(sync.wall_time(11.0), 11.0);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(sync.wall_time(0.0), 15.0);
+
+        // Setting the same rate again keeps the anchor
+        sync.set_wall_rate(8.0);
+        assert_eq!(sync.wall_time(0.0), 15.0);
+    }
+
+    #[test]
+    fn audio_position_scales_with_the_playback_speed() {
+        let audio = AudioClock::default();
+        audio.note_start(30.0);
+        audio.add_played(88_200);
+        assert_eq!(audio.position(88_200.0), Some(31.0));
+
+        // What was heard at 1x stays where it is
+        audio.set_speed(1.5, 88_200.0);
+        assert_eq!(audio.position(88_200.0), Some(31.0));
+        // One second of device audio at 1.5x covers 1.5 s of media
+        audio.add_played(88_200);
+        assert_eq!(audio.position(88_200.0), Some(32.5));
+
+        // After a flush the speed applies from the new base
+        audio.request_flush();
+        assert!(audio.take_flush());
+        audio.note_start(10.0);
+        audio.add_played(88_200);
+        assert_eq!(audio.position(88_200.0), Some(11.5));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::decoder::{choose_stream, is_cover_art, media_start_time, open_probed, VideoFrame};
+use crate::error::PlayerError;
+use crate::events::PlayerEvent;
+use crate::timebase::{pts_to_seconds, seconds_to_micros};
+use crate::tonemap::{ColorInfo, PixelFormat};
+use anyhow::Result;
+use crossbeam_channel::{bounded, unbounded, Sender};
+use ffmpeg_next as ffmpeg;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Default thumbnail width in pixels
+pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 160;
+/// Default width of scrub previews, large enough to stand in for the video
+pub const DEFAULT_SCRUB_WIDTH: u32 = 640;
+
+/// Request sent to the thumbnail thread
+enum ThumbnailRequest {
+    Frame {
+        time: f64,
+        max_width: u32,
+        reply: Sender<Result<VideoFrame, PlayerError>>,
+    },
+    Chapters {
+        max_width: u32,
+        reply: Sender<Vec<ChapterPreview>>,
+    },
+    /// Decode the latest scrub target, if it has not been served yet
+    Scrub { events: Sender<PlayerEvent> },
+}
+
+/// A chapter and a thumbnail of its start, for chapter browsers
+#[derive(Clone, Debug, Serialize)]
+pub struct ChapterPreview {
+    pub title: Option<String>,
+    /// Start and end in seconds
+    pub start: f64,
+    pub end: f64,
+    /// `None` for files without video or if the frame could not be decoded
+    pub thumbnail: Option<VideoFrame>,
+}
+
+/// Secondary decoder kept open for the loaded file.
+///
+/// Owns its own input context on a dedicated thread, so thumbnail seeks never
+/// touch the main playback decoder. The thread exits when the last handle is
+/// dropped.
+#[derive(Clone)]
+pub struct Thumbnailer {
+    path: String,
+    request_sender: Sender<ThumbnailRequest>,
+    /// Newest scrub position and width; older ones are skipped
+    scrub_target: Arc<Mutex<Option<(f64, u32)>>>,
+}
+
+impl Thumbnailer {
+    /// Open `path` on a new thumbnail thread
+    pub fn open(path: &str) -> Result<Self> {
+        let (request_sender, request_receiver) = unbounded::<ThumbnailRequest>();
+        let (ready_tx, ready_rx) = bounded::<Result<(), PlayerError>>(1);
+        let thread_path = path.to_string();
+        let scrub_target = Arc::new(Mutex::new(None));
+        let thread_scrub_target = scrub_target.clone();
+
+        std::thread::spawn(move || {
+            let mut source = match ThumbnailSource::open(&thread_path) {
+                Ok(source) => {
+                    let _ = ready_tx.send(Ok(()));
+                    source
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            while let Ok(request) = request_receiver.recv() {
+                match request {
+                    ThumbnailRequest::Frame {
+                        time,
+                        max_width,
+                        reply,
+                    } => {
+                        let _ = reply.send(source.thumbnail(time, max_width));
+                    }
+                    ThumbnailRequest::Chapters { max_width, reply } => {
+                        let _ = reply.send(source.chapter_previews(max_width));
+                    }
+                    ThumbnailRequest::Scrub { events } => {
+                        // Calls made while the previous frame was decoding all
+                        // queued a request, but only the newest target remains
+                        let Some((time, max_width)) = thread_scrub_target.lock().take() else {
+                            continue;
+                        };
+                        if let Ok(frame) = source.thumbnail(time, max_width) {
+                            let _ = events.send(PlayerEvent::ScrubFrame { frame });
+                        }
+                    }
+                }
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                path: path.to_string(),
+                request_sender,
+                scrub_target,
+            }),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(anyhow::anyhow!("Thumbnail thread closed")),
+        }
+    }
+
+    /// Path of the file this decoder serves
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Decode a frame near `time`, scaled to at most `max_width` pixels wide
+    pub fn thumbnail(&self, time: f64, max_width: u32) -> Result<VideoFrame> {
+        let (reply, response) = bounded(1);
+        self.request_sender
+            .send(ThumbnailRequest::Frame {
+                time,
+                max_width,
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("Thumbnail thread closed"))?;
+
+        match response.recv() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(anyhow::anyhow!("Thumbnail thread closed")),
+        }
+    }
+
+    /// Preview the keyframe at or before `time` while the user drags the
+    /// scrubber, without moving playback.
+    ///
+    /// Returns at once; the frame arrives as `ScrubFrame` on `events`. Calls
+    /// made while a frame is still decoding replace each other, so a fast drag
+    /// only decodes the newest position.
+    pub fn scrub(&self, time: f64, max_width: u32, events: Sender<PlayerEvent>) -> Result<()> {
+        *self.scrub_target.lock() = Some((time, max_width));
+        self.request_sender
+            .send(ThumbnailRequest::Scrub { events })
+            .map_err(|_| anyhow::anyhow!("Thumbnail thread closed"))
+    }
+
+    /// Drop a scrub preview that has not been decoded yet (the seek committed)
+    pub fn cancel_scrub(&self) {
+        self.scrub_target.lock().take();
+    }
+
+    /// Every chapter with a thumbnail of its start, at most `max_width` wide.
+    ///
+    /// The thumbnails are decoded one after another on the thumbnail thread, so
+    /// files with hundreds of chapters never compete with playback for more than
+    /// one decoder; the result is cached for this file.
+    pub fn chapter_previews(&self, max_width: u32) -> Result<Vec<ChapterPreview>> {
+        let (reply, response) = bounded(1);
+        self.request_sender
+            .send(ThumbnailRequest::Chapters { max_width, reply })
+            .map_err(|_| anyhow::anyhow!("Thumbnail thread closed"))?;
+
+        response
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Thumbnail thread closed"))
+    }
+}
+
+/// Chapters of `path` without thumbnails, for files that have no video
+pub fn chapters(path: &str) -> Result<Vec<ChapterPreview>, PlayerError> {
+    let _ = ffmpeg::init();
+    let input = open_probed(path)?;
+    Ok(read_chapters(&input, media_start_time(&input)))
+}
+
+/// Chapter titles and times, relative to the media start
+pub fn read_chapters(
+    input: &ffmpeg::format::context::Input,
+    start_time: i64,
+) -> Vec<ChapterPreview> {
+    input
+        .chapters()
+        .map(|chapter| {
+            let time_base = chapter.time_base();
+            ChapterPreview {
+                title: chapter.metadata().get("title").map(str::to_string),
+                start: pts_to_seconds(chapter.start(), time_base, start_time).max(0.0),
+                end: pts_to_seconds(chapter.end(), time_base, start_time).max(0.0),
+                thumbnail: None,
+            }
+        })
+        .collect()
+}
+
+/// Decode the embedded cover art of `path`, scaled to at most `max_width` pixels wide
+pub fn cover_art(path: &str, max_width: u32) -> Result<VideoFrame, PlayerError> {
+    let _ = ffmpeg::init();
+    let mut input = open_probed(path)?;
+
+    let stream = input
+        .streams()
+        .find(is_cover_art)
+        .ok_or_else(|| PlayerError::Unsupported("No cover art".to_string()))?;
+    let stream_index = stream.index();
+    let time_base = stream.time_base();
+
+    let mut decoder_context = ffmpeg::codec::Context::new();
+    decoder_context.set_parameters(stream.parameters())?;
+    let mut decoder = decoder_context.decoder().video()?;
+
+    // The picture is a single packet at the start of the stream
+    let mut frame = ffmpeg::frame::Video::empty();
+    for (stream, packet) in input.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        decoder.send_packet(&packet)?;
+        decoder.send_eof()?;
+        decoder.receive_frame(&mut frame)?;
+        return scale_thumbnail(&frame, time_base, 0, max_width);
+    }
+
+    Err(PlayerError::DecodeFailed(
+        "Cover art could not be decoded".to_string(),
+    ))
+}
+
+/// Input context and video decoder owned by the thumbnail thread
+struct ThumbnailSource {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Video,
+    stream_index: usize,
+    time_base: ffmpeg::Rational,
+    /// Media time origin (microseconds) shared with the main decoder
+    start_time: i64,
+    /// Chapter previews already built, with the width they were built for
+    chapters: Option<(u32, Vec<ChapterPreview>)>,
+}
+
+impl ThumbnailSource {
+    fn open(path: &str) -> Result<Self, PlayerError> {
+        let _ = ffmpeg::init();
+        let input = open_probed(path)?;
+
+        // Same track the player shows
+        let stream = choose_stream(&input, ffmpeg::media::Type::Video, None)
+            .and_then(|index| input.stream(index))
+            .ok_or_else(|| PlayerError::Unsupported("No video stream".to_string()))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        let mut decoder_context = ffmpeg::codec::Context::new();
+        decoder_context.set_parameters(stream.parameters())?;
+        let decoder = decoder_context.decoder().video()?;
+        let start_time = media_start_time(&input);
+
+        Ok(Self {
+            input,
+            decoder,
+            stream_index,
+            time_base,
+            start_time,
+            chapters: None,
+        })
+    }
+
+    /// Chapters with a thumbnail of each start, built once per width
+    fn chapter_previews(&mut self, max_width: u32) -> Vec<ChapterPreview> {
+        if let Some((width, ref previews)) = self.chapters {
+            if width == max_width {
+                return previews.clone();
+            }
+        }
+        let mut previews = read_chapters(&self.input, self.start_time);
+        for preview in previews.iter_mut() {
+            preview.thumbnail = self.thumbnail(preview.start, max_width).ok();
+        }
+        self.chapters = Some((max_width, previews.clone()));
+        previews
+    }
+
+    /// Seek to the keyframe at or before `time` and decode the first frame from there
+    fn thumbnail(&mut self, time: f64, max_width: u32) -> Result<VideoFrame, PlayerError> {
+        let timestamp = seconds_to_micros(time.max(0.0), self.start_time);
+        self.input.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+
+        let mut frame = ffmpeg::frame::Video::empty();
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index {
+                continue;
+            }
+            if self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            if self.decoder.receive_frame(&mut frame).is_ok() {
+                return scale_thumbnail(&frame, self.time_base, self.start_time, max_width);
+            }
+        }
+
+        Err(PlayerError::DecodeFailed(format!(
+            "No frame decoded at {:.3}s",
+            time
+        )))
+    }
+}
+
+/// Scale a decoded frame to RGBA, keeping aspect ratio within `max_width`
+fn scale_thumbnail(
+    frame: &ffmpeg::frame::Video,
+    time_base: ffmpeg::Rational,
+    start_time: i64,
+    max_width: u32,
+) -> Result<VideoFrame, PlayerError> {
+    let (src_width, src_height) = (frame.width(), frame.height());
+    if src_width == 0 || src_height == 0 {
+        return Err(PlayerError::DecodeFailed(
+            "Invalid frame dimensions".to_string(),
+        ));
+    }
+
+    let width = max_width.clamp(1, src_width);
+    let height = ((src_height as u64 * width as u64) / src_width as u64).max(1) as u32;
+
+    let mut scaler = ffmpeg::software::scaling::context::Context::get(
+        frame.format(),
+        src_width,
+        src_height,
+        ffmpeg::format::Pixel::RGBA,
+        width,
+        height,
+        ffmpeg::software::scaling::flag::Flags::BILINEAR,
+    )?;
+    let mut scaled = ffmpeg::frame::Video::empty();
+    scaler.run(frame, &mut scaled)?;
+
+    let timestamp = frame
+        .timestamp()
+        .map(|ts| pts_to_seconds(ts, time_base, start_time))
+        .unwrap_or(0.0);
+
+    // Copy row by row to drop any stride padding
+    let stride = scaled.stride(0);
+    let row = width as usize * 4;
+    let mut data = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        data.extend_from_slice(&scaled.data(0)[y * stride..y * stride + row]);
+    }
+
+    Ok(VideoFrame {
+        width,
+        height,
+        data,
+        format: PixelFormat::Rgba,
+        timestamp,
+        color: ColorInfo::for_frame(frame),
+    })
+}
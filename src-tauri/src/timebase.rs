@@ -0,0 +1,128 @@
+use ffmpeg::Rescale;
+use ffmpeg_next as ffmpeg;
+
+/// FFmpeg's `AV_TIME_BASE`: timestamps are rescaled to integer microseconds
+/// and only become seconds (`f64`) once they leave a decoder
+const MICROS_PER_SECOND: f64 = 1_000_000.0;
+
+/// `pts` in `time_base` units as microseconds, rescaled with FFmpeg's exact
+/// integer arithmetic
+pub fn pts_to_micros(pts: i64, time_base: ffmpeg::Rational) -> i64 {
+    pts.rescale(time_base, ffmpeg::rescale::TIME_BASE)
+}
+
+/// Seconds from `origin` (microseconds, see `media_start_time`) to `pts`.
+///
+/// The subtraction is done on integers, so positions stay exact to the
+/// microsecond however large the timestamps of a long or offset file get.
+pub fn pts_to_seconds(pts: i64, time_base: ffmpeg::Rational, origin: i64) -> f64 {
+    micros_to_seconds(pts_to_micros(pts, time_base).saturating_sub(origin))
+}
+
+pub fn micros_to_seconds(micros: i64) -> f64 {
+    micros as f64 / MICROS_PER_SECOND
+}
+
+/// Input timestamp (microseconds, for `Input::seek`) of `seconds` after `origin`
+pub fn seconds_to_micros(seconds: f64, origin: i64) -> i64 {
+    ((seconds * MICROS_PER_SECOND).round() as i64).saturating_add(origin)
+}
+
+/// `seconds` as `HH:MM:SS.mmm`, rounded to the millisecond; the hours keep
+/// counting past 99. Negative and non-finite values read as zero
+pub fn format_time(seconds: f64) -> String {
+    let millis = if seconds.is_finite() {
+        (seconds.max(0.0) * 1000.0).round() as u64
+    } else {
+        0
+    };
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// `format_time` of a duration; `None` while it is unknown (live streams, or
+/// files that do not declare one)
+pub fn format_duration(duration: f64) -> Option<String> {
+    (duration > 0.0 && duration.is_finite()).then(|| format_time(duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_count_from_the_media_start() {
+        // A transport stream whose video starts 10 s in and audio 0.2 s later
+        let mpeg_ts = ffmpeg::Rational::new(1, 90_000);
+        let origin = pts_to_micros(900_000, mpeg_ts);
+        assert_eq!(origin, 10_000_000);
+        assert_eq!(pts_to_seconds(900_000, mpeg_ts, origin), 0.0);
+        assert_eq!(pts_to_seconds(918_000, mpeg_ts, origin), 0.2);
+        assert_eq!(pts_to_seconds(945_000, mpeg_ts, origin), 0.5);
+        // Without an offset nothing is subtracted
+        assert_eq!(pts_to_seconds(45_000, mpeg_ts, 0), 0.5);
+        // Seeks go back the other way
+        assert_eq!(seconds_to_micros(0.5, origin), 10_500_000);
+        assert_eq!(seconds_to_micros(0.0, 0), 0);
+    }
+
+    #[test]
+    fn large_timestamps_stay_exact() {
+        // Ten days into a 90 kHz stream, starting a day in
+        let mpeg_ts = ffmpeg::Rational::new(1, 90_000);
+        let day = 86_400 * 90_000;
+        let origin = pts_to_micros(day, mpeg_ts);
+        assert_eq!(origin, 86_400_000_000);
+        assert_eq!(pts_to_micros(10 * day + 1, mpeg_ts), 864_000_000_011);
+        // One tick past the origin is 11 µs (rounded), not lost in f64
+        assert_eq!(pts_to_seconds(day + 1, mpeg_ts, origin), 0.000011);
+        // 1/1001-style time bases round to the nearest microsecond
+        let ntsc = ffmpeg::Rational::new(1001, 30_000);
+        assert_eq!(pts_to_micros(30_000 * 86_400, ntsc), 86_486_400_000_000);
+        assert_eq!(pts_to_micros(1, ntsc), 33_367);
+    }
+
+    #[test]
+    fn format_time_boundaries() {
+        let cases = [
+            (0.0, "00:00:00.000"),
+            (0.0004, "00:00:00.000"),
+            (0.0005, "00:00:00.001"),
+            (0.999, "00:00:00.999"),
+            (0.9996, "00:00:01.000"),
+            (59.999, "00:00:59.999"),
+            (60.0, "00:01:00.000"),
+            (3599.9995, "01:00:00.000"),
+            (3600.0, "01:00:00.000"),
+            (86399.999, "23:59:59.999"),
+            (360_000.0, "100:00:00.000"),
+            (-1.0, "00:00:00.000"),
+            (f64::NAN, "00:00:00.000"),
+            (f64::INFINITY, "00:00:00.000"),
+        ];
+        for (seconds, expected) in cases {
+            assert_eq!(format_time(seconds), expected, "{} s", seconds);
+        }
+    }
+
+    #[test]
+    fn format_duration_boundaries() {
+        let cases = [
+            (0.0, None),
+            (-5.0, None),
+            (f64::NAN, None),
+            (f64::INFINITY, None),
+            (0.001, Some("00:00:00.001")),
+            (5400.25, Some("01:30:00.250")),
+        ];
+        for (duration, expected) in cases {
+            let formatted = format_duration(duration);
+            assert_eq!(formatted.as_deref(), expected, "{} s", duration);
+        }
+    }
+}
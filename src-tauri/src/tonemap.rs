@@ -0,0 +1,530 @@
+use crate::adjustments::{ColorAdjuster, VideoAdjustments};
+use ffmpeg_next as ffmpeg;
+use ffmpeg_next::color::{Primaries, Range, Space, TransferCharacteristic};
+use ffmpeg_next::software::scaling::context::Context as Scaler;
+use serde::{Deserialize, Serialize};
+
+/// SDR reference white in nits (BT.2408)
+const REFERENCE_WHITE_NITS: f32 = 203.0;
+/// Mastering peak assumed when mapping to SDR
+const PEAK_NITS: f32 = 1000.0;
+/// Exposure applied before the tone curve
+const EXPOSURE: f32 = 2.0;
+
+/// HDR transfer functions the tone mapper understands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HdrTransfer {
+    /// SMPTE ST 2084 (HDR10, Dolby Vision base layers)
+    Pq,
+    /// ARIB STD-B67 hybrid log-gamma (broadcast HDR)
+    Hlg,
+}
+
+impl HdrTransfer {
+    pub fn detect(transfer: TransferCharacteristic) -> Option<Self> {
+        match transfer {
+            TransferCharacteristic::SMPTE2084 => Some(HdrTransfer::Pq),
+            TransferCharacteristic::ARIB_STD_B67 => Some(HdrTransfer::Hlg),
+            _ => None,
+        }
+    }
+
+    /// Display light for a non-linear signal in 0..=1, relative to SDR white
+    fn to_linear(self, signal: f32) -> f32 {
+        match self {
+            HdrTransfer::Pq => {
+                const M1: f32 = 0.159_301_76;
+                const M2: f32 = 78.843_75;
+                const C1: f32 = 0.835_937_5;
+                const C2: f32 = 18.851_563;
+                const C3: f32 = 18.6875;
+                let p = signal.powf(1.0 / M2);
+                let nits = 10000.0 * ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1);
+                nits / REFERENCE_WHITE_NITS
+            }
+            HdrTransfer::Hlg => {
+                const A: f32 = 0.178_832_77;
+                const B: f32 = 0.284_668_92;
+                const C: f32 = 0.559_910_7;
+                let scene = if signal <= 0.5 {
+                    signal * signal / 3.0
+                } else {
+                    (((signal - C) / A).exp() + B) / 12.0
+                };
+                // Approximate OOTF (system gamma 1.2) for a 1000 nit display
+                scene.powf(1.2) * PEAK_NITS / REFERENCE_WHITE_NITS
+            }
+        }
+    }
+}
+
+/// Bits per component of a pixel format (8 for unknown formats)
+pub fn bit_depth(format: ffmpeg::format::Pixel) -> u8 {
+    format
+        .descriptor()
+        .map(|d| unsafe { (*d.as_ptr()).comp[0].depth as u8 })
+        .filter(|depth| *depth > 0)
+        .unwrap_or(8)
+}
+
+/// Color metadata of decoded video, with FFmpeg's names for each value.
+///
+/// Frames are already converted to RGBA with the matrix and range; primaries
+/// and transfer describe the source signal (tone mapped frames are BT.709 SDR
+/// whatever they say). Unspecified values fall back to what players assume:
+/// limited range (full for JPEG pixel formats) and BT.709 for HD or
+/// SMPTE 170M (BT.601) below 720 lines.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ColorInfo {
+    /// "limited" (16-235) or "full" (0-255)
+    pub range: &'static str,
+    /// Color primaries, e.g. "bt709" or "bt2020"
+    pub primaries: &'static str,
+    /// Transfer characteristic, e.g. "bt709", "smpte2084" (PQ) or "arib-std-b67" (HLG)
+    pub transfer: &'static str,
+    /// YUV matrix, e.g. "bt709" or "bt2020nc"
+    pub matrix: &'static str,
+    /// All four values came from the stream rather than the fallbacks
+    pub tagged: bool,
+}
+
+impl ColorInfo {
+    pub fn new(
+        format: ffmpeg::format::Pixel,
+        height: u32,
+        range: Range,
+        primaries: Primaries,
+        transfer: TransferCharacteristic,
+        space: Space,
+    ) -> Self {
+        use ffmpeg::format::Pixel;
+
+        let hd = height >= 720;
+        let full_range = match range {
+            Range::JPEG => true,
+            Range::MPEG => false,
+            Range::Unspecified => matches!(
+                format,
+                Pixel::YUVJ420P | Pixel::YUVJ422P | Pixel::YUVJ444P | Pixel::YUVJ440P
+            ),
+        };
+        Self {
+            range: if full_range { "full" } else { "limited" },
+            primaries: primaries
+                .name()
+                .unwrap_or(if hd { "bt709" } else { "smpte170m" }),
+            transfer: transfer
+                .name()
+                .unwrap_or(if hd { "bt709" } else { "smpte170m" }),
+            matrix: space
+                .name()
+                .unwrap_or(if hd { "bt709" } else { "smpte170m" }),
+            tagged: range != Range::Unspecified
+                && primaries != Primaries::Unspecified
+                && transfer != TransferCharacteristic::Unspecified
+                && space != Space::Unspecified,
+        }
+    }
+
+    pub fn for_frame(frame: &ffmpeg::frame::Video) -> Self {
+        Self::new(
+            frame.format(),
+            frame.height(),
+            frame.color_range(),
+            frame.color_primaries(),
+            frame.color_transfer_characteristic(),
+            frame.color_space(),
+        )
+    }
+}
+
+/// Tell swscale which YUV matrix and range the source uses.
+///
+/// swscale assumes BT.601 otherwise, which shifts colors of HD and BT.2020
+/// content. Unspecified matrices are guessed from the frame height.
+pub fn set_source_colorspace(scaler: &mut Scaler, space: Space, range: Range, height: u32) {
+    let colorspace = match space {
+        Space::BT709 => ffmpeg::ffi::SWS_CS_ITU709,
+        Space::BT2020NCL | Space::BT2020CL => ffmpeg::ffi::SWS_CS_BT2020,
+        Space::FCC => ffmpeg::ffi::SWS_CS_FCC,
+        Space::SMPTE240M => ffmpeg::ffi::SWS_CS_SMPTE240M,
+        Space::BT470BG | Space::SMPTE170M => ffmpeg::ffi::SWS_CS_ITU601,
+        _ if height >= 720 => ffmpeg::ffi::SWS_CS_ITU709,
+        _ => ffmpeg::ffi::SWS_CS_ITU601,
+    };
+    let full_range = i32::from(range == Range::JPEG);
+
+    unsafe {
+        let coefficients = ffmpeg::ffi::sws_getCoefficients(colorspace);
+        ffmpeg::ffi::sws_setColorspaceDetails(
+            scaler.as_mut_ptr(),
+            coefficients,
+            full_range,
+            ffmpeg::ffi::sws_getCoefficients(ffmpeg::ffi::SWS_CS_DEFAULT),
+            1,
+            0,
+            1 << 16,
+            1 << 16,
+        );
+    }
+}
+
+/// Maps BT.2020 HDR RGB48 frames to SDR BT.709 RGBA.
+///
+/// Decodes the transfer function through a lookup table, converts the gamut
+/// in linear light, then compresses highlights with a filmic (Hable) curve
+/// folded into the output gamma table.
+pub struct ToneMapper {
+    /// 16-bit signal -> linear light relative to SDR white
+    to_linear: Vec<f32>,
+    /// Linear light in 0..=PEAK (quantized) -> tone-mapped, gamma-encoded 8-bit
+    to_display: Vec<u8>,
+}
+
+/// Linear BT.2020 to BT.709 primaries
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.6605, -0.5876, -0.0728],
+    [-0.1246, 1.1329, -0.0083],
+    [-0.0182, -0.1006, 1.1187],
+];
+
+const DISPLAY_LUT_SIZE: usize = 1 << 16;
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+impl ToneMapper {
+    pub fn new(transfer: HdrTransfer) -> Self {
+        let to_linear = (0..=u16::MAX)
+            .map(|code| transfer.to_linear(code as f32 / u16::MAX as f32))
+            .collect();
+
+        let peak = PEAK_NITS / REFERENCE_WHITE_NITS;
+        let white = hable(peak * EXPOSURE);
+        let to_display = (0..DISPLAY_LUT_SIZE)
+            .map(|i| {
+                let linear = i as f32 / (DISPLAY_LUT_SIZE - 1) as f32 * peak;
+                let mapped = (hable(linear * EXPOSURE) / white).clamp(0.0, 1.0);
+                // BT.1886 display gamma
+                (mapped.powf(1.0 / 2.4) * 255.0).round() as u8
+            })
+            .collect();
+
+        Self {
+            to_linear,
+            to_display,
+        }
+    }
+
+    /// Convert a packed little-endian RGB48 frame into tightly packed RGBA
+    pub fn process(&self, frame: &ffmpeg::frame::Video) -> Vec<u8> {
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let stride = frame.stride(0);
+        let data = frame.data(0);
+        let peak = PEAK_NITS / REFERENCE_WHITE_NITS;
+        let scale = (DISPLAY_LUT_SIZE - 1) as f32 / peak;
+
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            let row = &data[y * stride..y * stride + width * 6];
+            for px in row.chunks_exact(6) {
+                let rgb = [
+                    self.to_linear[u16::from_le_bytes([px[0], px[1]]) as usize],
+                    self.to_linear[u16::from_le_bytes([px[2], px[3]]) as usize],
+                    self.to_linear[u16::from_le_bytes([px[4], px[5]]) as usize],
+                ];
+                for m in BT2020_TO_BT709.iter() {
+                    let value = m[0] * rgb[0] + m[1] * rgb[1] + m[2] * rgb[2];
+                    let index = (value * scale).clamp(0.0, (DISPLAY_LUT_SIZE - 1) as f32);
+                    rgba.push(self.to_display[index as usize]);
+                }
+                rgba.push(255);
+            }
+        }
+        rgba
+    }
+}
+
+/// Pixel layout of the frames sent to the frontend; rows are always
+/// tightly packed (`width * bytes_per_pixel` bytes, no padding)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// Red, green, blue, then opaque alpha
+    #[default]
+    Rgba,
+    /// Blue, green, red, then opaque alpha (the native order of most GPU
+    /// surfaces)
+    Bgra,
+    /// Red, green, blue without alpha
+    Rgb24,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba | PixelFormat::Bgra => 4,
+            PixelFormat::Rgb24 => 3,
+        }
+    }
+
+    /// Offsets of the red, green and blue bytes within a pixel
+    pub fn rgb_offsets(self) -> [usize; 3] {
+        match self {
+            PixelFormat::Bgra => [2, 1, 0],
+            PixelFormat::Rgba | PixelFormat::Rgb24 => [0, 1, 2],
+        }
+    }
+
+    /// The bytes of one opaque pixel of color `rgb`
+    pub fn pixel(self, [r, g, b]: [u8; 3]) -> Vec<u8> {
+        match self {
+            PixelFormat::Rgba => vec![r, g, b, 255],
+            PixelFormat::Bgra => vec![b, g, r, 255],
+            PixelFormat::Rgb24 => vec![r, g, b],
+        }
+    }
+
+    fn scaler_format(self) -> ffmpeg::format::Pixel {
+        match self {
+            PixelFormat::Rgba => ffmpeg::format::Pixel::RGBA,
+            PixelFormat::Bgra => ffmpeg::format::Pixel::BGRA,
+            PixelFormat::Rgb24 => ffmpeg::format::Pixel::RGB24,
+        }
+    }
+
+    /// Reorder tightly packed RGBA into this layout
+    fn repack_rgba(self, mut rgba: Vec<u8>) -> Vec<u8> {
+        match self {
+            PixelFormat::Rgba => rgba,
+            PixelFormat::Bgra => {
+                for px in rgba.chunks_exact_mut(4) {
+                    px.swap(0, 2);
+                }
+                rgba
+            }
+            PixelFormat::Rgb24 => rgba
+                .chunks_exact(4)
+                .flat_map(|px| [px[0], px[1], px[2]])
+                .collect(),
+        }
+    }
+}
+
+/// Scaler chain turning decoded frames of one format into packed RGB(A).
+///
+/// SDR sources scale straight to the output `PixelFormat` (RGBA unless set
+/// otherwise). HDR sources (when tone mapping is on) scale to RGB48 first so
+/// the tone mapper sees the full bit depth, and its RGBA is reordered
+/// afterwards. Picture adjustments are applied to the result.
+pub struct VideoConverter {
+    format: ffmpeg::format::Pixel,
+    width: u32,
+    height: u32,
+    space: Space,
+    range: Range,
+    hdr: Option<HdrTransfer>,
+    output: PixelFormat,
+    scaler: Scaler,
+    tone_mapper: Option<ToneMapper>,
+    adjuster: Option<ColorAdjuster>,
+}
+
+impl VideoConverter {
+    pub fn new(
+        format: ffmpeg::format::Pixel,
+        width: u32,
+        height: u32,
+        space: Space,
+        range: Range,
+        hdr: Option<HdrTransfer>,
+        output: PixelFormat,
+    ) -> Result<Self, ffmpeg::Error> {
+        let target = if hdr.is_some() {
+            ffmpeg::format::Pixel::RGB48LE
+        } else {
+            output.scaler_format()
+        };
+        let mut scaler = Scaler::get(
+            format,
+            width,
+            height,
+            target,
+            width,
+            height,
+            ffmpeg::software::scaling::flag::Flags::BILINEAR,
+        )?;
+        set_source_colorspace(&mut scaler, space, range, height);
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            space,
+            range,
+            hdr,
+            output,
+            scaler,
+            tone_mapper: hdr.map(ToneMapper::new),
+            adjuster: None,
+        })
+    }
+
+    /// Build a converter for `frame`, tone mapping HDR if `tone_mapping` is set
+    pub fn for_frame(
+        frame: &ffmpeg::frame::Video,
+        tone_mapping: bool,
+        output: PixelFormat,
+    ) -> Result<Self, ffmpeg::Error> {
+        Self::new(
+            frame.format(),
+            frame.width(),
+            frame.height(),
+            frame.color_space(),
+            frame.color_range(),
+            Self::frame_hdr(frame, tone_mapping),
+            output,
+        )
+    }
+
+    fn frame_hdr(frame: &ffmpeg::frame::Video, tone_mapping: bool) -> Option<HdrTransfer> {
+        if tone_mapping {
+            HdrTransfer::detect(frame.color_transfer_characteristic())
+        } else {
+            None
+        }
+    }
+
+    /// Whether this converter was built for frames like `frame`, producing
+    /// `output`
+    pub fn matches(
+        &self,
+        frame: &ffmpeg::frame::Video,
+        tone_mapping: bool,
+        output: PixelFormat,
+    ) -> bool {
+        self.output == output
+            && self.format == frame.format()
+            && self.width == frame.width()
+            && self.height == frame.height()
+            && self.space == frame.color_space()
+            && self.range == frame.color_range()
+            && self.hdr == Self::frame_hdr(frame, tone_mapping)
+    }
+
+    /// Rebuild the brightness/contrast/saturation/gamma stage
+    pub fn set_adjustments(&mut self, adjustments: VideoAdjustments) {
+        self.adjuster = ColorAdjuster::new(adjustments);
+    }
+
+    /// Convert a frame to tightly packed pixels of the output format
+    pub fn convert(&mut self, frame: &ffmpeg::frame::Video) -> Result<Vec<u8>, ffmpeg::Error> {
+        let mut scaled = ffmpeg::frame::Video::empty();
+        self.scaler.run(frame, &mut scaled)?;
+
+        let mut data = if let Some(ref tone_mapper) = self.tone_mapper {
+            self.output.repack_rgba(tone_mapper.process(&scaled))
+        } else {
+            // Copy row by row to drop any stride padding
+            let stride = scaled.stride(0);
+            let row = self.width as usize * self.output.bytes_per_pixel();
+            let mut data = Vec::with_capacity(row * self.height as usize);
+            for y in 0..self.height as usize {
+                data.extend_from_slice(&scaled.data(0)[y * stride..y * stride + row]);
+            }
+            data
+        };
+        if let Some(ref adjuster) = self.adjuster {
+            adjuster.process(&mut data, self.output);
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32, tolerance: f32) -> bool {
+        (a - b).abs() <= tolerance * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn detects_hdr_transfers_only() {
+        let detect = HdrTransfer::detect;
+        assert_eq!(
+            detect(TransferCharacteristic::SMPTE2084),
+            Some(HdrTransfer::Pq)
+        );
+        assert_eq!(
+            detect(TransferCharacteristic::ARIB_STD_B67),
+            Some(HdrTransfer::Hlg)
+        );
+        assert_eq!(detect(TransferCharacteristic::BT709), None);
+        assert_eq!(detect(TransferCharacteristic::Unspecified), None);
+    }
+
+    #[test]
+    fn pq_decodes_to_absolute_nits() {
+        let pq = HdrTransfer::Pq;
+        assert_eq!(pq.to_linear(0.0), 0.0);
+        // Full signal is 10000 nits, the PQ code for 203 nits is SDR white
+        assert!(close(
+            pq.to_linear(1.0),
+            10000.0 / REFERENCE_WHITE_NITS,
+            1e-3
+        ));
+        assert!(close(pq.to_linear(0.5807), 1.0, 0.01));
+        // Around 100 nits
+        assert!(close(
+            pq.to_linear(0.5081),
+            100.0 / REFERENCE_WHITE_NITS,
+            0.01
+        ));
+    }
+
+    #[test]
+    fn hlg_peaks_at_the_assumed_display() {
+        let hlg = HdrTransfer::Hlg;
+        assert_eq!(hlg.to_linear(0.0), 0.0);
+        assert!(close(
+            hlg.to_linear(1.0),
+            PEAK_NITS / REFERENCE_WHITE_NITS,
+            0.01
+        ));
+        // The square-law and log halves of the curve meet at 0.5
+        let below = hlg.to_linear(0.5 - 1e-4);
+        let above = hlg.to_linear(0.5 + 1e-4);
+        assert!((above - below).abs() < 1e-3);
+    }
+
+    #[test]
+    fn transfers_are_monotonic() {
+        for transfer in [HdrTransfer::Pq, HdrTransfer::Hlg] {
+            let light: Vec<f32> = (0..=100)
+                .map(|i| transfer.to_linear(i as f32 / 100.0))
+                .collect();
+            assert!(light.windows(2).all(|w| w[0] < w[1]), "{:?}", transfer);
+        }
+    }
+
+    #[test]
+    fn display_table_maps_black_to_black_and_peak_to_white() {
+        let mapper = ToneMapper::new(HdrTransfer::Pq);
+        assert_eq!(mapper.to_linear.len(), 1 << 16);
+        assert_eq!(mapper.to_display.len(), DISPLAY_LUT_SIZE);
+        assert_eq!(mapper.to_display[0], 0);
+        assert_eq!(mapper.to_display[DISPLAY_LUT_SIZE - 1], 255);
+        assert!(mapper.to_display.windows(2).all(|w| w[0] <= w[1]));
+        // SDR white keeps well inside the range, leaving room for highlights
+        let peak = PEAK_NITS / REFERENCE_WHITE_NITS;
+        let white = mapper.to_display[(DISPLAY_LUT_SIZE as f32 / peak) as usize];
+        assert!((150..250).contains(&white), "SDR white at {}", white);
+    }
+}
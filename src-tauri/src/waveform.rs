@@ -0,0 +1,273 @@
+use crate::decoder::{media_start_time, open_probed, source_channel_layout};
+use crate::error::PlayerError;
+use crate::timebase::{micros_to_seconds, pts_to_seconds, seconds_to_micros};
+use anyhow::Result;
+use crossbeam_channel::{bounded, unbounded, Sender};
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Most buckets one window may be split into
+pub const MAX_WAVEFORM_BUCKETS: u32 = 16384;
+/// Windows kept per file, so zooming back and forth does not decode again
+const WAVEFORM_CACHE_WINDOWS: usize = 32;
+
+/// Peak levels of one time window, for zoomed waveform views
+#[derive(Clone, Debug, Serialize)]
+pub struct WaveformWindow {
+    /// The window in seconds, after clamping to the media duration
+    pub start: f64,
+    pub end: f64,
+    /// Lowest and highest sample (-1.0..=1.0) of each bucket over all channels;
+    /// buckets without decoded audio are 0.0
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+struct WaveformRequest {
+    start: f64,
+    end: f64,
+    buckets: u32,
+    reply: Sender<Result<WaveformWindow, PlayerError>>,
+}
+
+/// Secondary audio decoder kept open for the loaded file.
+///
+/// Like the thumbnailer, it owns its own input context on a dedicated thread,
+/// so windows are decoded without touching playback. The thread exits when
+/// the last handle is dropped.
+#[derive(Clone)]
+pub struct Waveform {
+    path: String,
+    request_sender: Sender<WaveformRequest>,
+}
+
+impl Waveform {
+    /// Open `path` on a new waveform thread
+    pub fn open(path: &str) -> Result<Self> {
+        let (request_sender, request_receiver) = unbounded::<WaveformRequest>();
+        let (ready_tx, ready_rx) = bounded::<Result<(), PlayerError>>(1);
+        let thread_path = path.to_string();
+
+        std::thread::spawn(move || {
+            let mut source = match WaveformSource::open(&thread_path) {
+                Ok(source) => {
+                    let _ = ready_tx.send(Ok(()));
+                    source
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            while let Ok(request) = request_receiver.recv() {
+                let window = source.window(request.start, request.end, request.buckets);
+                let _ = request.reply.send(window);
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                path: path.to_string(),
+                request_sender,
+            }),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(anyhow::anyhow!("Waveform thread closed")),
+        }
+    }
+
+    /// Path of the file this decoder serves
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Peaks of `start..end` seconds in `buckets` buckets; the window is
+    /// clamped to the duration and recent windows are served from a cache
+    pub fn window(&self, start: f64, end: f64, buckets: u32) -> Result<WaveformWindow> {
+        let (reply, response) = bounded(1);
+        self.request_sender
+            .send(WaveformRequest {
+                start,
+                end,
+                buckets,
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("Waveform thread closed"))?;
+
+        match response.recv() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(anyhow::anyhow!("Waveform thread closed")),
+        }
+    }
+}
+
+/// Input, decoder and resampler owned by the waveform thread
+struct WaveformSource {
+    input: ffmpeg::format::context::Input,
+    decoder: ffmpeg::decoder::Audio,
+    resampler: ffmpeg::software::resampling::context::Context,
+    stream_index: usize,
+    time_base: ffmpeg::Rational,
+    start_time: i64,
+    duration: f64,
+    rate: u32,
+    /// Recent windows by (start, end, buckets), newest last
+    cache: VecDeque<((u64, u64, u32), WaveformWindow)>,
+}
+
+impl WaveformSource {
+    fn open(path: &str) -> Result<Self, PlayerError> {
+        let _ = ffmpeg::init();
+        let input = open_probed(path)?;
+
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .ok_or_else(|| PlayerError::Unsupported("No audio stream".to_string()))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        let mut decoder_context = ffmpeg::codec::Context::new();
+        decoder_context.set_parameters(stream.parameters())?;
+        let decoder = decoder_context.decoder().audio()?;
+
+        // Keep the source rate and channels, only convert to planar float
+        let rate = decoder.rate();
+        let channels = decoder.channels();
+        let layout = source_channel_layout(decoder.channel_layout(), channels);
+        let resampler = ffmpeg::software::resampling::context::Context::get(
+            decoder.format(),
+            layout,
+            rate,
+            ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Planar),
+            layout,
+            rate,
+        )?;
+        let start_time = media_start_time(&input);
+        let duration = micros_to_seconds(input.duration()).max(0.0);
+
+        Ok(Self {
+            input,
+            decoder,
+            resampler,
+            stream_index,
+            time_base,
+            start_time,
+            duration,
+            rate,
+            cache: VecDeque::new(),
+        })
+    }
+
+    fn window(
+        &mut self,
+        start: f64,
+        end: f64,
+        buckets: u32,
+    ) -> Result<WaveformWindow, PlayerError> {
+        if !start.is_finite() || !end.is_finite() {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Invalid waveform window {}..{}",
+                start, end
+            )));
+        }
+        if !(1..=MAX_WAVEFORM_BUCKETS).contains(&buckets) {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Waveform buckets must be 1..={}, got {}",
+                MAX_WAVEFORM_BUCKETS, buckets
+            )));
+        }
+        // Windows reaching past the end (or the start) cover what exists
+        let start = start.max(0.0);
+        let end = if self.duration > 0.0 {
+            end.min(self.duration)
+        } else {
+            end
+        };
+        if end <= start {
+            return Err(PlayerError::InvalidArgument(format!(
+                "Empty waveform window {:.3}..{:.3}",
+                start, end
+            )));
+        }
+
+        let key = (start.to_bits(), end.to_bits(), buckets);
+        if let Some(i) = self.cache.iter().position(|(k, _)| *k == key) {
+            let entry = self.cache.remove(i).unwrap();
+            let window = entry.1.clone();
+            self.cache.push_back(entry);
+            return Ok(window);
+        }
+
+        let window = self.decode(start, end, buckets as usize)?;
+        if self.cache.len() >= WAVEFORM_CACHE_WINDOWS {
+            self.cache.pop_front();
+        }
+        self.cache.push_back((key, window.clone()));
+        Ok(window)
+    }
+
+    /// Decode `start..end` and reduce it to min/max per bucket
+    fn decode(&mut self, start: f64, end: f64, buckets: usize) -> Result<WaveformWindow> {
+        let timestamp = seconds_to_micros(start, self.start_time);
+        self.input.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+
+        let mut min = vec![f32::INFINITY; buckets];
+        let mut max = vec![f32::NEG_INFINITY; buckets];
+        let bucket_seconds = (end - start) / buckets as f64;
+        let mut frame = ffmpeg::frame::Audio::empty();
+        let mut resampled = ffmpeg::frame::Audio::empty();
+
+        'packets: for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index || self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+
+            while self.decoder.receive_frame(&mut frame).is_ok() {
+                let Some(pts) = frame.timestamp() else {
+                    continue;
+                };
+                let frame_start = pts_to_seconds(pts, self.time_base, self.start_time);
+                if frame.channel_layout().is_empty() {
+                    let layout = source_channel_layout(frame.channel_layout(), frame.channels());
+                    frame.set_channel_layout(layout);
+                }
+                if self.resampler.run(&frame, &mut resampled).is_err() {
+                    continue;
+                }
+
+                let planes: Vec<&[f32]> = (0..resampled.planes())
+                    .map(|ch| resampled.plane::<f32>(ch))
+                    .collect();
+                let first = ((start - frame_start) * self.rate as f64).ceil().max(0.0) as usize;
+                for i in first..resampled.samples() {
+                    let time = frame_start + i as f64 / self.rate as f64;
+                    if time >= end {
+                        break 'packets;
+                    }
+                    let bucket = (((time - start) / bucket_seconds) as usize).min(buckets - 1);
+                    for plane in &planes {
+                        min[bucket] = min[bucket].min(plane[i]);
+                        max[bucket] = max[bucket].max(plane[i]);
+                    }
+                }
+            }
+        }
+
+        // Buckets no sample fell into (gaps, or fewer samples than buckets)
+        for (low, high) in min.iter_mut().zip(max.iter_mut()) {
+            if *low > *high {
+                *low = 0.0;
+                *high = 0.0;
+            }
+        }
+        Ok(WaveformWindow {
+            start,
+            end,
+            min,
+            max,
+        })
+    }
+}
@@ -16,10 +16,36 @@ let videoContext: CanvasRenderingContext2D | null = null;
 interface VideoFrameData {
   width: number;
   height: number;
-  data: number[]; // RGBA bytes
+  data: number[]; // 按 format 紧密排列的像素字节（无行填充）
+  format: 'Rgba' | 'Bgra' | 'Rgb24';
   timestamp: number;
 }
 
+// 将帧数据转换为画布所需的 RGBA
+function toRgba(frameData: VideoFrameData): Uint8ClampedArray {
+  const pixels = frameData.width * frameData.height;
+  if (frameData.format === 'Bgra') {
+    const rgba = new Uint8ClampedArray(frameData.data);
+    for (let i = 0; i < rgba.length; i += 4) {
+      const b = rgba[i];
+      rgba[i] = rgba[i + 2];
+      rgba[i + 2] = b;
+    }
+    return rgba;
+  }
+  if (frameData.format === 'Rgb24') {
+    const rgba = new Uint8ClampedArray(pixels * 4);
+    for (let p = 0; p < pixels; p++) {
+      rgba[p * 4] = frameData.data[p * 3];
+      rgba[p * 4 + 1] = frameData.data[p * 3 + 1];
+      rgba[p * 4 + 2] = frameData.data[p * 3 + 2];
+      rgba[p * 4 + 3] = 255;
+    }
+    return rgba;
+  }
+  return new Uint8ClampedArray(frameData.data);
+}
+
 // 渲染视频帧
 function renderVideoFrame(frameData: VideoFrameData) {
   if (!videoCanvas || !videoContext) {
@@ -42,7 +68,7 @@ function renderVideoFrame(frameData: VideoFrameData) {
 
   // 创建 ImageData 并绘制
   const imageData = new ImageData(
-    new Uint8ClampedArray(frameData.data),
+    toRgba(frameData),
     frameData.width,
     frameData.height
   );